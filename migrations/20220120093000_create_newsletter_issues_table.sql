@@ -0,0 +1,22 @@
+-- Add migration script here
+CREATE TABLE newsletter_issues (
+    newsletter_issue_id uuid NOT NULL,
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    published_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    -- Only set when the issue A/B tests its subject lines
+    subject_test_metric TEXT NULL,
+    subject_test_sample_percentage SMALLINT NULL,
+    subject_test_decide_at TIMESTAMP WITH TIME ZONE NULL,
+    winning_variant SMALLINT NULL,
+    PRIMARY KEY(newsletter_issue_id)
+);
+
+CREATE TABLE newsletter_issue_variants (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    variant SMALLINT NOT NULL,
+    subject TEXT NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, variant)
+);
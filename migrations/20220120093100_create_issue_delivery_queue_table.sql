@@ -0,0 +1,9 @@
+-- Add migration script here
+CREATE TABLE issue_delivery_queue (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id),
+    variant SMALLINT NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, subscriber_id)
+);
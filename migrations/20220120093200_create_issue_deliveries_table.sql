@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE issue_deliveries (
+    delivery_id uuid NOT NULL,
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    -- Deliveries outlive their subscriber to keep the issue statistics accurate
+    subscriber_id uuid NULL
+        REFERENCES subscriptions (id) ON DELETE SET NULL,
+    variant SMALLINT NOT NULL,
+    delivered_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    opened_at TIMESTAMP WITH TIME ZONE NULL,
+    clicked_at TIMESTAMP WITH TIME ZONE NULL,
+    PRIMARY KEY(delivery_id)
+);
+
+CREATE INDEX issue_deliveries_newsletter_issue_id_idx ON issue_deliveries (newsletter_issue_id);
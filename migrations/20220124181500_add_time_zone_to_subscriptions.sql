@@ -0,0 +1,3 @@
+-- Add migration script here
+-- IANA name, e.g. `Europe/Berlin`. NULL falls back to the newsletter default time zone.
+ALTER TABLE subscriptions ADD COLUMN time_zone TEXT NULL;
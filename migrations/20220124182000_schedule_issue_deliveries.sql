@@ -0,0 +1,7 @@
+-- Add migration script here
+-- Wall clock time at which each subscriber should receive the issue, NULL to send right away
+ALTER TABLE newsletter_issues ADD COLUMN local_send_time TIME NULL;
+-- Tasks are only picked up by the delivery worker once `execute_after` has passed
+ALTER TABLE issue_delivery_queue
+    ADD COLUMN execute_after TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now();
+CREATE INDEX issue_delivery_queue_execute_after_idx ON issue_delivery_queue (execute_after);
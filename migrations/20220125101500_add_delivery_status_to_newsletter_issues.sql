@@ -0,0 +1,8 @@
+-- Add migration script here
+-- Delivery workers only pick up tasks of issues that are `sending`
+ALTER TABLE newsletter_issues
+    ADD COLUMN delivery_status TEXT NOT NULL DEFAULT 'sending'
+    CHECK (delivery_status IN ('sending', 'paused', 'cancelled'));
+-- A correction is only sent to the recipients of the issue it corrects
+ALTER TABLE newsletter_issues
+    ADD COLUMN corrects_issue_id uuid NULL REFERENCES newsletter_issues (newsletter_issue_id);
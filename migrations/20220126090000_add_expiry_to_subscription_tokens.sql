@@ -0,0 +1,7 @@
+-- Add migration script here
+-- Tokens issued before this migration get a day to be used
+ALTER TABLE subscription_tokens
+    ADD COLUMN created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    ADD COLUMN expires_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now() + interval '1 day';
+ALTER TABLE subscription_tokens ALTER COLUMN expires_at DROP DEFAULT;
+CREATE INDEX subscription_tokens_expires_at_idx ON subscription_tokens (expires_at);
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Used tokens are kept until they expire, so that a repeated confirmation can be recognised
+ALTER TABLE subscription_tokens ADD COLUMN consumed_at TIMESTAMP WITH TIME ZONE NULL;
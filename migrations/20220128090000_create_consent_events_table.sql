@@ -0,0 +1,30 @@
+-- Add migration script here
+-- Proof of how and when each subscriber consented
+CREATE TABLE consent_events(
+    consent_event_id uuid PRIMARY KEY,
+    -- Deleting a subscriber never takes their consent history along, see the trigger below
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE RESTRICT,
+    action TEXT NOT NULL CHECK (action IN ('subscribe', 'confirm')),
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    ip_address TEXT NULL,
+    user_agent TEXT NULL,
+    source TEXT NOT NULL,
+    consent_version TEXT NOT NULL
+);
+CREATE INDEX consent_events_subscriber_id_idx ON consent_events (subscriber_id);
+
+-- The trail is append-only: recorded events can never be rewritten, and they are only removed
+-- by an erasure, which flags its transaction with `zero2prod.erasing_subscriber`
+CREATE FUNCTION reject_consent_event_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' AND current_setting('zero2prod.erasing_subscriber', true) = 'on' THEN
+        RETURN OLD;
+    END IF;
+    RAISE EXCEPTION 'consent_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER consent_events_append_only
+    BEFORE UPDATE OR DELETE ON consent_events
+    FOR EACH ROW EXECUTE FUNCTION reject_consent_event_change();
@@ -0,0 +1,7 @@
+-- Add migration script here
+-- Addresses that must never be added back, stored as a SHA-256 hash of the address
+CREATE TABLE suppressions(
+    email_hash TEXT PRIMARY KEY,
+    reason TEXT NOT NULL,
+    suppressed_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
@@ -0,0 +1,33 @@
+-- Add migration script here
+-- Canonical form of the address, `email` keeps the form the subscriber typed
+ALTER TABLE subscriptions ADD COLUMN normalized_email TEXT NULL;
+
+-- Backfill: trim and lowercase the domain. Internationalized domains of existing rows are
+-- lowercased but not converted to punycode, the application does it for new subscribers.
+UPDATE subscriptions
+    SET normalized_email =
+        left(trim(email), length(trim(email)) - strpos(reverse(trim(email)), '@'))
+        || '@'
+        || lower(right(trim(email), strpos(reverse(trim(email)), '@') - 1));
+
+-- Refuse to go on while the same address is stored more than once, they have to be merged by hand
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(format('%s (%s subscribers)', address, occurrences), ', ')
+    INTO duplicates
+    FROM (
+        SELECT lower(normalized_email) AS address, COUNT(*) AS occurrences
+        FROM subscriptions
+        GROUP BY lower(normalized_email)
+        HAVING COUNT(*) > 1
+    ) d;
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION 'Duplicate subscribers found, merge them before migrating: %', duplicates;
+    END IF;
+END $$;
+
+ALTER TABLE subscriptions ALTER COLUMN normalized_email SET NOT NULL;
+CREATE UNIQUE INDEX subscriptions_normalized_email_key ON subscriptions (lower(normalized_email));
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Labels attached to subscribers, e.g. carried over from an imported list
+CREATE TABLE tags(
+    tag_id uuid PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE subscription_tags(
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    tag_id uuid NOT NULL
+        REFERENCES tags (tag_id) ON DELETE CASCADE,
+    PRIMARY KEY (subscriber_id, tag_id)
+);
@@ -0,0 +1,5 @@
+-- Add migration script here
+-- Imported subscribers consented through another tool, the import itself is recorded in their trail
+ALTER TABLE consent_events DROP CONSTRAINT consent_events_action_check;
+ALTER TABLE consent_events ADD CONSTRAINT consent_events_action_check
+    CHECK (action IN ('subscribe', 'confirm', 'import'));
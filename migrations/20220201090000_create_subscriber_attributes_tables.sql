@@ -0,0 +1,22 @@
+-- Add migration script here
+-- Custom fields defined by the admins, e.g. company or role
+CREATE TABLE subscriber_attributes(
+    attribute_id uuid PRIMARY KEY,
+    key TEXT NOT NULL UNIQUE,
+    label TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('text', 'number', 'date', 'enum', 'boolean')),
+    -- Allowed values of an `enum` attribute
+    options TEXT[] NOT NULL DEFAULT '{}',
+    required BOOLEAN NOT NULL DEFAULT false,
+    created_at timestamptz NOT NULL
+);
+
+-- Values are stored in their normalized text form, see `AttributeKind::normalize`
+CREATE TABLE subscriber_attribute_values(
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    attribute_id uuid NOT NULL
+        REFERENCES subscriber_attributes (attribute_id) ON DELETE CASCADE,
+    value TEXT NOT NULL,
+    PRIMARY KEY (subscriber_id, attribute_id)
+);
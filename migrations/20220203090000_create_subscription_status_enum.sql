@@ -0,0 +1,34 @@
+-- Add migration script here
+-- The status of a subscriber can only take the values of the lifecycle
+CREATE TYPE subscription_status AS ENUM (
+    'pending_confirmation',
+    'confirmed',
+    'unsubscribed',
+    'bounced',
+    'complained',
+    'cleaned'
+);
+
+-- Rows with a status the application never understood were never sent anything
+UPDATE subscriptions
+    SET status = 'cleaned'
+    WHERE status NOT IN ('pending_confirmation', 'confirmed', 'unsubscribed');
+ALTER TABLE subscriptions
+    ALTER COLUMN status TYPE subscription_status USING status::subscription_status;
+
+-- Every status a subscriber went through, see `change_status`
+CREATE TABLE subscription_events(
+    subscription_event_id uuid PRIMARY KEY,
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    -- NULL when the subscriber was created
+    from_status subscription_status NULL,
+    to_status subscription_status NOT NULL,
+    reason TEXT NOT NULL,
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+CREATE INDEX subscription_events_subscriber_id_idx ON subscription_events (subscriber_id, occurred_at);
+
+-- Existing subscribers start their history in their current status
+INSERT INTO subscription_events (subscription_event_id, subscriber_id, from_status, to_status, reason, occurred_at)
+    SELECT uuid_generate_v4(), id, NULL, status, 'migration', subscribed_at FROM subscriptions;
@@ -0,0 +1,24 @@
+-- Add migration script here
+-- The email sent once a subscriber confirms, edited by admins. A single row holds it.
+CREATE TABLE welcome_email(
+    welcome_email_id BOOLEAN PRIMARY KEY DEFAULT true CHECK (welcome_email_id),
+    enabled BOOLEAN NOT NULL,
+    subject TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    -- Append the most recent issue, so that new readers get something to read right away
+    include_latest_issue BOOLEAN NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+INSERT INTO welcome_email (enabled, subject, text_content, html_content, include_latest_issue, updated_at)
+VALUES (
+    false,
+    'Welcome aboard, {{ name | reader }}!',
+    E'Hi {{ name | there }},\n\nThanks for confirming your subscription. The next issue will land in your inbox.',
+    '<p>Hi {{ name | there }},</p><p>Thanks for confirming your subscription. The next issue will land in your inbox.</p>',
+    false,
+    now()
+);
+
+-- Set once the welcome email went out, so that it is never sent twice
+ALTER TABLE subscriptions ADD COLUMN welcome_sent_at TIMESTAMP WITH TIME ZONE NULL;
@@ -0,0 +1,55 @@
+-- Add migration script here
+-- Drip sequences: emails sent to subscribers a number of days after they enrolled
+CREATE TABLE sequences(
+    sequence_id uuid PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    -- Subscribers are enrolled when they confirm, or when they are given `trigger_tag`
+    trigger TEXT NOT NULL CHECK (trigger IN ('confirmation', 'tag')),
+    trigger_tag TEXT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    CHECK ((trigger = 'tag') = (trigger_tag IS NOT NULL))
+);
+
+CREATE TABLE sequence_steps(
+    sequence_id uuid NOT NULL
+        REFERENCES sequences (sequence_id) ON DELETE CASCADE,
+    position SMALLINT NOT NULL,
+    -- Counted from the enrollment, steps never go back in time
+    delay_days INTEGER NOT NULL CHECK (delay_days >= 0),
+    subject TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    -- The step is skipped for subscribers who do not meet the condition
+    condition_kind TEXT NULL CHECK (condition_kind IN ('attribute', 'engaged', 'not_engaged')),
+    condition_key TEXT NULL,
+    condition_value TEXT NULL,
+    PRIMARY KEY (sequence_id, position)
+);
+
+CREATE TABLE sequence_enrollments(
+    enrollment_id uuid PRIMARY KEY,
+    sequence_id uuid NOT NULL
+        REFERENCES sequences (sequence_id) ON DELETE CASCADE,
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    status TEXT NOT NULL CHECK (status IN ('active', 'completed', 'stopped')),
+    enrolled_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    -- Position of the next step and when it is due, while active
+    next_position SMALLINT NOT NULL,
+    next_step_at TIMESTAMP WITH TIME ZONE NULL,
+    finished_at TIMESTAMP WITH TIME ZONE NULL,
+    -- A subscriber goes through a sequence once
+    UNIQUE (sequence_id, subscriber_id)
+);
+CREATE INDEX sequence_enrollments_due_idx ON sequence_enrollments (next_step_at) WHERE status = 'active';
+CREATE INDEX sequence_enrollments_subscriber_id_idx ON sequence_enrollments (subscriber_id);
+
+-- What happened at each step of each enrollment, for the per-step statistics
+CREATE TABLE sequence_step_runs(
+    enrollment_id uuid NOT NULL
+        REFERENCES sequence_enrollments (enrollment_id) ON DELETE CASCADE,
+    position SMALLINT NOT NULL,
+    outcome TEXT NOT NULL CHECK (outcome IN ('sent', 'skipped', 'failed')),
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (enrollment_id, position)
+);
@@ -0,0 +1,30 @@
+-- Add migration script here
+-- Confirmed subscribers share their code, subscribers who sign up through it are credited to them once they confirm
+ALTER TABLE subscriptions ADD COLUMN referral_code TEXT NULL UNIQUE;
+ALTER TABLE subscriptions ADD COLUMN referred_by uuid NULL
+    REFERENCES subscriptions (id) ON DELETE SET NULL;
+ALTER TABLE subscriptions ADD COLUMN referral_credited_at TIMESTAMP WITH TIME ZONE NULL;
+CREATE INDEX subscriptions_referred_by_idx ON subscriptions (referred_by) WHERE referred_by IS NOT NULL;
+
+UPDATE subscriptions
+SET referral_code = substr(md5(random()::text || id::text), 1, 10)
+WHERE status = 'confirmed';
+
+-- Emails sent to referrers once their credited referrals reach the milestone
+CREATE TABLE referral_rewards(
+    milestone INTEGER PRIMARY KEY CHECK (milestone > 0),
+    subject TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- Each reward is sent once to each referrer
+CREATE TABLE referral_rewards_sent(
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    milestone INTEGER NOT NULL
+        REFERENCES referral_rewards (milestone) ON DELETE CASCADE,
+    sent_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (subscriber_id, milestone)
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+-- Sends that failed for good, so that the remainder of a subject test does not send the issue to them again
+CREATE TABLE issue_delivery_failures(
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    failed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_id)
+);
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- The admin subscriber list pages through each sort column with the id breaking ties
+CREATE INDEX subscriptions_subscribed_at_idx ON subscriptions (subscribed_at, id);
+CREATE INDEX subscriptions_lower_email_idx ON subscriptions (lower(email), id);
+CREATE INDEX subscriptions_lower_name_idx ON subscriptions (lower(name), id);
+CREATE INDEX subscriptions_status_idx ON subscriptions (status, id);
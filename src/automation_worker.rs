@@ -0,0 +1,302 @@
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+use crate::configuration::settings::Settings;
+use crate::domain::automation::{SequenceStep, StepOutcome};
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::email::email_client::EmailClient;
+use crate::email::footer::with_preferences_footer;
+use crate::email::message_options::MessageOptions;
+use crate::errors::send_email_error::SendEmailError;
+use crate::helpers::automation::get_steps;
+use crate::helpers::preferences::preferences_url;
+use crate::helpers::subscriber_attributes::get_merge_values;
+use crate::issue_delivery_worker::ExecutionOutcome;
+use crate::startup::application::get_connection_pool;
+
+struct DueEnrollment {
+    enrollment_id: Uuid,
+    sequence_id: Uuid,
+    subscriber_id: Uuid,
+    subscriber_email: String,
+    confirmed: bool,
+    enrolled_at: DateTime<Utc>,
+    next_position: i16,
+}
+
+
+pub async fn run_automation_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    let email_client = configuration.email_client.client();
+
+    worker_loop(
+        connection_pool,
+        email_client,
+        configuration.application.base_url,
+        configuration.application.hmac_secret,
+    ).await
+}
+
+
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: String,
+    hmac_secret: Secret<String>,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_execute_step(&pool, &email_client, &base_url, &hmac_secret).await {
+            Ok(ExecutionOutcome::EmptyQueue) | Ok(ExecutionOutcome::ProviderUnavailable) => {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+
+/// Run the next due step of an enrollment and schedule the one after.
+///
+/// The enrollment stays locked until the step is recorded, so that each step runs once
+/// even with several workers.
+#[tracing::instrument(
+    skip_all,
+    fields(
+        enrollment_id=tracing::field::Empty,
+        position=tracing::field::Empty,
+    ),
+    err
+)]
+pub async fn try_execute_step(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let (mut transaction, enrollment) = match dequeue_enrollment(pool).await? {
+        Some(due) => due,
+        None => return Ok(ExecutionOutcome::EmptyQueue),
+    };
+
+    Span::current()
+        .record("enrollment_id", &display(enrollment.enrollment_id))
+        .record("position", &display(enrollment.next_position));
+
+    // Unsubscribed, bounced and other subscribers who left do not get the rest of the sequence
+    if !enrollment.confirmed {
+        finish_enrollment(&mut transaction, enrollment.enrollment_id, "stopped").await?;
+        transaction.commit().await?;
+        return Ok(ExecutionOutcome::TaskCompleted);
+    }
+
+    let steps = get_steps(pool, enrollment.sequence_id).await?;
+    let step = match steps.iter().find(|step| step.position == enrollment.next_position) {
+        Some(step) => step,
+        None => {
+            finish_enrollment(&mut transaction, enrollment.enrollment_id, "completed").await?;
+            transaction.commit().await?;
+            return Ok(ExecutionOutcome::TaskCompleted);
+        }
+    };
+
+    let merge_values = get_merge_values(pool, enrollment.subscriber_id).await?;
+    let meets_condition = match &step.condition {
+        Some(condition) => condition.matches(&merge_values, is_engaged(pool, enrollment.subscriber_id).await?),
+        None => true,
+    };
+
+    let outcome = if !meets_condition {
+        StepOutcome::Skipped
+    } else {
+        match SubscriberEmail::parse(enrollment.subscriber_email.clone()) {
+            Ok(email) => {
+                match send_step(email_client, &email, &enrollment, step, &merge_values, base_url, hmac_secret).await {
+                    Ok(()) => StepOutcome::Sent,
+                    Err(SendEmailError::CircuitOpen) => {
+                        // Dropping the transaction releases the enrollment for a later attempt
+                        return Ok(ExecutionOutcome::ProviderUnavailable);
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            error.cause_chain = ?e,
+                            error.message = %e,
+                            "Failed to send a sequence step. Moving on to the next step.",
+                        );
+                        StepOutcome::Failed
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!(
+                    error.message = %e,
+                    "Skipping a sequence step. The stored contact details of the subscriber are invalid",
+                );
+                StepOutcome::Failed
+            }
+        }
+    };
+
+    record_step_run(&mut transaction, enrollment.enrollment_id, step.position, outcome).await?;
+    match steps.iter().find(|next| next.position > step.position) {
+        Some(next) => {
+            let next_step_at = enrollment.enrolled_at + chrono::Duration::days(next.delay_days.into());
+            schedule_step(&mut transaction, enrollment.enrollment_id, next.position, next_step_at).await?;
+        }
+        None => finish_enrollment(&mut transaction, enrollment.enrollment_id, "completed").await?,
+    }
+    transaction.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+
+async fn send_step(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    enrollment: &DueEnrollment,
+    step: &SequenceStep,
+    merge_values: &std::collections::HashMap<String, String>,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<(), SendEmailError> {
+    let (subject, html_content, text_content) = step.render(merge_values);
+    let (html_content, text_content) = with_preferences_footer(
+        &html_content,
+        &text_content,
+        &preferences_url(base_url, hmac_secret, enrollment.subscriber_id),
+    );
+
+    let mut options = MessageOptions::broadcast();
+    options.tag = Some("sequence".into());
+    options.metadata.insert("sequence_id".into(), enrollment.sequence_id.to_string());
+    options.metadata.insert("step".into(), step.position.to_string());
+    options.metadata.insert("subscriber_id".into(), enrollment.subscriber_id.to_string());
+
+    email_client
+        .send_email(email, &subject, &html_content, &text_content, &[], &options)
+        .await
+}
+
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_enrollment(pool: &PgPool) -> Result<Option<(PgTransaction, DueEnrollment)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let enrollment = sqlx::query_as!(
+        DueEnrollment,
+        r#"
+        SELECT
+            e.enrollment_id,
+            e.sequence_id,
+            e.subscriber_id,
+            s.email AS subscriber_email,
+            s.status = 'confirmed' AS "confirmed!",
+            e.enrolled_at,
+            e.next_position
+        FROM sequence_enrollments e
+        JOIN subscriptions s ON s.id = e.subscriber_id
+        WHERE e.status = 'active' AND e.next_step_at <= now()
+        ORDER BY e.next_step_at
+        FOR UPDATE OF e
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+        .fetch_optional(&mut transaction)
+        .await?;
+
+    Ok(enrollment.map(|e| (transaction, e)))
+}
+
+
+/// Whether the subscriber ever opened or clicked through an issue.
+#[tracing::instrument(skip(pool))]
+async fn is_engaged(pool: &PgPool, subscriber_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM issue_deliveries
+            WHERE subscriber_id = $1 AND (opened_at IS NOT NULL OR clicked_at IS NOT NULL)
+        ) AS "engaged!"
+        "#,
+        subscriber_id,
+    )
+        .fetch_one(pool)
+        .await?;
+
+    Ok(row.engaged)
+}
+
+
+#[tracing::instrument(skip(transaction))]
+async fn record_step_run(
+    transaction: &mut PgTransaction,
+    enrollment_id: Uuid,
+    position: i16,
+    outcome: StepOutcome,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO sequence_step_runs (enrollment_id, position, outcome, occurred_at)
+        VALUES ($1, $2, $3, now())
+        ON CONFLICT (enrollment_id, position) DO NOTHING
+        "#,
+        enrollment_id,
+        position,
+        outcome.as_str(),
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(skip(transaction))]
+async fn schedule_step(
+    transaction: &mut PgTransaction,
+    enrollment_id: Uuid,
+    position: i16,
+    next_step_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE sequence_enrollments SET next_position = $2, next_step_at = $3 WHERE enrollment_id = $1"#,
+        enrollment_id,
+        position,
+        next_step_at,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(skip(transaction))]
+async fn finish_enrollment(
+    transaction: &mut PgTransaction,
+    enrollment_id: Uuid,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE sequence_enrollments
+        SET status = $2, next_step_at = NULL, finished_at = now()
+        WHERE enrollment_id = $1
+        "#,
+        enrollment_id,
+        status,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
@@ -3,3 +3,9 @@ pub mod email_settings;
 pub mod database_settings;
 pub mod environment;
 pub mod settings;
+pub mod newsletter_settings;
+
+pub mod subscription_settings;
+pub mod email_policy_settings;
+pub mod bot_protection_settings;
+pub mod rate_limit_settings;
\ No newline at end of file
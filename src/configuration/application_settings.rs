@@ -1,6 +1,9 @@
+use ipnet::IpNet;
 use secrecy::Secret;
 use serde_aux::field_attributes::deserialize_number_from_string;
 
+use crate::configuration::bot_protection_settings::BotProtectionSettings;
+
 #[derive(serde::Deserialize, Clone)]
 pub struct ApplicationSettings {
     #[serde(deserialize_with = "deserialize_number_from_string")]
@@ -8,6 +11,11 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    /// Protection of the public signup form against bots.
+    pub bot_protection: BotProtectionSettings,
+    /// Networks of the proxies in front of the application, whose `X-Forwarded-For` header is believed.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
 }
 
 
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use secrecy::Secret;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+use crate::configuration::rate_limit_settings::RateLimitBackend;
+use crate::domain::bot_protection::{
+    BotProtection, FormTokenStore, InMemoryFormTokenStore, RedisFormTokenStore, StubCaptchaVerifier,
+};
+
+#[derive(serde::Deserialize, Clone)]
+pub struct BotProtectionSettings {
+    /// Signups sent sooner than this after the form was served are rejected, 0 disables the form token.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_form_fill_seconds: u32,
+    /// How long a served signup form can be submitted.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_form_age_seconds: u32,
+    /// Leading zero bits the browser has to find before submitting, 0 disables the proof of work.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub proof_of_work_difficulty: u8,
+    /// Where submitted form tokens are remembered, so that each form is accepted once.
+    pub used_form_tokens: RateLimitBackend,
+    /// Local development and tests only: require this exact CAPTCHA response.
+    #[serde(default)]
+    pub captcha_stub_response: Option<Secret<String>>,
+}
+
+impl BotProtectionSettings {
+    /// The protection described by the settings. A real CAPTCHA provider is plugged in with
+    /// `BotProtection::with_captcha_verifier`.
+    pub async fn protection(
+        &self,
+        hmac_secret: &Secret<String>,
+        redis_uri: &Secret<String>,
+    ) -> Result<BotProtection, anyhow::Error> {
+        let form_tokens: Arc<dyn FormTokenStore> = match self.used_form_tokens {
+            RateLimitBackend::Redis => Arc::new(RedisFormTokenStore::connect(redis_uri).await?),
+            RateLimitBackend::Memory => Arc::new(InMemoryFormTokenStore::default()),
+        };
+        let protection = BotProtection::new(
+            hmac_secret.clone(),
+            chrono::Duration::seconds(self.min_form_fill_seconds as i64),
+            chrono::Duration::seconds(self.max_form_age_seconds as i64),
+            self.proof_of_work_difficulty,
+        )
+            .with_form_token_store(form_tokens);
+        Ok(match &self.captcha_stub_response {
+            Some(response) => protection.with_captcha_verifier(Arc::new(StubCaptchaVerifier::new(response.clone()))),
+            None => protection,
+        })
+    }
+}
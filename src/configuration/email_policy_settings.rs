@@ -0,0 +1,26 @@
+use crate::domain::email_policy::EmailPolicy;
+
+#[derive(serde::Deserialize, Clone)]
+pub struct EmailPolicySettings {
+    /// Domains exempt from the deny and disposable lists.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    #[serde(default)]
+    pub denied_domains: Vec<String>,
+    /// Reject the bundled list of disposable mailbox providers.
+    pub reject_disposable_domains: bool,
+    /// Reject addresses such as `postmaster@` that do not belong to a person.
+    pub reject_role_addresses: bool,
+}
+
+impl EmailPolicySettings {
+    /// The policy described by the settings, without MX lookups.
+    pub fn policy(&self) -> EmailPolicy {
+        let policy = EmailPolicy::new(&self.allowed_domains, &self.denied_domains, self.reject_role_addresses);
+        if self.reject_disposable_domains {
+            policy.with_disposable_domains()
+        } else {
+            policy
+        }
+    }
+}
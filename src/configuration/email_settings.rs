@@ -1,11 +1,33 @@
 use crate::domain::subscriber_email::SubscriberEmail;
+use crate::email::circuit_breaker::CircuitBreaker;
+use crate::email::email_client::EmailClient;
+use crate::email::message_options::MessageDefaults;
+use crate::email::postmark_transport::PostmarkTransport;
+use crate::email::sparkpost_transport::SparkPostTransport;
+use crate::email::transport::EmailTransport;
+
+#[derive(serde::Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailProvider {
+    /// JSON API with message streams.
+    Postmark,
+    /// Takes raw MIME messages, `authorization_token` is the API key.
+    SparkPost,
+}
 
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
+    pub provider: EmailProvider,
     pub base_url: String,
     pub sender_email: String,
     pub authorization_token: String,
     pub timeout_milliseconds: u64,
+    pub max_attachments_size_bytes: u64,
+    pub reply_to: Option<String>,
+    pub transactional_message_stream: String,
+    pub broadcast_message_stream: String,
+    pub circuit_breaker_failure_threshold: u32,
+    pub circuit_breaker_open_milliseconds: u64,
 }
 
 impl EmailClientSettings {
@@ -16,5 +38,35 @@ impl EmailClientSettings {
     pub fn timeout(&self) -> std::time::Duration {
         std::time::Duration::from_millis(self.timeout_milliseconds)
     }
+
+    pub fn client(self) -> EmailClient {
+        let sender_email = self.sender().expect("Invalid sender email address.");
+        let timeout = self.timeout();
+        let message_defaults = self.message_defaults();
+        let circuit_breaker = CircuitBreaker::new(
+            self.circuit_breaker_failure_threshold,
+            std::time::Duration::from_millis(self.circuit_breaker_open_milliseconds),
+        );
+
+        let transport: Box<dyn EmailTransport> = match self.provider {
+            EmailProvider::Postmark => Box::new(PostmarkTransport::new(self.base_url, self.authorization_token, timeout)),
+            EmailProvider::SparkPost => Box::new(SparkPostTransport::new(self.base_url, self.authorization_token, timeout)),
+        };
+
+        EmailClient::new(
+            transport,
+            sender_email,
+            self.max_attachments_size_bytes,
+            message_defaults,
+            circuit_breaker)
+    }
+
+    pub fn message_defaults(&self) -> MessageDefaults {
+        MessageDefaults {
+            reply_to: self.reply_to.clone(),
+            transactional_stream: self.transactional_message_stream.clone(),
+            broadcast_stream: self.broadcast_message_stream.clone(),
+        }
+    }
 }
 
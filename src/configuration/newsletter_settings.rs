@@ -0,0 +1,24 @@
+use serde_aux::field_attributes::deserialize_number_from_string;
+use crate::domain::subscriber_time_zone::SubscriberTimeZone;
+
+#[derive(serde::Deserialize, Clone)]
+pub struct NewsletterSettings {
+    /// How long the subject lines of an A/B test are measured before the winner goes out.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub subject_test_window_minutes: u32,
+    /// Default share of the confirmed list receiving each subject line of an A/B test.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub subject_test_sample_percentage: u8,
+    /// Time zone of subscribers who did not tell us theirs, used for delivery in local time.
+    pub default_time_zone: String,
+}
+
+impl NewsletterSettings {
+    pub fn subject_test_window(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.subject_test_window_minutes as i64)
+    }
+
+    pub fn default_time_zone(&self) -> SubscriberTimeZone {
+        SubscriberTimeZone::parse(&self.default_time_zone).expect("Invalid default time zone.")
+    }
+}
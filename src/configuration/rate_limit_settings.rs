@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use std::time::Duration;
+use secrecy::Secret;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+use crate::rate_limiting::limiter::{Limit, RateLimiter, RouteLimits};
+use crate::rate_limiting::store::{InMemoryRateLimitStore, RateLimitStore, RedisRateLimitStore};
+
+#[derive(serde::Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBackend {
+    /// Shared by every instance, through `redis_uri`.
+    Redis,
+    /// Local to the process, e.g. for tests.
+    Memory,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct RateLimitSettings {
+    pub backend: RateLimitBackend,
+    #[serde(default)]
+    pub routes: Vec<RouteRateLimitSettings>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct RouteRateLimitSettings {
+    pub path: String,
+    #[serde(default)]
+    pub per_ip: Option<LimitSettings>,
+    /// Per email address or username the request is about.
+    #[serde(default)]
+    pub per_target: Option<LimitSettings>,
+    /// Across every client.
+    #[serde(default)]
+    pub per_route: Option<LimitSettings>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy)]
+pub struct LimitSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub requests: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub window_seconds: u64,
+}
+
+impl LimitSettings {
+    pub fn limit(&self) -> Limit {
+        Limit { requests: self.requests, window: Duration::from_secs(self.window_seconds) }
+    }
+}
+
+impl RateLimitSettings {
+    pub async fn limiter(&self, redis_uri: &Secret<String>) -> Result<RateLimiter, anyhow::Error> {
+        let store: Arc<dyn RateLimitStore> = match self.backend {
+            RateLimitBackend::Redis => Arc::new(RedisRateLimitStore::connect(redis_uri).await?),
+            RateLimitBackend::Memory => Arc::new(InMemoryRateLimitStore::default()),
+        };
+        let routes = self.routes
+            .iter()
+            .map(|route| {
+                let limits = RouteLimits {
+                    per_ip: route.per_ip.map(|l| l.limit()),
+                    per_target: route.per_target.map(|l| l.limit()),
+                    per_route: route.per_route.map(|l| l.limit()),
+                };
+                (route.path.clone(), limits)
+            })
+            .collect();
+
+        Ok(RateLimiter::new(store, routes))
+    }
+}
@@ -3,6 +3,10 @@ use crate::configuration::{
     database_settings::DatabaseSettings,
     application_settings::ApplicationSettings,
     email_settings::EmailClientSettings,
+    newsletter_settings::NewsletterSettings,
+    subscription_settings::SubscriptionSettings,
+    email_policy_settings::EmailPolicySettings,
+    rate_limit_settings::RateLimitSettings,
     environment::Environment,
 };
 use secrecy::Secret;
@@ -12,6 +16,10 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
+    pub newsletter: NewsletterSettings,
+    pub subscriptions: SubscriptionSettings,
+    pub email_policy: EmailPolicySettings,
+    pub rate_limits: RateLimitSettings,
     pub redis_uri: Secret<String>,
 }
 
@@ -0,0 +1,37 @@
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    /// How long a confirmation link stays valid.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub token_ttl_hours: u32,
+    /// Subscribers who never confirmed are deleted after this many days.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub unconfirmed_retention_days: u32,
+    /// How often expired tokens and stale subscribers are purged.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub cleanup_interval_seconds: u64,
+    /// Minimum time between two confirmation emails to the same address.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub resend_cooldown_seconds: u32,
+    /// Version of the consent wording on the signup form, recorded with every consent event.
+    pub consent_version: String,
+}
+
+impl SubscriptionSettings {
+    pub fn token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.token_ttl_hours as i64)
+    }
+
+    pub fn unconfirmed_retention(&self) -> chrono::Duration {
+        chrono::Duration::days(self.unconfirmed_retention_days as i64)
+    }
+
+    pub fn resend_cooldown(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.resend_cooldown_seconds as i64)
+    }
+
+    pub fn cleanup_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.cleanup_interval_seconds)
+    }
+}
@@ -1,3 +1,19 @@
 pub mod subscriber_name;
 pub mod new_subscriber;
-pub mod subscriber_email;
\ No newline at end of file
+pub mod subscriber_email;
+pub mod new_issue;
+pub mod subject_test;
+pub mod subscriber_time_zone;
+pub mod delivery_status;
+pub mod email_policy;
+pub mod bot_protection;
+pub mod subscriber_import;
+pub mod subscriber_export;
+pub mod subscriber_attribute;
+pub mod merge_fields;
+pub mod subscriber_listing;
+pub mod bulk_action;
+pub mod subscription_status;
+pub mod welcome_email;
+pub mod automation;
+pub mod referral;
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use crate::domain::merge_fields::render_merge_fields;
+use crate::domain::subscriber_attribute::AttributeDefinition;
+
+/// Longest delay of a step, a sequence is meant for onboarding rather than for the years to come.
+const MAX_DELAY_DAYS: i32 = 365;
+
+/// What enrolls subscribers in a sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SequenceTrigger {
+    /// The subscriber confirmed their subscription.
+    Confirmation,
+    /// The subscriber was given the tag.
+    Tag(String),
+}
+
+impl SequenceTrigger {
+    pub fn parse(kind: &str, tag: &str) -> Result<SequenceTrigger, String> {
+        match (kind.trim(), tag.trim()) {
+            ("confirmation", _) => Ok(SequenceTrigger::Confirmation),
+            ("tag", "") => Err("Name the tag that enrolls subscribers.".into()),
+            ("tag", tag) => Ok(SequenceTrigger::Tag(tag.to_owned())),
+            (other, _) => Err(format!("{} is not a sequence trigger.", other)),
+        }
+    }
+
+    /// From the `trigger` and `trigger_tag` columns.
+    pub fn from_columns(kind: &str, tag: Option<String>) -> Result<SequenceTrigger, String> {
+        Self::parse(kind, tag.as_deref().unwrap_or(""))
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SequenceTrigger::Confirmation => "confirmation",
+            SequenceTrigger::Tag(_) => "tag",
+        }
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        match self {
+            SequenceTrigger::Confirmation => None,
+            SequenceTrigger::Tag(tag) => Some(tag),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            SequenceTrigger::Confirmation => "On confirmation".into(),
+            SequenceTrigger::Tag(tag) => format!("When tagged {}", tag),
+        }
+    }
+}
+
+/// Who a step is sent to, the others skip it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepCondition {
+    /// A custom attribute has the value, compared in its normalized form.
+    Attribute { key: String, value: String },
+    /// Opened or clicked through an issue.
+    Engaged,
+    /// Never opened nor clicked through an issue.
+    NotEngaged,
+}
+
+impl StepCondition {
+    /// An empty `kind` means the step has no condition.
+    pub fn parse(
+        definitions: &[AttributeDefinition],
+        kind: &str,
+        key: &str,
+        value: &str,
+    ) -> Result<Option<StepCondition>, String> {
+        match kind.trim() {
+            "" => Ok(None),
+            "engaged" => Ok(Some(StepCondition::Engaged)),
+            "not_engaged" => Ok(Some(StepCondition::NotEngaged)),
+            "attribute" => {
+                let definition = definitions
+                    .iter()
+                    .find(|definition| definition.key == key.trim())
+                    .ok_or_else(|| format!("{} is not a subscriber attribute.", key.trim()))?;
+                Ok(Some(StepCondition::Attribute {
+                    key: definition.key.clone(),
+                    value: definition.normalize(value)?,
+                }))
+            }
+            other => Err(format!("{} is not a step condition.", other)),
+        }
+    }
+
+    /// From the `condition_kind`, `condition_key` and `condition_value` columns.
+    pub fn from_columns(
+        kind: Option<String>,
+        key: Option<String>,
+        value: Option<String>,
+    ) -> Result<Option<StepCondition>, String> {
+        match kind.as_deref() {
+            None => Ok(None),
+            Some("engaged") => Ok(Some(StepCondition::Engaged)),
+            Some("not_engaged") => Ok(Some(StepCondition::NotEngaged)),
+            Some("attribute") => Ok(Some(StepCondition::Attribute {
+                key: key.ok_or("An attribute condition needs a key.")?,
+                value: value.unwrap_or_default(),
+            })),
+            Some(other) => Err(format!("{} is not a step condition.", other)),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StepCondition::Attribute { .. } => "attribute",
+            StepCondition::Engaged => "engaged",
+            StepCondition::NotEngaged => "not_engaged",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            StepCondition::Attribute { key, value } => format!("{} is {}", key, value),
+            StepCondition::Engaged => "Opened or clicked an issue".into(),
+            StepCondition::NotEngaged => "Never opened nor clicked an issue".into(),
+        }
+    }
+
+    /// `attributes` are the normalized values of the subscriber's custom attributes.
+    pub fn matches(&self, attributes: &HashMap<String, String>, engaged: bool) -> bool {
+        match self {
+            StepCondition::Attribute { key, value } => attributes.get(key) == Some(value),
+            StepCondition::Engaged => engaged,
+            StepCondition::NotEngaged => !engaged,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceStep {
+    pub position: i16,
+    pub delay_days: i32,
+    pub subject: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub condition: Option<StepCondition>,
+}
+
+impl SequenceStep {
+    /// The step after `previous`, which it may not precede.
+    pub fn parse(
+        previous: Option<&SequenceStep>,
+        delay_days: &str,
+        subject: &str,
+        text_content: &str,
+        html_content: &str,
+        condition: Option<StepCondition>,
+    ) -> Result<SequenceStep, String> {
+        let delay_days: i32 = delay_days
+            .trim()
+            .parse()
+            .map_err(|_| format!("{} is not a number of days.", delay_days.trim()))?;
+        if !(0..=MAX_DELAY_DAYS).contains(&delay_days) {
+            return Err(format!("The delay must be between 0 and {} days.", MAX_DELAY_DAYS));
+        }
+        if let Some(previous) = previous {
+            if delay_days < previous.delay_days {
+                return Err(format!(
+                    "The delay must be at least {} days, the delay of the previous step.",
+                    previous.delay_days,
+                ));
+            }
+        }
+        if subject.trim().is_empty() {
+            return Err("The step needs a subject.".into());
+        }
+        if text_content.trim().is_empty() || html_content.trim().is_empty() {
+            return Err("The step needs both a plain text and an HTML body.".into());
+        }
+
+        Ok(Self {
+            position: previous.map(|previous| previous.position + 1).unwrap_or(0),
+            delay_days,
+            subject: subject.trim().to_owned(),
+            text_content: text_content.to_owned(),
+            html_content: html_content.to_owned(),
+            condition,
+        })
+    }
+
+    /// Subject, HTML and plain text bodies for one subscriber.
+    pub fn render(&self, values: &HashMap<String, String>) -> (String, String, String) {
+        (
+            render_merge_fields(&self.subject, values, false),
+            render_merge_fields(&self.html_content, values, true),
+            render_merge_fields(&self.text_content, values, false),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepOutcome {
+    Sent,
+    /// The subscriber did not meet the condition of the step.
+    Skipped,
+    Failed,
+}
+
+impl StepOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepOutcome::Sent => "sent",
+            StepOutcome::Skipped => "skipped",
+            StepOutcome::Failed => "failed",
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::automation::{SequenceStep, SequenceTrigger, StepCondition};
+    use crate::domain::subscriber_attribute::{AttributeDefinition, AttributeKind};
+    use claim::{assert_err, assert_ok};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn definitions() -> Vec<AttributeDefinition> {
+        vec![AttributeDefinition {
+            attribute_id: Uuid::new_v4(),
+            key: "plan".into(),
+            label: "Plan".into(),
+            kind: AttributeKind::Enum,
+            options: vec!["free".into(), "pro".into()],
+            required: false,
+        }]
+    }
+
+    fn step(previous: Option<&SequenceStep>, delay_days: &str) -> Result<SequenceStep, String> {
+        SequenceStep::parse(previous, delay_days, "Day {{ day }}", "Hi", "<p>Hi</p>", None)
+    }
+
+    #[test]
+    fn a_tag_trigger_needs_a_tag() {
+        assert_eq!(SequenceTrigger::parse("tag", " trial ").unwrap(), SequenceTrigger::Tag("trial".into()));
+        assert_eq!(SequenceTrigger::parse("confirmation", "").unwrap(), SequenceTrigger::Confirmation);
+        assert_err!(SequenceTrigger::parse("tag", ""));
+        assert_err!(SequenceTrigger::parse("birthday", ""));
+    }
+
+    #[test]
+    fn steps_are_numbered_in_order() {
+        let welcome = step(None, "0").unwrap();
+        let best_of = step(Some(&welcome), "2").unwrap();
+        let survey = step(Some(&best_of), "7").unwrap();
+
+        assert_eq!((welcome.position, best_of.position, survey.position), (0, 1, 2));
+        assert_eq!(survey.delay_days, 7);
+    }
+
+    #[test]
+    fn a_step_cannot_come_before_the_previous_one() {
+        let best_of = step(None, "2").unwrap();
+
+        assert_err!(step(Some(&best_of), "1"));
+        assert_ok!(step(Some(&best_of), "2"));
+    }
+
+    #[test]
+    fn invalid_delays_are_rejected() {
+        assert_err!(step(None, "-1"));
+        assert_err!(step(None, "two"));
+        assert_err!(step(None, "366"));
+    }
+
+    #[test]
+    fn attribute_conditions_compare_normalized_values() {
+        let condition = StepCondition::parse(&definitions(), "attribute", "plan", " Pro ").unwrap().unwrap();
+        let pro: HashMap<String, String> = vec![("plan".to_string(), "pro".to_string())].into_iter().collect();
+        let free: HashMap<String, String> = vec![("plan".to_string(), "free".to_string())].into_iter().collect();
+
+        assert!(condition.matches(&pro, false));
+        assert!(!condition.matches(&free, true));
+        assert!(!condition.matches(&HashMap::new(), true));
+    }
+
+    #[test]
+    fn engagement_conditions_follow_opens_and_clicks() {
+        assert!(StepCondition::Engaged.matches(&HashMap::new(), true));
+        assert!(!StepCondition::Engaged.matches(&HashMap::new(), false));
+        assert!(StepCondition::NotEngaged.matches(&HashMap::new(), false));
+    }
+
+    #[test]
+    fn unknown_conditions_are_rejected() {
+        assert_eq!(StepCondition::parse(&definitions(), "", "", "").unwrap(), None);
+        assert_err!(StepCondition::parse(&definitions(), "attribute", "company", "Acme"));
+        assert_err!(StepCondition::parse(&definitions(), "attribute", "plan", "enterprise"));
+        assert_err!(StepCondition::parse(&definitions(), "birthday", "", ""));
+    }
+
+    #[test]
+    fn conditions_round_trip_through_their_columns() {
+        let condition = StepCondition::Attribute { key: "plan".into(), value: "pro".into() };
+        let restored = StepCondition::from_columns(
+            Some(condition.kind().into()),
+            Some("plan".into()),
+            Some("pro".into()),
+        ).unwrap();
+
+        assert_eq!(restored, Some(condition));
+    }
+}
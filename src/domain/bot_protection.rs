@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use redis::aio::MultiplexedConnection;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+use crate::helpers::signed_token::{sign, verify};
+
+const FORM_TOKEN_PURPOSE: &str = "signup-form";
+/// Claimed tokens kept in memory before the expired ones are swept.
+const MIN_SWEEP_SIZE: usize = 1024;
+
+/// Checks the response of a CAPTCHA widget with its provider.
+#[async_trait::async_trait]
+pub trait CaptchaVerifier: Send + Sync {
+    async fn verify(&self, response: &str, remote_ip: Option<&str>) -> Result<bool, anyhow::Error>;
+}
+
+/// Accepts a single, fixed response. Meant for local development and tests.
+pub struct StubCaptchaVerifier {
+    expected_response: Secret<String>,
+}
+
+impl StubCaptchaVerifier {
+    pub fn new(expected_response: Secret<String>) -> Self {
+        Self { expected_response }
+    }
+}
+
+#[async_trait::async_trait]
+impl CaptchaVerifier for StubCaptchaVerifier {
+    async fn verify(&self, response: &str, _remote_ip: Option<&str>) -> Result<bool, anyhow::Error> {
+        Ok(!response.is_empty() && response == self.expected_response.expose_secret())
+    }
+}
+
+/// Remembers the form tokens that were submitted, so that each one is accepted once.
+#[async_trait::async_trait]
+pub trait FormTokenStore: Send + Sync {
+    /// Returns `false` when the token was claimed already within the last `ttl`.
+    async fn claim(&self, form_token: &str, ttl: std::time::Duration) -> Result<bool, anyhow::Error>;
+}
+
+/// Shares the claimed tokens between every instance of the application.
+pub struct RedisFormTokenStore {
+    connection: MultiplexedConnection,
+}
+
+impl RedisFormTokenStore {
+    pub async fn connect(redis_uri: &Secret<String>) -> Result<Self, anyhow::Error> {
+        let client = redis::Client::open(redis_uri.expose_secret().as_str())?;
+        let connection = client.get_multiplexed_tokio_connection().await?;
+        Ok(Self { connection })
+    }
+}
+
+#[async_trait::async_trait]
+impl FormTokenStore for RedisFormTokenStore {
+    async fn claim(&self, form_token: &str, ttl: std::time::Duration) -> Result<bool, anyhow::Error> {
+        // SET NX answers nil when the key exists already
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(format!("form-token:{}", form_token))
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis() as u64)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        Ok(claimed.is_some())
+    }
+}
+
+/// Claimed tokens local to the process, for tests and single instance deployments.
+pub struct InMemoryFormTokenStore {
+    claimed: Mutex<ClaimedTokens>,
+}
+
+struct ClaimedTokens {
+    /// When each token can be forgotten.
+    expires_at: HashMap<String, Instant>,
+    /// Expired tokens are swept once this many are kept, so that the sweeps stay rare.
+    sweep_size: usize,
+}
+
+impl Default for InMemoryFormTokenStore {
+    fn default() -> Self {
+        let claimed = ClaimedTokens { expires_at: HashMap::new(), sweep_size: MIN_SWEEP_SIZE };
+        Self { claimed: Mutex::new(claimed) }
+    }
+}
+
+#[async_trait::async_trait]
+impl FormTokenStore for InMemoryFormTokenStore {
+    async fn claim(&self, form_token: &str, ttl: std::time::Duration) -> Result<bool, anyhow::Error> {
+        let now = Instant::now();
+        let mut claimed = self.claimed.lock().unwrap();
+        if claimed.expires_at.len() >= claimed.sweep_size {
+            claimed.expires_at.retain(|_, expires_at| *expires_at > now);
+            claimed.sweep_size = MIN_SWEEP_SIZE.max(claimed.expires_at.len() * 2);
+        }
+
+        match claimed.expires_at.get(form_token) {
+            Some(expires_at) if *expires_at > now => Ok(false),
+            _ => {
+                claimed.expires_at.insert(form_token.to_owned(), now + ttl);
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// What a signup form submitted, besides the subscriber details.
+pub struct SignupAttempt<'a> {
+    /// A field hidden from people: anything in it was filled in by a bot.
+    pub honeypot: &'a str,
+    /// The address being subscribed, part of the proof of work challenge.
+    pub email: &'a str,
+    pub form_token: &'a str,
+    pub pow_nonce: &'a str,
+    pub captcha_response: &'a str,
+    pub remote_ip: Option<&'a str>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BotRejection {
+    /// The honeypot was filled in. The caller should pretend the signup went through.
+    Trapped,
+    Rejected(String),
+}
+
+/// Layered checks that a signup comes from a person filling in our form.
+pub struct BotProtection {
+    hmac_secret: Secret<String>,
+    min_fill_time: Duration,
+    max_form_age: Duration,
+    /// Leading zero bits required from the proof of work, 0 disables it.
+    pow_difficulty: u8,
+    captcha_verifier: Option<Arc<dyn CaptchaVerifier>>,
+    form_tokens: Arc<dyn FormTokenStore>,
+}
+
+impl BotProtection {
+    pub fn new(
+        hmac_secret: Secret<String>,
+        min_fill_time: Duration,
+        max_form_age: Duration,
+        pow_difficulty: u8,
+    ) -> Self {
+        Self {
+            hmac_secret,
+            min_fill_time,
+            max_form_age,
+            pow_difficulty,
+            captcha_verifier: None,
+            form_tokens: Arc::new(InMemoryFormTokenStore::default()),
+        }
+    }
+
+    pub fn with_captcha_verifier(mut self, verifier: Arc<dyn CaptchaVerifier>) -> Self {
+        self.captcha_verifier = Some(verifier);
+        self
+    }
+
+    /// Where submitted form tokens are remembered, in memory by default.
+    pub fn with_form_token_store(mut self, store: Arc<dyn FormTokenStore>) -> Self {
+        self.form_tokens = store;
+        self
+    }
+
+    pub fn pow_difficulty(&self) -> u8 {
+        self.pow_difficulty
+    }
+
+    /// A token to embed in the signup form, recording when the form was served.
+    ///
+    /// Together with the submitted address it makes the proof of work challenge, hence the random part.
+    pub fn issue_form_token(&self, now: DateTime<Utc>) -> String {
+        let challenge: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .map(char::from)
+            .take(16)
+            .collect();
+        sign(&self.hmac_secret, FORM_TOKEN_PURPOSE, &format!("{}:{}", now.timestamp(), challenge))
+    }
+
+    pub async fn check(&self, attempt: &SignupAttempt<'_>, now: DateTime<Utc>) -> Result<(), BotRejection> {
+        if !attempt.honeypot.trim().is_empty() {
+            return Err(BotRejection::Trapped);
+        }
+
+        // Without a minimum fill time or a proof of work there is nothing the token is needed for
+        let needs_form_token = self.min_fill_time > Duration::zero() || self.pow_difficulty > 0;
+        if needs_form_token {
+            let issued_at = self.form_issued_at(attempt.form_token).ok_or_else(|| {
+                BotRejection::Rejected("The signup form is invalid, please reload the page and try again.".into())
+            })?;
+            if now - issued_at < self.min_fill_time {
+                return Err(BotRejection::Rejected(
+                    "The signup form was submitted too quickly, please try again.".into(),
+                ));
+            }
+            if now - issued_at > self.max_form_age {
+                return Err(BotRejection::Rejected(
+                    "The signup form has expired, please reload the page and try again.".into(),
+                ));
+            }
+            let challenge = proof_of_work_challenge(attempt.form_token, attempt.email);
+            if !is_valid_proof_of_work(&challenge, attempt.pow_nonce, self.pow_difficulty) {
+                return Err(BotRejection::Rejected(
+                    "The signup form could not be verified, please make sure JavaScript is enabled.".into(),
+                ));
+            }
+        }
+
+        if let Some(verifier) = &self.captcha_verifier {
+            match verifier.verify(attempt.captcha_response, attempt.remote_ip).await {
+                Ok(true) => {}
+                Ok(false) => return Err(BotRejection::Rejected("The CAPTCHA was not solved.".into())),
+                Err(e) => {
+                    tracing::warn!(error.message = %e, "CAPTCHA verification failed");
+                    return Err(BotRejection::Rejected(
+                        "The CAPTCHA could not be verified, please try again.".into(),
+                    ));
+                }
+            }
+        }
+
+        // Claimed last, so that a form turned away above can be fixed and sent again
+        if needs_form_token {
+            let ttl = self.max_form_age.to_std().unwrap_or_default();
+            match self.form_tokens.claim(attempt.form_token, ttl).await {
+                Ok(true) => {}
+                Ok(false) => return Err(BotRejection::Rejected(
+                    "The signup form was submitted already, please reload the page and try again.".into(),
+                )),
+                // An unavailable store is no reason to turn everyone away
+                Err(e) => tracing::warn!(error.message = %e, "Form token claim failed"),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn form_issued_at(&self, form_token: &str) -> Option<DateTime<Utc>> {
+        let payload = verify(&self.hmac_secret, FORM_TOKEN_PURPOSE, form_token)?;
+        let (issued_at, _challenge) = payload.split_once(':')?;
+        Some(Utc.timestamp(issued_at.parse().ok()?, 0))
+    }
+}
+
+/// The proof of work is bound to the address, so that a solved form cannot sign up someone else.
+pub fn proof_of_work_challenge(form_token: &str, email: &str) -> String {
+    format!("{}:{}", form_token, email.trim())
+}
+
+/// Whether `sha256("{challenge}:{nonce}")` starts with at least `difficulty` zero bits.
+pub fn is_valid_proof_of_work(challenge: &str, nonce: &str, difficulty: u8) -> bool {
+    let digest = Sha256::digest(format!("{}:{}", challenge, nonce).as_bytes());
+    let mut zero_bits = 0;
+    for byte in digest.iter() {
+        if *byte == 0 {
+            zero_bits += 8;
+        } else {
+            zero_bits += byte.leading_zeros();
+            break;
+        }
+    }
+    zero_bits >= difficulty as u32
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::domain::bot_protection::{
+        is_valid_proof_of_work, proof_of_work_challenge, BotProtection, BotRejection, FormTokenStore,
+        InMemoryFormTokenStore, SignupAttempt, StubCaptchaVerifier,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+    use claim::{assert_err, assert_ok};
+    use secrecy::Secret;
+
+    fn secret() -> Secret<String> {
+        Secret::new("a-very-secret-key".to_string())
+    }
+
+    fn protection(min_fill_seconds: i64, pow_difficulty: u8) -> BotProtection {
+        BotProtection::new(secret(), Duration::seconds(min_fill_seconds), Duration::hours(1), pow_difficulty)
+    }
+
+    fn attempt<'a>(form_token: &'a str, pow_nonce: &'a str) -> SignupAttempt<'a> {
+        SignupAttempt {
+            honeypot: "",
+            email: "ursula@example.com",
+            form_token,
+            pow_nonce,
+            captcha_response: "",
+            remote_ip: None,
+        }
+    }
+
+    fn solve(challenge: &str, difficulty: u8) -> String {
+        (0u64..)
+            .map(|nonce| nonce.to_string())
+            .find(|nonce| is_valid_proof_of_work(challenge, nonce, difficulty))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_filled_in_honeypot_is_trapped() {
+        let mut submission = attempt("", "");
+        submission.honeypot = "https://buy-now.example";
+
+        assert_eq!(protection(0, 0).check(&submission, Utc::now()).await, Err(BotRejection::Trapped));
+    }
+
+    #[tokio::test]
+    async fn no_token_is_needed_when_fill_time_and_proof_of_work_are_disabled() {
+        assert_ok!(protection(0, 0).check(&attempt("", ""), Utc::now()).await);
+    }
+
+    #[tokio::test]
+    async fn a_form_submitted_too_quickly_is_rejected() {
+        let protection = protection(5, 0);
+        let served_at = Utc.ymd(2022, 1, 31).and_hms(9, 0, 0);
+        let token = protection.issue_form_token(served_at);
+
+        assert_err!(protection.check(&attempt(&token, ""), served_at + Duration::seconds(2)).await);
+        assert_ok!(protection.check(&attempt(&token, ""), served_at + Duration::seconds(5)).await);
+    }
+
+    #[tokio::test]
+    async fn a_stale_form_is_rejected() {
+        let protection = protection(5, 0);
+        let served_at = Utc.ymd(2022, 1, 31).and_hms(9, 0, 0);
+        let token = protection.issue_form_token(served_at);
+
+        assert_err!(protection.check(&attempt(&token, ""), served_at + Duration::hours(2)).await);
+    }
+
+    #[tokio::test]
+    async fn forged_or_missing_tokens_are_rejected() {
+        let now = Utc::now();
+        let forged = format!("{}:abc.deadbeef", (now - Duration::minutes(1)).timestamp());
+
+        assert_err!(protection(5, 0).check(&attempt("", ""), now).await);
+        assert_err!(protection(5, 0).check(&attempt(&forged, ""), now).await);
+    }
+
+    #[tokio::test]
+    async fn the_proof_of_work_is_checked_against_the_form_token() {
+        let protection = protection(0, 8);
+        let now = Utc::now();
+        let token = protection.issue_form_token(now);
+        let nonce = solve(&proof_of_work_challenge(&token, "ursula@example.com"), 8);
+
+        assert_ok!(protection.check(&attempt(&token, &nonce), now).await);
+        let other_token = protection.issue_form_token(now);
+        assert_err!(protection.check(&attempt(&other_token, &nonce), now).await);
+    }
+
+    #[tokio::test]
+    async fn the_proof_of_work_is_bound_to_the_submitted_address() {
+        let protection = protection(0, 8);
+        let now = Utc::now();
+        let token = protection.issue_form_token(now);
+        let nonce = solve(&proof_of_work_challenge(&token, "someone-else@example.com"), 8);
+
+        assert_err!(protection.check(&attempt(&token, &nonce), now).await);
+    }
+
+    #[tokio::test]
+    async fn a_form_token_is_accepted_once() {
+        let protection = protection(0, 8);
+        let now = Utc::now();
+        let token = protection.issue_form_token(now);
+        let nonce = solve(&proof_of_work_challenge(&token, "ursula@example.com"), 8);
+
+        assert_ok!(protection.check(&attempt(&token, &nonce), now).await);
+        assert_err!(protection.check(&attempt(&token, &nonce), now).await);
+    }
+
+    #[tokio::test]
+    async fn claimed_tokens_are_forgotten_once_expired() {
+        let store = InMemoryFormTokenStore::default();
+        let ttl = std::time::Duration::from_millis(10);
+
+        assert!(store.claim("token", ttl).await.unwrap());
+        assert!(!store.claim("token", ttl).await.unwrap());
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(store.claim("token", ttl).await.unwrap());
+    }
+
+    #[test]
+    fn proof_of_work_counts_leading_zero_bits() {
+        let nonce = solve("challenge", 12);
+
+        assert!(is_valid_proof_of_work("challenge", &nonce, 12));
+        assert!(is_valid_proof_of_work("challenge", &nonce, 0));
+    }
+
+    #[tokio::test]
+    async fn the_captcha_response_is_verified_when_a_verifier_is_plugged_in() {
+        let protection = protection(0, 0)
+            .with_captcha_verifier(Arc::new(StubCaptchaVerifier::new(Secret::new("solved".into()))));
+        let mut submission = attempt("", "");
+
+        assert_err!(protection.check(&submission, Utc::now()).await);
+        submission.captcha_response = "solved";
+        assert_ok!(protection.check(&submission, Utc::now()).await);
+    }
+}
@@ -0,0 +1,117 @@
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BulkAction {
+    Unsubscribe,
+    /// Removes the records for good. Unlike an erasure, the addresses are not suppressed.
+    Delete,
+}
+
+impl BulkAction {
+    pub fn parse(s: &str) -> Result<BulkAction, String> {
+        match s.trim() {
+            "unsubscribe" => Ok(BulkAction::Unsubscribe),
+            "delete" => Ok(BulkAction::Delete),
+            other => Err(format!("{} is not an action on subscribers.", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BulkAction::Unsubscribe => "unsubscribe",
+            BulkAction::Delete => "delete",
+        }
+    }
+
+    pub fn verb(&self) -> &'static str {
+        match self {
+            BulkAction::Unsubscribe => "Unsubscribe",
+            BulkAction::Delete => "Delete",
+        }
+    }
+}
+
+/// An action on the subscribers selected in the admin list.
+#[derive(Debug, PartialEq)]
+pub struct BulkSelection {
+    pub action: BulkAction,
+    pub subscriber_ids: Vec<Uuid>,
+    /// Set once the admin went through the confirmation prompt.
+    pub confirmed: bool,
+}
+
+impl BulkSelection {
+    /// From the fields of a form, where `subscriber_id` is repeated once per selected checkbox.
+    pub fn parse(fields: &[(String, String)]) -> Result<BulkSelection, String> {
+        let mut action = None;
+        let mut subscriber_ids = Vec::new();
+        let mut confirmed = false;
+        for (name, value) in fields {
+            match name.as_str() {
+                "action" => action = Some(BulkAction::parse(value)?),
+                "subscriber_id" => {
+                    let id = Uuid::parse_str(value).map_err(|_| format!("{} is not a subscriber id.", value))?;
+                    if !subscriber_ids.contains(&id) {
+                        subscriber_ids.push(id);
+                    }
+                }
+                "confirmed" => confirmed = value == "yes",
+                _ => {}
+            }
+        }
+
+        let action = action.ok_or("Choose an action to apply.")?;
+        if subscriber_ids.is_empty() {
+            return Err("Select at least one subscriber.".into());
+        }
+        Ok(Self { action, subscriber_ids, confirmed })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::bulk_action::{BulkAction, BulkSelection};
+    use claim::assert_err;
+    use uuid::Uuid;
+
+    fn field(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn every_selected_subscriber_is_collected_once() {
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let fields = vec![
+            field("action", "delete"),
+            field("subscriber_id", &first.to_string()),
+            field("subscriber_id", &second.to_string()),
+            field("subscriber_id", &first.to_string()),
+        ];
+
+        let selection = BulkSelection::parse(&fields).unwrap();
+
+        assert_eq!(selection.action, BulkAction::Delete);
+        assert_eq!(selection.subscriber_ids, vec![first, second]);
+        assert!(!selection.confirmed);
+    }
+
+    #[test]
+    fn the_confirmation_is_recorded() {
+        let fields = vec![
+            field("action", "unsubscribe"),
+            field("subscriber_id", &Uuid::new_v4().to_string()),
+            field("confirmed", "yes"),
+        ];
+
+        assert!(BulkSelection::parse(&fields).unwrap().confirmed);
+    }
+
+    #[test]
+    fn an_empty_selection_or_unknown_action_is_rejected() {
+        assert_err!(BulkSelection::parse(&[field("action", "delete")]));
+        assert_err!(BulkSelection::parse(&[field("subscriber_id", &Uuid::new_v4().to_string())]));
+        assert_err!(BulkSelection::parse(&[field("action", "promote"), field("subscriber_id", &Uuid::new_v4().to_string())]));
+        assert_err!(BulkSelection::parse(&[field("action", "delete"), field("subscriber_id", "42")]));
+    }
+}
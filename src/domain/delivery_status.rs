@@ -0,0 +1,106 @@
+/// Where the delivery of a newsletter issue stands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeliveryStatus {
+    Sending,
+    Paused,
+    Cancelled,
+}
+
+/// What an admin can do to an issue that is being delivered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeliveryAction {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+impl DeliveryStatus {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "sending" => Ok(Self::Sending),
+            "paused" => Ok(Self::Paused),
+            "cancelled" => Ok(Self::Cancelled),
+            other => Err(format!("{} is not a valid delivery status.", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sending => "sending",
+            Self::Paused => "paused",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    /// The status after `action`, cancelling is final.
+    pub fn apply(self, action: DeliveryAction) -> Result<Self, String> {
+        match (self, action) {
+            (Self::Sending, DeliveryAction::Pause) => Ok(Self::Paused),
+            (Self::Paused, DeliveryAction::Resume) => Ok(Self::Sending),
+            (Self::Sending, DeliveryAction::Cancel) | (Self::Paused, DeliveryAction::Cancel) => Ok(Self::Cancelled),
+            (status, action) => Err(format!(
+                "An issue that is {} cannot be {}.",
+                status.as_str(),
+                action.past_tense(),
+            )),
+        }
+    }
+}
+
+impl DeliveryAction {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "pause" => Ok(Self::Pause),
+            "resume" => Ok(Self::Resume),
+            "cancel" => Ok(Self::Cancel),
+            other => Err(format!("{} is not a valid delivery action.", other)),
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            Self::Pause => "paused",
+            Self::Resume => "resumed",
+            Self::Cancel => "cancelled",
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::delivery_status::{DeliveryAction, DeliveryStatus};
+    use claim::assert_err;
+
+    #[test]
+    fn a_sending_issue_can_be_paused_and_resumed() {
+        let paused = DeliveryStatus::Sending.apply(DeliveryAction::Pause).unwrap();
+        assert_eq!(paused, DeliveryStatus::Paused);
+        assert_eq!(paused.apply(DeliveryAction::Resume).unwrap(), DeliveryStatus::Sending);
+    }
+
+    #[test]
+    fn sending_and_paused_issues_can_be_cancelled() {
+        assert_eq!(DeliveryStatus::Sending.apply(DeliveryAction::Cancel).unwrap(), DeliveryStatus::Cancelled);
+        assert_eq!(DeliveryStatus::Paused.apply(DeliveryAction::Cancel).unwrap(), DeliveryStatus::Cancelled);
+    }
+
+    #[test]
+    fn cancelling_is_final() {
+        for action in [DeliveryAction::Pause, DeliveryAction::Resume, DeliveryAction::Cancel] {
+            assert_err!(DeliveryStatus::Cancelled.apply(action));
+        }
+    }
+
+    #[test]
+    fn only_paused_issues_can_be_resumed() {
+        assert_err!(DeliveryStatus::Sending.apply(DeliveryAction::Resume));
+    }
+
+    #[test]
+    fn statuses_round_trip_through_their_database_representation() {
+        for status in [DeliveryStatus::Sending, DeliveryStatus::Paused, DeliveryStatus::Cancelled] {
+            assert_eq!(DeliveryStatus::parse(status.as_str()).unwrap(), status);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::domain::subscriber_email::SubscriberEmail;
+
+const DISPOSABLE_DOMAINS: &str = include_str!("disposable_domains.txt");
+
+/// Mailboxes that belong to a function rather than a person.
+const ROLE_ADDRESSES: &[&str] = &[
+    "abuse", "admin", "administrator", "hostmaster", "mailer-daemon", "no-reply", "noreply",
+    "postmaster", "root", "security", "webmaster",
+];
+
+/// Tells whether a domain can receive email at all.
+#[async_trait::async_trait]
+pub trait MxResolver: Send + Sync {
+    async fn has_mx_records(&self, domain: &str) -> Result<bool, anyhow::Error>;
+}
+
+/// Which addresses may subscribe, on top of being syntactically valid.
+#[derive(Default)]
+pub struct EmailPolicy {
+    /// Domains exempt from every other domain rule.
+    allowed_domains: HashSet<String>,
+    denied_domains: HashSet<String>,
+    disposable_domains: HashSet<String>,
+    reject_role_addresses: bool,
+    mx_resolver: Option<Arc<dyn MxResolver>>,
+}
+
+impl EmailPolicy {
+    pub fn new(allowed_domains: &[String], denied_domains: &[String], reject_role_addresses: bool) -> Self {
+        Self {
+            allowed_domains: allowed_domains.iter().map(|d| d.trim().to_lowercase()).collect(),
+            denied_domains: denied_domains.iter().map(|d| d.trim().to_lowercase()).collect(),
+            reject_role_addresses,
+            ..Self::default()
+        }
+    }
+
+    /// Reject the domains of the bundled list of disposable mailbox providers.
+    pub fn with_disposable_domains(mut self) -> Self {
+        self.disposable_domains = DISPOSABLE_DOMAINS
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_owned)
+            .collect();
+        self
+    }
+
+    pub fn with_mx_resolver(mut self, resolver: Arc<dyn MxResolver>) -> Self {
+        self.mx_resolver = Some(resolver);
+        self
+    }
+
+    pub async fn check(&self, email: &SubscriberEmail) -> Result<(), String> {
+        let (local, domain) = email.normalized().rsplit_once('@').expect("A parsed email contains an @.");
+        let domain = domain.to_lowercase();
+
+        if self.reject_role_addresses && ROLE_ADDRESSES.contains(&local.to_lowercase().as_str()) {
+            return Err(format!(
+                "{} is a role address, please subscribe with a personal address.",
+                email,
+            ));
+        }
+        if matches(&self.allowed_domains, &domain) {
+            return Ok(());
+        }
+        if matches(&self.denied_domains, &domain) {
+            return Err(format!("Addresses at {} cannot subscribe.", domain));
+        }
+        if matches(&self.disposable_domains, &domain) {
+            return Err(format!(
+                "{} is a disposable email provider, please subscribe with a permanent address.",
+                domain,
+            ));
+        }
+        if let Some(resolver) = &self.mx_resolver {
+            match resolver.has_mx_records(&domain).await {
+                Ok(true) => {}
+                Ok(false) => return Err(format!("{} does not accept email.", domain)),
+                // A lookup failure on our side is no reason to turn a subscriber away
+                Err(e) => tracing::warn!(error.message = %e, domain = %domain, "MX lookup failed"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `domain` or one of its parent domains is in `domains`.
+fn matches(domains: &HashSet<String>, domain: &str) -> bool {
+    let mut candidate = domain;
+    loop {
+        if domains.contains(candidate) {
+            return true;
+        }
+        match candidate.split_once('.') {
+            Some((_, parent)) => candidate = parent,
+            None => return false,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::domain::email_policy::{EmailPolicy, MxResolver};
+    use crate::domain::subscriber_email::SubscriberEmail;
+    use claim::{assert_err, assert_ok};
+
+    fn email(s: &str) -> SubscriberEmail {
+        SubscriberEmail::parse(s.to_string()).unwrap()
+    }
+
+    struct StubResolver(bool);
+
+    #[async_trait::async_trait]
+    impl MxResolver for StubResolver {
+        async fn has_mx_records(&self, _domain: &str) -> Result<bool, anyhow::Error> {
+            Ok(self.0)
+        }
+    }
+
+    struct FailingResolver;
+
+    #[async_trait::async_trait]
+    impl MxResolver for FailingResolver {
+        async fn has_mx_records(&self, _domain: &str) -> Result<bool, anyhow::Error> {
+            Err(anyhow::anyhow!("DNS is down"))
+        }
+    }
+
+    #[tokio::test]
+    async fn denied_domains_and_their_subdomains_are_rejected() {
+        let policy = EmailPolicy::new(&[], &["spammy.com".to_string()], false);
+
+        assert_err!(policy.check(&email("ursula@spammy.com")).await);
+        assert_err!(policy.check(&email("ursula@mail.Spammy.com")).await);
+        assert_ok!(policy.check(&email("ursula@notspammy.com")).await);
+    }
+
+    #[tokio::test]
+    async fn bundled_disposable_domains_are_rejected_when_enabled() {
+        let address = email("ursula@mailinator.com");
+
+        assert_ok!(EmailPolicy::default().check(&address).await);
+        assert_err!(EmailPolicy::default().with_disposable_domains().check(&address).await);
+    }
+
+    #[tokio::test]
+    async fn allowed_domains_bypass_the_domain_rules() {
+        let policy = EmailPolicy::new(&["mailinator.com".to_string()], &[], false).with_disposable_domains();
+
+        assert_ok!(policy.check(&email("qa@mailinator.com")).await);
+    }
+
+    #[tokio::test]
+    async fn role_addresses_are_rejected_when_enabled() {
+        let policy = EmailPolicy::new(&[], &[], true);
+
+        assert_err!(policy.check(&email("Postmaster@domain.com")).await);
+        assert_ok!(policy.check(&email("ursula@domain.com")).await);
+        assert_ok!(EmailPolicy::default().check(&email("postmaster@domain.com")).await);
+    }
+
+    #[tokio::test]
+    async fn domains_without_mx_records_are_rejected() {
+        let policy = EmailPolicy::default().with_mx_resolver(Arc::new(StubResolver(false)));
+        assert_err!(policy.check(&email("ursula@domain.com")).await);
+
+        let policy = EmailPolicy::default().with_mx_resolver(Arc::new(StubResolver(true)));
+        assert_ok!(policy.check(&email("ursula@domain.com")).await);
+    }
+
+    #[tokio::test]
+    async fn a_failing_mx_lookup_lets_the_address_through() {
+        let policy = EmailPolicy::default().with_mx_resolver(Arc::new(FailingResolver));
+
+        assert_ok!(policy.check(&email("ursula@domain.com")).await);
+    }
+}
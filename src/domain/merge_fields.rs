@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+/// Replace the `{{ key }}` placeholders of a template with the subscriber's values.
+///
+/// `{{ key | fallback }}` renders the fallback when the subscriber has no value for `key`,
+/// unknown keys render as nothing. Values are HTML escaped when rendering an HTML body.
+pub fn render_merge_fields(template: &str, values: &HashMap<String, String>, escape_html: bool) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        rendered.push_str(&rest[..start]);
+
+        let placeholder = &rest[start + 2..end];
+        let (key, fallback) = match placeholder.split_once('|') {
+            Some((key, fallback)) => (key.trim(), fallback.trim()),
+            None => (placeholder.trim(), ""),
+        };
+        let value = values
+            .get(key)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(fallback);
+        if escape_html {
+            rendered.push_str(&htmlescape::encode_minimal(value));
+        } else {
+            rendered.push_str(value);
+        }
+
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::domain::merge_fields::render_merge_fields;
+
+    fn values() -> HashMap<String, String> {
+        vec![
+            ("name".to_string(), "Ursula".to_string()),
+            ("company".to_string(), "Smith & Sons".to_string()),
+            ("role".to_string(), "".to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        assert_eq!(
+            render_merge_fields("Hi {{name}}, how is {{ company }}?", &values(), false),
+            "Hi Ursula, how is Smith & Sons?",
+        );
+    }
+
+    #[test]
+    fn missing_values_use_the_fallback() {
+        assert_eq!(render_merge_fields("Dear {{ role | reader }}", &values(), false), "Dear reader");
+        assert_eq!(render_merge_fields("Dear {{ team | }}{{unknown}}!", &values(), false), "Dear !");
+    }
+
+    #[test]
+    fn values_are_escaped_in_html() {
+        assert_eq!(render_merge_fields("<b>{{company}}</b>", &values(), true), "<b>Smith &amp; Sons</b>");
+    }
+
+    #[test]
+    fn unterminated_placeholders_are_left_alone() {
+        assert_eq!(render_merge_fields("Hi {{name", &values(), false), "Hi {{name");
+    }
+}
@@ -0,0 +1,33 @@
+use chrono::NaiveTime;
+use crate::domain::subject_test::SubjectTest;
+
+pub struct NewIssue {
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub subject_test: Option<SubjectTest>,
+    /// Deliver at this wall clock time in each subscriber's time zone instead of right away.
+    pub local_send_time: Option<NaiveTime>,
+}
+
+impl NewIssue {
+    pub fn parse(
+        title: String,
+        text_content: String,
+        html_content: String,
+        subject_test: Option<SubjectTest>,
+        local_send_time: Option<NaiveTime>,
+    ) -> Result<Self, String> {
+        if title.trim().is_empty() {
+            return Err("The issue title cannot be empty.".into());
+        }
+        if text_content.trim().is_empty() || html_content.trim().is_empty() {
+            return Err("The issue needs both a plain text and an HTML body.".into());
+        }
+        if subject_test.is_some() && local_send_time.is_some() {
+            return Err("A subject line A/B test cannot be combined with delivery in local time.".into());
+        }
+
+        Ok(Self { title, text_content, html_content, subject_test, local_send_time })
+    }
+}
@@ -1,9 +1,14 @@
 use crate::domain::{
     subscriber_name::SubscriberName,
     subscriber_email::SubscriberEmail,
+    subscriber_time_zone::SubscriberTimeZone,
+    subscriber_attribute::AttributeValue,
 };
 
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
+    pub time_zone: Option<SubscriberTimeZone>,
+    /// Validated against the attribute definitions, which `TryFrom<FormData>` does not know about.
+    pub attributes: Vec<AttributeValue>,
 }
@@ -0,0 +1,135 @@
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+
+use crate::domain::merge_fields::render_merge_fields;
+use crate::domain::welcome_email::RenderedEmail;
+
+const REFERRAL_CODE_LENGTH: usize = 10;
+
+/// Generate a random referral code, short enough to be typed in.
+pub fn generate_referral_code() -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(REFERRAL_CODE_LENGTH)
+        .collect()
+}
+
+/// The signup page, with the referral code of the subscriber sharing it.
+pub fn share_url(base_url: &str, referral_code: &str) -> String {
+    format!("{}/?ref={}", base_url, referral_code)
+}
+
+/// Codes come from URLs people copy around, anything but letters and digits is dropped.
+pub fn clean_referral_code(code: &str) -> Option<String> {
+    let code: String = code.chars().filter(char::is_ascii_alphanumeric).collect();
+    if code.is_empty() || code.len() > REFERRAL_CODE_LENGTH {
+        None
+    } else {
+        Some(code)
+    }
+}
+
+/// The email sent to a referrer once `milestone` of their referrals confirmed.
+///
+/// On top of the referrer's merge fields, `{{ referral_count }}` and `{{ share_url }}` are available.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferralReward {
+    pub milestone: i32,
+    pub subject: String,
+    pub text_content: String,
+    pub html_content: String,
+}
+
+impl ReferralReward {
+    pub fn parse(milestone: &str, subject: &str, text_content: &str, html_content: &str) -> Result<ReferralReward, String> {
+        let milestone: i32 = milestone
+            .trim()
+            .parse()
+            .map_err(|_| format!("{} is not a number of referrals.", milestone.trim()))?;
+        if milestone < 1 {
+            return Err("The milestone must be at least one referral.".into());
+        }
+        if subject.trim().is_empty() {
+            return Err("The reward email needs a subject.".into());
+        }
+        if text_content.trim().is_empty() || html_content.trim().is_empty() {
+            return Err("The reward email needs both a plain text and an HTML body.".into());
+        }
+
+        Ok(Self {
+            milestone,
+            subject: subject.trim().to_owned(),
+            text_content: text_content.to_owned(),
+            html_content: html_content.to_owned(),
+        })
+    }
+
+    pub fn render(&self, values: &HashMap<String, String>) -> RenderedEmail {
+        RenderedEmail {
+            subject: render_merge_fields(&self.subject, values, false),
+            html_content: render_merge_fields(&self.html_content, values, true),
+            text_content: render_merge_fields(&self.text_content, values, false),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::referral::{clean_referral_code, generate_referral_code, share_url, ReferralReward};
+    use claim::{assert_err, assert_none};
+    use std::collections::HashMap;
+
+    #[test]
+    fn generated_codes_survive_cleaning() {
+        let code = generate_referral_code();
+
+        assert_eq!(code.len(), 10);
+        assert_eq!(clean_referral_code(&code), Some(code));
+    }
+
+    #[test]
+    fn codes_are_cleaned_of_stray_characters() {
+        assert_eq!(clean_referral_code(" aB3d9/ "), Some("aB3d9".to_string()));
+        assert_none!(clean_referral_code(""));
+        assert_none!(clean_referral_code("<>"));
+        assert_none!(clean_referral_code("abcdefghijklmnop"));
+    }
+
+    #[test]
+    fn the_share_url_points_to_the_signup_page() {
+        assert_eq!(share_url("https://example.com", "aB3d9"), "https://example.com/?ref=aB3d9");
+    }
+
+    #[test]
+    fn rewards_need_a_positive_milestone_and_content() {
+        assert_eq!(ReferralReward::parse(" 3 ", "Thanks", "Hi", "<p>Hi</p>").unwrap().milestone, 3);
+        assert_err!(ReferralReward::parse("0", "Thanks", "Hi", "<p>Hi</p>"));
+        assert_err!(ReferralReward::parse("three", "Thanks", "Hi", "<p>Hi</p>"));
+        assert_err!(ReferralReward::parse("3", "", "Hi", "<p>Hi</p>"));
+        assert_err!(ReferralReward::parse("3", "Thanks", "Hi", " "));
+    }
+
+    #[test]
+    fn rewards_fill_in_the_referral_count() {
+        let reward = ReferralReward::parse(
+            "3",
+            "{{ referral_count }} friends!",
+            "Share {{ share_url }}",
+            "<p>{{ name }}</p>",
+        ).unwrap();
+        let values: HashMap<String, String> = vec![
+            ("referral_count".to_string(), "3".to_string()),
+            ("share_url".to_string(), "https://example.com/?ref=aB3d9".to_string()),
+            ("name".to_string(), "<Ada>".to_string()),
+        ].into_iter().collect();
+
+        let email = reward.render(&values);
+
+        assert_eq!(email.subject, "3 friends!");
+        assert_eq!(email.text_content, "Share https://example.com/?ref=aB3d9");
+        assert_eq!(email.html_content, "<p>&lt;Ada&gt;</p>");
+    }
+}
@@ -0,0 +1,221 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use uuid::Uuid;
+
+/// What decides the winning subject line of an A/B test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubjectTestMetric {
+    OpenRate,
+    ClickRate,
+}
+
+impl SubjectTestMetric {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "open" => Ok(Self::OpenRate),
+            "click" => Ok(Self::ClickRate),
+            other => Err(format!("{} is not a valid test metric. Use either `open` or `click`.", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OpenRate => "open",
+            Self::ClickRate => "click",
+        }
+    }
+}
+
+/// Several subject lines for the same issue, each sent to a random sample of the confirmed list.
+///
+/// Variant `0` is always the issue title.
+#[derive(Debug)]
+pub struct SubjectTest {
+    subjects: Vec<String>,
+    sample_percentage: u8,
+    pub metric: SubjectTestMetric,
+    pub window: chrono::Duration,
+}
+
+impl SubjectTest {
+    pub fn parse(
+        subjects: Vec<String>,
+        sample_percentage: u8,
+        metric: SubjectTestMetric,
+        window: chrono::Duration,
+    ) -> Result<Self, String> {
+        if subjects.len() < 2 {
+            return Err("A subject test needs at least two subject lines.".into());
+        }
+        if subjects.iter().any(|s| s.trim().is_empty()) {
+            return Err("Subject lines cannot be empty.".into());
+        }
+        if sample_percentage == 0 || sample_percentage as usize * subjects.len() >= 100 {
+            return Err(format!(
+                "A {}% sample for each of the {} subject lines leaves nobody to send the winner to.",
+                sample_percentage, subjects.len()
+            ));
+        }
+
+        Ok(Self { subjects, sample_percentage, metric, window })
+    }
+
+    pub fn subjects(&self) -> &[String] {
+        &self.subjects
+    }
+
+    pub fn sample_percentage(&self) -> u8 {
+        self.sample_percentage
+    }
+
+    /// Shuffle the recipients and hand each variant its sample.
+    ///
+    /// Recipients that are not returned belong to the remainder, who will receive the winner.
+    pub fn assign_sample<R: Rng>(&self, mut recipients: Vec<Uuid>, rng: &mut R) -> Vec<(Uuid, i16)> {
+        recipients.shuffle(rng);
+        let sample_size = (recipients.len() * self.sample_percentage as usize).div_ceil(100);
+
+        recipients
+            .chunks(sample_size.max(1))
+            .take(self.subjects.len())
+            .enumerate()
+            .flat_map(|(variant, chunk)| chunk.iter().map(move |id| (*id, variant as i16)))
+            .collect()
+    }
+}
+
+/// How a single subject line performed with its sample.
+#[derive(Debug, Clone)]
+pub struct VariantResult {
+    pub variant: i16,
+    pub delivered: i64,
+    pub opened: i64,
+    pub clicked: i64,
+}
+
+impl VariantResult {
+    pub fn open_rate(&self) -> f64 {
+        rate(self.opened, self.delivered)
+    }
+
+    pub fn click_rate(&self) -> f64 {
+        rate(self.clicked, self.delivered)
+    }
+
+    pub fn rate(&self, metric: SubjectTestMetric) -> f64 {
+        match metric {
+            SubjectTestMetric::OpenRate => self.open_rate(),
+            SubjectTestMetric::ClickRate => self.click_rate(),
+        }
+    }
+}
+
+fn rate(count: i64, delivered: i64) -> f64 {
+    if delivered == 0 {
+        0.0
+    } else {
+        count as f64 / delivered as f64
+    }
+}
+
+/// The variant with the best rate for `metric`. Ties go to the lowest variant, i.e. the issue title.
+pub fn pick_winner(results: &[VariantResult], metric: SubjectTestMetric) -> i16 {
+    let mut winner: Option<&VariantResult> = None;
+    for result in results {
+        winner = match winner {
+            Some(best) if best.rate(metric) > result.rate(metric) => Some(best),
+            Some(best) if best.rate(metric) == result.rate(metric) && best.variant < result.variant => Some(best),
+            _ => Some(result),
+        };
+    }
+
+    winner.map(|w| w.variant).unwrap_or(0)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::subject_test::{pick_winner, SubjectTest, SubjectTestMetric, VariantResult};
+    use claim::{assert_err, assert_ok};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use uuid::Uuid;
+
+    fn subjects(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("Subject {}", i)).collect()
+    }
+
+    fn test(n: usize, sample_percentage: u8) -> SubjectTest {
+        SubjectTest::parse(subjects(n), sample_percentage, SubjectTestMetric::OpenRate, chrono::Duration::hours(1)).unwrap()
+    }
+
+    fn result(variant: i16, delivered: i64, opened: i64, clicked: i64) -> VariantResult {
+        VariantResult { variant, delivered, opened, clicked }
+    }
+
+    #[test]
+    fn a_single_subject_is_rejected() {
+        assert_err!(SubjectTest::parse(subjects(1), 10, SubjectTestMetric::OpenRate, chrono::Duration::hours(1)));
+    }
+
+    #[test]
+    fn samples_covering_the_whole_list_are_rejected() {
+        assert_err!(SubjectTest::parse(subjects(2), 50, SubjectTestMetric::OpenRate, chrono::Duration::hours(1)));
+        assert_err!(SubjectTest::parse(subjects(2), 0, SubjectTestMetric::OpenRate, chrono::Duration::hours(1)));
+        assert_ok!(SubjectTest::parse(subjects(2), 49, SubjectTestMetric::OpenRate, chrono::Duration::hours(1)));
+    }
+
+    #[test]
+    fn empty_subjects_are_rejected() {
+        let subjects = vec!["Subject".to_string(), " ".to_string()];
+        assert_err!(SubjectTest::parse(subjects, 10, SubjectTestMetric::ClickRate, chrono::Duration::hours(1)));
+    }
+
+    #[test]
+    fn each_variant_gets_its_share_of_the_list() {
+        let recipients: Vec<Uuid> = (0..100).map(|_| Uuid::new_v4()).collect();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let sample = test(2, 10).assign_sample(recipients, &mut rng);
+
+        assert_eq!(sample.len(), 20);
+        assert_eq!(sample.iter().filter(|(_, v)| *v == 0).count(), 10);
+        assert_eq!(sample.iter().filter(|(_, v)| *v == 1).count(), 10);
+    }
+
+    #[test]
+    fn small_lists_still_get_one_recipient_per_variant() {
+        let recipients: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let sample = test(2, 10).assign_sample(recipients, &mut rng);
+
+        assert_eq!(sample.len(), 2);
+    }
+
+    #[test]
+    fn an_empty_list_yields_an_empty_sample() {
+        let mut rng = StdRng::seed_from_u64(42);
+        assert!(test(2, 10).assign_sample(vec![], &mut rng).is_empty());
+    }
+
+    #[test]
+    fn the_best_rate_wins() {
+        let results = vec![result(0, 100, 10, 5), result(1, 100, 30, 1)];
+
+        assert_eq!(pick_winner(&results, SubjectTestMetric::OpenRate), 1);
+        assert_eq!(pick_winner(&results, SubjectTestMetric::ClickRate), 0);
+    }
+
+    #[test]
+    fn ties_go_to_the_issue_title() {
+        let results = vec![result(1, 100, 10, 0), result(0, 50, 5, 0)];
+
+        assert_eq!(pick_winner(&results, SubjectTestMetric::OpenRate), 0);
+    }
+
+    #[test]
+    fn no_results_fall_back_to_the_issue_title() {
+        assert_eq!(pick_winner(&[], SubjectTestMetric::OpenRate), 0);
+    }
+}
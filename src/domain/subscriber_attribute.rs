@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+/// Names taken by the signup form and by the built-in merge fields.
+const RESERVED_KEYS: &[&str] = &[
+    "email", "name", "time_zone", "source", "website", "form_token", "pow_nonce", "captcha_response",
+];
+const MAX_KEY_LENGTH: usize = 32;
+const MAX_TEXT_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttributeKind {
+    Text,
+    Number,
+    /// `YYYY-MM-DD`
+    Date,
+    /// One of the options of the attribute.
+    Enum,
+    Boolean,
+}
+
+impl AttributeKind {
+    pub fn parse(s: &str) -> Result<AttributeKind, String> {
+        match s.trim() {
+            "text" => Ok(AttributeKind::Text),
+            "number" => Ok(AttributeKind::Number),
+            "date" => Ok(AttributeKind::Date),
+            "enum" => Ok(AttributeKind::Enum),
+            "boolean" => Ok(AttributeKind::Boolean),
+            other => Err(format!("{} is not an attribute type.", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributeKind::Text => "text",
+            AttributeKind::Number => "number",
+            AttributeKind::Date => "date",
+            AttributeKind::Enum => "enum",
+            AttributeKind::Boolean => "boolean",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AttributeDefinition {
+    pub attribute_id: Uuid,
+    /// Form field and merge field name.
+    pub key: String,
+    pub label: String,
+    pub kind: AttributeKind,
+    pub options: Vec<String>,
+    pub required: bool,
+}
+
+impl AttributeDefinition {
+    /// Validate a submitted value and bring it to the form it is stored and compared in.
+    pub fn normalize(&self, raw: &str) -> Result<String, String> {
+        let raw = raw.trim();
+        let invalid = |expected: &str| format!("{} must be {}.", self.label, expected);
+        match self.kind {
+            AttributeKind::Text => {
+                if raw.graphemes(true).count() > MAX_TEXT_LENGTH {
+                    return Err(invalid("at most 256 characters long"));
+                }
+                Ok(raw.to_owned())
+            }
+            AttributeKind::Number => match raw.parse::<f64>() {
+                Ok(number) if number.is_finite() => Ok(number.to_string()),
+                _ => Err(invalid("a number")),
+            },
+            AttributeKind::Date => NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .map_err(|_| invalid("a date formatted as YYYY-MM-DD")),
+            AttributeKind::Enum => self
+                .options
+                .iter()
+                .find(|option| option.eq_ignore_ascii_case(raw))
+                .cloned()
+                .ok_or_else(|| invalid(&format!("one of {}", self.options.join(", ")))),
+            AttributeKind::Boolean => match raw.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok("true".into()),
+                "false" | "no" | "off" | "0" => Ok("false".into()),
+                _ => Err(invalid("yes or no")),
+            },
+        }
+    }
+}
+
+/// An attribute as submitted on the admin form, before it gets an id.
+#[derive(Debug)]
+pub struct NewAttribute {
+    pub key: String,
+    pub label: String,
+    pub kind: AttributeKind,
+    pub options: Vec<String>,
+    pub required: bool,
+}
+
+impl NewAttribute {
+    /// `options` is a comma separated list, only used by `enum` attributes.
+    pub fn parse(key: &str, label: &str, kind: &str, options: &str, required: bool) -> Result<Self, String> {
+        let key = key.trim().to_lowercase();
+        let is_valid_key = key.starts_with(|c: char| c.is_ascii_lowercase())
+            && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !is_valid_key || key.len() > MAX_KEY_LENGTH {
+            return Err(
+                "The key must start with a letter and hold at most 32 letters, digits or underscores.".into()
+            );
+        }
+        if RESERVED_KEYS.contains(&key.as_str()) {
+            return Err(format!("{} is a reserved key.", key));
+        }
+
+        let label = label.trim();
+        if label.is_empty() {
+            return Err("The label cannot be empty.".into());
+        }
+
+        let kind = AttributeKind::parse(kind)?;
+        let mut options: Vec<String> = options
+            .split(',')
+            .map(str::trim)
+            .filter(|option| !option.is_empty())
+            .map(str::to_owned)
+            .collect();
+        options.dedup();
+        match kind {
+            AttributeKind::Enum if options.is_empty() => {
+                return Err("An enum attribute needs at least one option.".into());
+            }
+            AttributeKind::Enum => {}
+            _ => options.clear(),
+        }
+
+        Ok(Self { key, label: label.to_owned(), kind, options, required })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AttributeValue {
+    pub attribute_id: Uuid,
+    pub value: String,
+}
+
+/// Validate the submitted values of every defined attribute, keyed by attribute key.
+///
+/// Empty fields are left out, unless the attribute is required. Keys that match no definition are ignored.
+pub fn parse_attribute_values(
+    definitions: &[AttributeDefinition],
+    submitted: &HashMap<String, String>,
+) -> Result<Vec<AttributeValue>, String> {
+    let mut values = Vec::new();
+    for definition in definitions {
+        let raw = submitted.get(&definition.key).map(|s| s.trim()).unwrap_or("");
+        if raw.is_empty() {
+            // A boolean sent without a value is a no, whereas a checkbox left unchecked is not sent at all
+            if definition.kind == AttributeKind::Boolean && submitted.contains_key(&definition.key) {
+                values.push(AttributeValue { attribute_id: definition.attribute_id, value: "false".into() });
+            } else if definition.required {
+                return Err(format!("{} is required.", definition.label));
+            }
+            continue;
+        }
+        values.push(AttributeValue { attribute_id: definition.attribute_id, value: definition.normalize(raw)? });
+    }
+    Ok(values)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::domain::subscriber_attribute::{
+        parse_attribute_values, AttributeDefinition, AttributeKind, NewAttribute,
+    };
+    use claim::{assert_err, assert_ok};
+    use uuid::Uuid;
+
+    fn definition(key: &str, kind: AttributeKind, required: bool) -> AttributeDefinition {
+        AttributeDefinition {
+            attribute_id: Uuid::new_v4(),
+            key: key.into(),
+            label: key.into(),
+            kind,
+            options: vec!["Engineering".into(), "Sales".into()],
+            required,
+        }
+    }
+
+    #[test]
+    fn values_are_normalized_by_type() {
+        assert_eq!(definition("seats", AttributeKind::Number, false).normalize(" 12.50 "), Ok("12.5".into()));
+        assert_eq!(definition("born", AttributeKind::Date, false).normalize("1990-02-03"), Ok("1990-02-03".into()));
+        assert_eq!(definition("role", AttributeKind::Enum, false).normalize("sales"), Ok("Sales".into()));
+        assert_eq!(definition("beta", AttributeKind::Boolean, false).normalize("Yes"), Ok("true".into()));
+        assert_eq!(definition("company", AttributeKind::Text, false).normalize(" Acme "), Ok("Acme".into()));
+    }
+
+    #[test]
+    fn values_of_the_wrong_type_are_rejected() {
+        assert_err!(definition("seats", AttributeKind::Number, false).normalize("a dozen"));
+        assert_err!(definition("seats", AttributeKind::Number, false).normalize("NaN"));
+        assert_err!(definition("born", AttributeKind::Date, false).normalize("03/02/1990"));
+        assert_err!(definition("role", AttributeKind::Enum, false).normalize("Marketing"));
+        assert_err!(definition("beta", AttributeKind::Boolean, false).normalize("maybe"));
+        assert_err!(definition("company", AttributeKind::Text, false).normalize(&"a".repeat(257)));
+    }
+
+    #[test]
+    fn required_attributes_must_be_submitted() {
+        let definitions = vec![definition("company", AttributeKind::Text, true)];
+
+        assert_err!(parse_attribute_values(&definitions, &HashMap::new()));
+        let submitted: HashMap<_, _> = vec![("company".to_string(), "Acme".to_string())].into_iter().collect();
+        assert_eq!(parse_attribute_values(&definitions, &submitted).unwrap()[0].value, "Acme");
+    }
+
+    #[test]
+    fn optional_attributes_left_empty_are_skipped() {
+        let definitions = vec![definition("company", AttributeKind::Text, false)];
+        let submitted: HashMap<_, _> = vec![("company".to_string(), " ".to_string()), ("other".to_string(), "x".to_string())]
+            .into_iter()
+            .collect();
+
+        assert!(parse_attribute_values(&definitions, &submitted).unwrap().is_empty());
+    }
+
+    #[test]
+    fn keys_must_be_identifiers_and_not_reserved() {
+        assert_ok!(NewAttribute::parse("company_size", "Company size", "number", "", false));
+        assert_err!(NewAttribute::parse("2fa", "2FA", "boolean", "", false));
+        assert_err!(NewAttribute::parse("job title", "Job title", "text", "", false));
+        assert_err!(NewAttribute::parse("email", "Email", "text", "", false));
+    }
+
+    #[test]
+    fn enum_attributes_need_options() {
+        assert_err!(NewAttribute::parse("role", "Role", "enum", " , ", false));
+        let attribute = NewAttribute::parse("role", "Role", "enum", "Engineering, Sales", false).unwrap();
+        assert_eq!(attribute.options, vec!["Engineering", "Sales"]);
+    }
+}
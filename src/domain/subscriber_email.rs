@@ -1,27 +1,51 @@
 use validator::validate_email;
 
+/// An email address as the subscriber typed it, along with its canonical form.
 #[derive(Debug)]
-pub struct SubscriberEmail(String);
+pub struct SubscriberEmail {
+    display: String,
+    normalized: String,
+}
 
 impl SubscriberEmail {
     pub fn parse(s: String) -> Result<SubscriberEmail, String> {
-        if validate_email(&s) {
-            Ok(Self(s))
-        } else {
-            Err(format!("{} is not a valid subscriber email.", s))
-        }
+        let display = s.trim();
+        let normalized = normalize(display)
+            .filter(|normalized| validate_email(normalized))
+            .ok_or_else(|| format!("{} is not a valid subscriber email.", s))?;
+
+        Ok(Self { display: display.to_string(), normalized })
+    }
+
+    /// The address with its domain lowercased and converted to punycode.
+    ///
+    /// Two addresses are the same subscriber when their normalized forms match, ignoring case.
+    pub fn normalized(&self) -> &str {
+        &self.normalized
     }
 }
 
+fn normalize(email: &str) -> Option<String> {
+    let (local, domain) = email.rsplit_once('@')?;
+    if local.is_empty() || domain.is_empty() {
+        return None;
+    }
+    // `domain_to_ascii` lowercases ASCII labels as well
+    let domain = idna::domain_to_ascii(domain).ok()?;
+
+    Some(format!("{}@{}", local, domain))
+}
+
+/// The form used for sending, as the subscriber typed it minus surrounding whitespace.
 impl AsRef<str> for SubscriberEmail {
     fn as_ref(&self) -> &str {
-        &self.0
+        &self.display
     }
 }
 
 impl std::fmt::Display for SubscriberEmail {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.fmt(f)
+        self.display.fmt(f)
     }
 }
 
@@ -64,4 +88,24 @@ mod tests {
         let email = "@domain.com".to_string();
         assert_err!(SubscriberEmail::parse(email));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn surrounding_whitespace_is_trimmed() {
+        let email = SubscriberEmail::parse("  ursula@domain.com \n".to_string()).unwrap();
+        assert_eq!(email.as_ref(), "ursula@domain.com");
+    }
+
+    #[test]
+    fn the_domain_is_lowercased_but_the_display_form_is_kept() {
+        let email = SubscriberEmail::parse("Ursula@Domain.COM".to_string()).unwrap();
+        assert_eq!(email.normalized(), "Ursula@domain.com");
+        assert_eq!(email.as_ref(), "Ursula@Domain.COM");
+    }
+
+    #[test]
+    fn internationalized_domains_are_converted_to_punycode() {
+        let email = SubscriberEmail::parse("ursula@Bücher.example".to_string()).unwrap();
+        assert_eq!(email.normalized(), "ursula@xn--bcher-kva.example");
+        assert_eq!(email.as_ref(), "ursula@Bücher.example");
+    }
+}
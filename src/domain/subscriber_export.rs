@@ -0,0 +1,165 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+
+use crate::domain::subscriber_attribute::AttributeDefinition;
+use crate::domain::subscription_status::SubscriptionStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Result<ExportFormat, String> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "csv" => Ok(ExportFormat::Csv),
+            "ndjson" | "jsonl" => Ok(ExportFormat::Ndjson),
+            other => Err(format!("{} is not a supported export format.", other)),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Which subscribers to export. Every criterion is optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportFilter {
+    pub status: Option<SubscriptionStatus>,
+    pub tag: Option<String>,
+    /// Subscribed at or after.
+    pub subscribed_from: Option<DateTime<Utc>>,
+    /// Subscribed strictly before.
+    pub subscribed_until: Option<DateTime<Utc>>,
+    /// Key and normalized value of a custom attribute.
+    pub attribute: Option<(String, String)>,
+}
+
+impl ExportFilter {
+    /// Dates are `YYYY-MM-DD` in UTC, both ends of the range are inclusive.
+    pub fn parse(status: &str, tag: &str, from: &str, to: &str) -> Result<ExportFilter, String> {
+        let status = match status.trim() {
+            "" => None,
+            status => Some(SubscriptionStatus::parse(status)?),
+        };
+        let tag = match tag.trim() {
+            "" => None,
+            tag => Some(tag.to_owned()),
+        };
+        let subscribed_from = parse_date(from)?.map(start_of_day);
+        let subscribed_until = parse_date(to)?.map(|date| start_of_day(date) + Duration::days(1));
+
+        if let (Some(from), Some(until)) = (subscribed_from, subscribed_until) {
+            if from >= until {
+                return Err("The start of the date range must not be after its end.".into());
+            }
+        }
+
+        Ok(Self { status, tag, subscribed_from, subscribed_until, attribute: None })
+    }
+
+    /// Only keep the subscribers whose `key` attribute is `value`, compared in its normalized form.
+    pub fn with_attribute(
+        mut self,
+        definitions: &[AttributeDefinition],
+        key: &str,
+        value: &str,
+    ) -> Result<ExportFilter, String> {
+        let key = key.trim();
+        if key.is_empty() {
+            return Ok(self);
+        }
+        let definition = definitions
+            .iter()
+            .find(|definition| definition.key == key)
+            .ok_or_else(|| format!("{} is not a subscriber attribute.", key))?;
+        self.attribute = Some((definition.key.clone(), definition.normalize(value)?));
+        Ok(self)
+    }
+}
+
+fn parse_date(s: &str) -> Result<Option<NaiveDate>, String> {
+    match s.trim() {
+        "" => Ok(None),
+        s => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| format!("{} is not a valid date, use YYYY-MM-DD.", s)),
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms(0, 0, 0))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::subscriber_attribute::{AttributeDefinition, AttributeKind};
+    use crate::domain::subscriber_export::{ExportFilter, ExportFormat};
+    use crate::domain::subscription_status::SubscriptionStatus;
+    use chrono::{TimeZone, Utc};
+    use claim::assert_err;
+    use uuid::Uuid;
+
+    #[test]
+    fn an_empty_filter_matches_everyone() {
+        assert_eq!(ExportFilter::parse("", " ", "", ""), Ok(ExportFilter::default()));
+    }
+
+    #[test]
+    fn the_date_range_includes_both_ends() {
+        let filter = ExportFilter::parse("confirmed", "vip", "2022-01-01", "2022-01-31").unwrap();
+
+        assert_eq!(filter.status, Some(SubscriptionStatus::Confirmed));
+        assert_eq!(filter.tag.as_deref(), Some("vip"));
+        assert_eq!(filter.subscribed_from, Some(Utc.ymd(2022, 1, 1).and_hms(0, 0, 0)));
+        assert_eq!(filter.subscribed_until, Some(Utc.ymd(2022, 2, 1).and_hms(0, 0, 0)));
+    }
+
+    #[test]
+    fn invalid_criteria_are_rejected() {
+        assert_err!(ExportFilter::parse("lapsed", "", "", ""));
+        assert_err!(ExportFilter::parse("", "", "01/02/2022", ""));
+        assert_err!(ExportFilter::parse("", "", "2022-02-01", "2022-01-01"));
+    }
+
+    #[test]
+    fn attribute_criteria_are_normalized_by_type() {
+        let definitions = vec![AttributeDefinition {
+            attribute_id: Uuid::new_v4(),
+            key: "beta".into(),
+            label: "Beta tester".into(),
+            kind: AttributeKind::Boolean,
+            options: vec![],
+            required: false,
+        }];
+        let filter = ExportFilter::default();
+
+        assert_eq!(
+            filter.clone().with_attribute(&definitions, "beta", "yes").unwrap().attribute,
+            Some(("beta".into(), "true".into())),
+        );
+        assert_eq!(filter.clone().with_attribute(&definitions, "", "yes"), Ok(ExportFilter::default()));
+        assert_err!(filter.clone().with_attribute(&definitions, "beta", "perhaps"));
+        assert_err!(filter.with_attribute(&definitions, "company", "Acme"));
+    }
+
+    #[test]
+    fn formats_are_parsed() {
+        assert_eq!(ExportFormat::parse(""), Ok(ExportFormat::Csv));
+        assert_eq!(ExportFormat::parse("NDJSON"), Ok(ExportFormat::Ndjson));
+        assert_err!(ExportFormat::parse("xlsx"));
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::domain::subscriber_name::SubscriberName;
+use crate::domain::subscription_status::SubscriptionStatus;
+
+/// Which CSV header holds each field. Only the email column is required, empty names are not mapped.
+#[derive(Debug, Clone)]
+pub struct ColumnMapping {
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub tags: String,
+    pub subscribed_at: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            email: "email".into(),
+            name: "name".into(),
+            status: "status".into(),
+            tags: "tags".into(),
+            subscribed_at: "subscribed_at".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportStatus {
+    Confirmed,
+    Pending,
+    Unsubscribed,
+}
+
+impl ImportStatus {
+    /// Accepts our own statuses as well as the usual names other tools export.
+    ///
+    /// Rows without a status are taken to be confirmed subscribers.
+    pub fn parse(s: &str) -> Result<ImportStatus, String> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "confirmed" | "subscribed" | "active" => Ok(ImportStatus::Confirmed),
+            "pending" | "pending_confirmation" | "unconfirmed" => Ok(ImportStatus::Pending),
+            "unsubscribed" | "inactive" => Ok(ImportStatus::Unsubscribed),
+            other => Err(format!("{} is not a known subscription status.", other)),
+        }
+    }
+
+    pub fn status(&self) -> SubscriptionStatus {
+        match self {
+            ImportStatus::Confirmed => SubscriptionStatus::Confirmed,
+            ImportStatus::Pending => SubscriptionStatus::PendingConfirmation,
+            ImportStatus::Unsubscribed => SubscriptionStatus::Unsubscribed,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ImportRow {
+    /// Line of the CSV input, for reporting.
+    pub line: u64,
+    pub email: SubscriberEmail,
+    pub name: SubscriberName,
+    pub status: ImportStatus,
+    pub tags: Vec<String>,
+    pub subscribed_at: Option<DateTime<Utc>>,
+}
+
+/// A row that will not be imported, and why.
+#[derive(Debug)]
+pub struct SkippedRow {
+    pub line: u64,
+    pub email: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedImport {
+    pub valid: Vec<ImportRow>,
+    pub invalid: Vec<SkippedRow>,
+    pub duplicates: Vec<SkippedRow>,
+}
+
+impl ParsedImport {
+    /// Move the valid rows matching `is_duplicate` to the duplicates, e.g. the addresses already subscribed.
+    pub fn mark_duplicates(&mut self, reason: &str, is_duplicate: impl Fn(&ImportRow) -> bool) {
+        let (duplicates, valid) = std::mem::take(&mut self.valid).into_iter().partition(is_duplicate);
+        self.valid = valid;
+        self.duplicates.extend(skipped(duplicates, reason));
+    }
+
+    /// Move the valid rows matching `is_invalid` to the invalid ones.
+    pub fn mark_invalid(&mut self, reason: &str, is_invalid: impl Fn(&ImportRow) -> bool) {
+        let (invalid, valid) = std::mem::take(&mut self.valid).into_iter().partition(is_invalid);
+        self.valid = valid;
+        self.invalid.extend(skipped(invalid, reason));
+    }
+}
+
+fn skipped(rows: Vec<ImportRow>, reason: &str) -> impl Iterator<Item = SkippedRow> + '_ {
+    rows.into_iter().map(move |row| SkippedRow {
+        line: row.line,
+        email: row.email.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+
+/// Validate every row of a CSV export with a header line.
+///
+/// Fails only when the header does not fit `mapping`; bad rows are reported in the result.
+pub fn parse_import(input: &str, mapping: &ColumnMapping) -> Result<ParsedImport, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(input.as_bytes());
+
+    let headers = reader.headers().map_err(|e| format!("The CSV header cannot be read: {}", e))?.clone();
+    let column = |name: &str| -> Result<Option<usize>, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Ok(None);
+        }
+        headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(name))
+            .map(Some)
+            .ok_or_else(|| format!("The CSV has no {} column.", name))
+    };
+    let email_column = column(&mapping.email)?.ok_or("The email column must be mapped.")?;
+    let name_column = column(&mapping.name)?;
+    let status_column = column(&mapping.status)?;
+    let tags_column = column(&mapping.tags)?;
+    let subscribed_at_column = column(&mapping.subscribed_at)?;
+
+    let mut parsed = ParsedImport::default();
+    // Normalized, lowercased address -> line it was first seen on
+    let mut seen: HashMap<String, u64> = HashMap::new();
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                parsed.invalid.push(SkippedRow { line, email: String::new(), reason: e.to_string() });
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        let field = |column: Option<usize>| column.and_then(|i| record.get(i)).unwrap_or("");
+        let raw_email = field(Some(email_column));
+
+        match parse_row(
+            line,
+            raw_email,
+            field(name_column),
+            field(status_column),
+            field(tags_column),
+            field(subscribed_at_column),
+        ) {
+            Ok(row) => {
+                let key = row.email.normalized().to_lowercase();
+                match seen.get(&key) {
+                    Some(first_line) => parsed.duplicates.push(SkippedRow {
+                        line,
+                        email: raw_email.to_string(),
+                        reason: format!("Same address as line {}.", first_line),
+                    }),
+                    None => {
+                        seen.insert(key, line);
+                        parsed.valid.push(row);
+                    }
+                }
+            }
+            Err(reason) => parsed.invalid.push(SkippedRow { line, email: raw_email.to_string(), reason }),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_row(
+    line: u64,
+    email: &str,
+    name: &str,
+    status: &str,
+    tags: &str,
+    subscribed_at: &str,
+) -> Result<ImportRow, String> {
+    let email = SubscriberEmail::parse(email.to_string())?;
+    // Other tools rarely require a name, the local part stands in for a missing one
+    let name = match name {
+        "" => email.as_ref().rsplit_once('@').map(|(local, _)| local).unwrap_or_default(),
+        name => name,
+    };
+    let name = SubscriberName::parse(name.to_string())?;
+    let status = ImportStatus::parse(status)?;
+    let mut tags: Vec<String> = tags
+        .split(&[',', ';'][..])
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_owned)
+        .collect();
+    tags.sort();
+    tags.dedup();
+    let subscribed_at = match subscribed_at {
+        "" => None,
+        s => Some(parse_timestamp(s)?),
+    };
+
+    Ok(ImportRow { line, email, name, status, tags, subscribed_at })
+}
+
+/// RFC 3339, or a UTC date with an optional time.
+fn parse_timestamp(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").map(|t| Utc.from_utc_datetime(&t)))
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|d| Utc.from_utc_datetime(&d.and_hms(0, 0, 0))))
+        .map_err(|_| format!("{} is not a valid date.", s))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::subscriber_import::{parse_import, ColumnMapping, ImportStatus};
+    use chrono::{TimeZone, Utc};
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn valid_rows_are_parsed() {
+        let csv = "email,name,status,tags,subscribed_at\n\
+                   ursula@domain.com,Ursula,unsubscribed,\"books, sci-fi\",2021-03-04\n";
+
+        let parsed = parse_import(csv, &ColumnMapping::default()).unwrap();
+
+        assert_eq!(parsed.valid.len(), 1);
+        let row = &parsed.valid[0];
+        assert_eq!(row.line, 2);
+        assert_eq!(row.email.as_ref(), "ursula@domain.com");
+        assert_eq!(row.name.as_ref(), "Ursula");
+        assert_eq!(row.status, ImportStatus::Unsubscribed);
+        assert_eq!(row.tags, vec!["books", "sci-fi"]);
+        assert_eq!(row.subscribed_at, Some(Utc.ymd(2021, 3, 4).and_hms(0, 0, 0)));
+    }
+
+    #[test]
+    fn columns_are_mapped_by_header_name() {
+        let csv = "Full Name,E-mail\nUrsula,ursula@domain.com\n";
+        let mapping = ColumnMapping {
+            email: "e-mail".into(),
+            name: "Full Name".into(),
+            status: "".into(),
+            tags: "".into(),
+            subscribed_at: "".into(),
+        };
+
+        let parsed = parse_import(csv, &mapping).unwrap();
+
+        assert_eq!(parsed.valid[0].email.as_ref(), "ursula@domain.com");
+        assert_eq!(parsed.valid[0].status, ImportStatus::Confirmed);
+    }
+
+    #[test]
+    fn a_mapped_column_missing_from_the_header_is_an_error() {
+        assert_err!(parse_import("email\nursula@domain.com\n", &ColumnMapping::default()));
+    }
+
+    #[test]
+    fn a_missing_name_falls_back_to_the_local_part() {
+        let mapping = ColumnMapping {
+            email: "email".into(),
+            name: "".into(),
+            status: "".into(),
+            tags: "".into(),
+            subscribed_at: "".into(),
+        };
+
+        let parsed = parse_import("email\nursula@domain.com\n", &mapping).unwrap();
+
+        assert_eq!(parsed.valid[0].name.as_ref(), "ursula");
+    }
+
+    #[test]
+    fn invalid_rows_are_reported_with_their_line() {
+        let csv = "email,name,status,tags,subscribed_at\n\
+                   not-an-email,Ursula,,,\n\
+                   ursula@domain.com,Ursula,maybe,,\n\
+                   le.guin@domain.com,Le Guin,,,yesterday\n";
+
+        let parsed = parse_import(csv, &ColumnMapping::default()).unwrap();
+
+        assert!(parsed.valid.is_empty());
+        let lines: Vec<_> = parsed.invalid.iter().map(|row| row.line).collect();
+        assert_eq!(lines, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn repeated_addresses_are_duplicates() {
+        let csv = "email,name,status,tags,subscribed_at\n\
+                   ursula@domain.com,Ursula,,,\n\
+                   Ursula@DOMAIN.com,Ursula,,,\n";
+
+        let parsed = parse_import(csv, &ColumnMapping::default()).unwrap();
+
+        assert_eq!(parsed.valid.len(), 1);
+        assert_eq!(parsed.duplicates.len(), 1);
+        assert_eq!(parsed.duplicates[0].line, 3);
+    }
+
+    #[test]
+    fn statuses_from_other_tools_are_understood() {
+        assert_eq!(ImportStatus::parse("Subscribed"), Ok(ImportStatus::Confirmed));
+        assert_eq!(ImportStatus::parse("unconfirmed"), Ok(ImportStatus::Pending));
+        assert_ok!(ImportStatus::parse(""));
+        assert_err!(ImportStatus::parse("bounced"));
+    }
+}
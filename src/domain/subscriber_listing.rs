@@ -0,0 +1,200 @@
+use chrono::DateTime;
+use uuid::Uuid;
+
+use crate::domain::subscriber_export::ExportFilter;
+use crate::domain::subscription_status::SubscriptionStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortColumn {
+    Email,
+    Name,
+    Status,
+    SubscribedAt,
+}
+
+impl SortColumn {
+    pub fn parse(s: &str) -> Result<SortColumn, String> {
+        match s.trim() {
+            "email" => Ok(SortColumn::Email),
+            "name" => Ok(SortColumn::Name),
+            "status" => Ok(SortColumn::Status),
+            "" | "subscribed_at" => Ok(SortColumn::SubscribedAt),
+            other => Err(format!("Subscribers cannot be sorted by {}.", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortColumn::Email => "email",
+            SortColumn::Name => "name",
+            SortColumn::Status => "status",
+            SortColumn::SubscribedAt => "subscribed_at",
+        }
+    }
+
+    /// Whether `sort_key` can be the value of this column in a cursor.
+    fn accepts(&self, sort_key: &str) -> bool {
+        match self {
+            SortColumn::Email | SortColumn::Name => true,
+            SortColumn::Status => SubscriptionStatus::parse(sort_key).is_ok(),
+            SortColumn::SubscribedAt => DateTime::parse_from_rfc3339(sort_key).is_ok(),
+        }
+    }
+
+    /// Newest subscribers first, statuses in the order of the lifecycle, everything else alphabetically.
+    fn default_direction(&self) -> SortDirection {
+        match self {
+            SortColumn::SubscribedAt => SortDirection::Descending,
+            _ => SortDirection::Ascending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortDirection::Ascending => "asc",
+            SortDirection::Descending => "desc",
+        }
+    }
+
+    pub fn reversed(&self) -> SortDirection {
+        match self {
+            SortDirection::Ascending => SortDirection::Descending,
+            SortDirection::Descending => SortDirection::Ascending,
+        }
+    }
+}
+
+/// Where the next page starts: the sort key of the last row shown and its id, which breaks ties.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListCursor {
+    pub sort_key: String,
+    pub id: Uuid,
+}
+
+impl ListCursor {
+    pub fn encode(&self) -> String {
+        format!("{}.{}", hex::encode(&self.sort_key), self.id)
+    }
+
+    pub fn parse(s: &str) -> Result<ListCursor, String> {
+        let invalid = || "The page link is invalid.".to_string();
+        let (sort_key, id) = s.split_once('.').ok_or_else(invalid)?;
+        let sort_key = hex::decode(sort_key).ok().and_then(|key| String::from_utf8(key).ok()).ok_or_else(invalid)?;
+        let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+        Ok(Self { sort_key, id })
+    }
+}
+
+/// One page of the admin subscriber browser.
+#[derive(Debug)]
+pub struct SubscriberListQuery {
+    /// Start of an email address or of a name.
+    pub search: Option<String>,
+    /// Status, tag and signup date criteria, shared with exports.
+    pub filter: ExportFilter,
+    pub sort: SortColumn,
+    pub direction: SortDirection,
+    pub after: Option<ListCursor>,
+}
+
+impl SubscriberListQuery {
+    pub fn parse(
+        search: &str,
+        filter: ExportFilter,
+        sort: &str,
+        direction: &str,
+        after: &str,
+    ) -> Result<SubscriberListQuery, String> {
+        let search = match search.trim() {
+            "" => None,
+            search => Some(search.to_owned()),
+        };
+        let sort = SortColumn::parse(sort)?;
+        let direction = match direction.trim() {
+            "" => sort.default_direction(),
+            "asc" => SortDirection::Ascending,
+            "desc" => SortDirection::Descending,
+            other => return Err(format!("{} is not a sort direction.", other)),
+        };
+        let after = match after.trim() {
+            "" => None,
+            after => Some(ListCursor::parse(after)?),
+        };
+        if matches!(&after, Some(cursor) if !sort.accepts(&cursor.sort_key)) {
+            return Err("The page link is invalid.".to_string());
+        }
+
+        Ok(Self { search, filter, sort, direction, after })
+    }
+
+    /// A `LIKE` pattern matching values that start with the search, which may contain wildcards itself.
+    pub fn search_pattern(&self) -> Option<String> {
+        self.search.as_ref().map(|search| {
+            let escaped = search.to_lowercase().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("{}%", escaped)
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::subscriber_export::ExportFilter;
+    use crate::domain::subscriber_listing::{ListCursor, SortColumn, SortDirection, SubscriberListQuery};
+    use claim::assert_err;
+    use uuid::Uuid;
+
+    #[test]
+    fn the_newest_subscribers_come_first_by_default() {
+        let query = SubscriberListQuery::parse("", ExportFilter::default(), "", "", "").unwrap();
+
+        assert_eq!(query.sort, SortColumn::SubscribedAt);
+        assert_eq!(query.direction, SortDirection::Descending);
+        assert_eq!(query.search_pattern(), None);
+    }
+
+    #[test]
+    fn other_columns_sort_alphabetically_by_default() {
+        let query = SubscriberListQuery::parse("", ExportFilter::default(), "email", "", "").unwrap();
+
+        assert_eq!(query.direction, SortDirection::Ascending);
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let cursor = ListCursor { sort_key: "ada.lovelace@example.com".into(), id: Uuid::new_v4() };
+
+        assert_eq!(ListCursor::parse(&cursor.encode()), Ok(cursor));
+        assert_err!(ListCursor::parse("not-a-cursor"));
+    }
+
+    #[test]
+    fn cursors_must_fit_the_sort_column() {
+        let cursor = ListCursor { sort_key: "ada.lovelace@example.com".into(), id: Uuid::new_v4() }.encode();
+
+        assert_err!(SubscriberListQuery::parse("", ExportFilter::default(), "subscribed_at", "", &cursor));
+        assert_err!(SubscriberListQuery::parse("", ExportFilter::default(), "status", "", &cursor));
+        assert!(SubscriberListQuery::parse("", ExportFilter::default(), "email", "", &cursor).is_ok());
+    }
+
+    #[test]
+    fn wildcards_in_the_search_are_escaped() {
+        let query = SubscriberListQuery::parse(" Ada_L% ", ExportFilter::default(), "", "", "").unwrap();
+
+        assert_eq!(query.search_pattern().as_deref(), Some("ada\\_l\\%%"));
+    }
+
+    #[test]
+    fn unknown_sorts_are_rejected() {
+        assert_err!(SubscriberListQuery::parse("", ExportFilter::default(), "password", "", ""));
+        assert_err!(SubscriberListQuery::parse("", ExportFilter::default(), "email", "up", ""));
+    }
+}
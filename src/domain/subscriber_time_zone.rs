@@ -0,0 +1,102 @@
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// An IANA time zone, e.g. `Europe/Berlin`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubscriberTimeZone(Tz);
+
+impl SubscriberTimeZone {
+    pub fn parse(s: &str) -> Result<SubscriberTimeZone, String> {
+        s.trim()
+            .parse::<Tz>()
+            .map(Self)
+            .map_err(|_| format!("{} is not a valid IANA time zone.", s))
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    /// The next instant, at or after `now`, at which the wall clock in this time zone reads `local_time`.
+    ///
+    /// Local times skipped by a daylight saving change are moved forward by the size of the gap.
+    pub fn next_occurrence(&self, local_time: NaiveTime, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.with_timezone(&self.0).date().naive_local();
+
+        (0..=2)
+            .filter_map(|days| {
+                let local = (today + Duration::days(days)).and_time(local_time);
+                self.0.from_local_datetime(&local).earliest()
+                    .or_else(|| self.0.from_local_datetime(&(local + Duration::hours(1))).earliest())
+            })
+            .map(|local| local.with_timezone(&Utc))
+            .find(|instant| *instant >= now)
+            .unwrap_or(now)
+    }
+}
+
+impl Default for SubscriberTimeZone {
+    fn default() -> Self {
+        Self(Tz::UTC)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::subscriber_time_zone::SubscriberTimeZone;
+    use chrono::{NaiveTime, TimeZone, Utc};
+    use claim::{assert_err, assert_ok};
+
+    fn eight() -> NaiveTime {
+        NaiveTime::from_hms(8, 0, 0)
+    }
+
+    #[test]
+    fn iana_names_are_accepted() {
+        assert_ok!(SubscriberTimeZone::parse("Europe/Berlin"));
+        assert_ok!(SubscriberTimeZone::parse("America/Argentina/Buenos_Aires"));
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        assert_err!(SubscriberTimeZone::parse("Mars/Olympus_Mons"));
+        assert_err!(SubscriberTimeZone::parse(""));
+    }
+
+    #[test]
+    fn a_local_time_later_today_is_scheduled_today() {
+        let tz = SubscriberTimeZone::parse("Europe/Berlin").unwrap();
+        let now = Utc.ymd(2022, 1, 20).and_hms(5, 0, 0);
+
+        assert_eq!(tz.next_occurrence(eight(), now), Utc.ymd(2022, 1, 20).and_hms(7, 0, 0));
+    }
+
+    #[test]
+    fn a_local_time_already_past_is_scheduled_tomorrow() {
+        let tz = SubscriberTimeZone::parse("America/New_York").unwrap();
+        let now = Utc.ymd(2022, 1, 20).and_hms(18, 0, 0);
+
+        assert_eq!(tz.next_occurrence(eight(), now), Utc.ymd(2022, 1, 21).and_hms(13, 0, 0));
+    }
+
+    #[test]
+    fn time_zones_ahead_of_utc_use_their_own_date() {
+        let tz = SubscriberTimeZone::parse("Pacific/Auckland").unwrap();
+        // 23:00 UTC is already 12:00 the next day in Auckland (UTC+13 in January)
+        let now = Utc.ymd(2022, 1, 20).and_hms(23, 0, 0);
+
+        assert_eq!(tz.next_occurrence(eight(), now), Utc.ymd(2022, 1, 21).and_hms(19, 0, 0));
+    }
+
+    #[test]
+    fn skipped_local_times_are_moved_forward() {
+        let tz = SubscriberTimeZone::parse("Europe/Berlin").unwrap();
+        // Clocks jump from 02:00 to 03:00 on 27 March 2022
+        let now = Utc.ymd(2022, 3, 26).and_hms(23, 0, 0);
+
+        let instant = tz.next_occurrence(NaiveTime::from_hms(2, 30, 0), now);
+
+        assert_eq!(instant, Utc.ymd(2022, 3, 27).and_hms(1, 30, 0));
+    }
+}
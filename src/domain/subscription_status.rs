@@ -0,0 +1,140 @@
+/// Where a subscriber stands in their lifecycle, stored as the `subscription_status` enum.
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "subscription_status", rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    PendingConfirmation,
+    Confirmed,
+    Unsubscribed,
+    /// The address does not accept email any more.
+    Bounced,
+    /// The subscriber reported an issue as spam.
+    Complained,
+    /// Removed from the list by an admin, for good.
+    Cleaned,
+}
+
+impl SubscriptionStatus {
+    pub const ALL: [SubscriptionStatus; 6] = [
+        SubscriptionStatus::PendingConfirmation,
+        SubscriptionStatus::Confirmed,
+        SubscriptionStatus::Unsubscribed,
+        SubscriptionStatus::Bounced,
+        SubscriptionStatus::Complained,
+        SubscriptionStatus::Cleaned,
+    ];
+
+    pub fn parse(s: &str) -> Result<SubscriptionStatus, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| format!("{} is not a subscription status.", s))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionStatus::PendingConfirmation => "pending_confirmation",
+            SubscriptionStatus::Confirmed => "confirmed",
+            SubscriptionStatus::Unsubscribed => "unsubscribed",
+            SubscriptionStatus::Bounced => "bounced",
+            SubscriptionStatus::Complained => "complained",
+            SubscriptionStatus::Cleaned => "cleaned",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SubscriptionStatus::PendingConfirmation => "Pending confirmation",
+            SubscriptionStatus::Confirmed => "Confirmed",
+            SubscriptionStatus::Unsubscribed => "Unsubscribed",
+            SubscriptionStatus::Bounced => "Bounced",
+            SubscriptionStatus::Complained => "Complained",
+            SubscriptionStatus::Cleaned => "Cleaned",
+        }
+    }
+
+    /// The status after moving to `next`, if the lifecycle allows it.
+    ///
+    /// Unsubscribed people may sign up again, the other ways out of the list are final
+    /// short of cleaning the address.
+    pub fn transition(self, next: SubscriptionStatus) -> Result<SubscriptionStatus, String> {
+        use SubscriptionStatus::*;
+
+        match (self, next) {
+            (PendingConfirmation, Confirmed)
+            | (PendingConfirmation, Unsubscribed)
+            | (PendingConfirmation, Cleaned)
+            | (Confirmed, Unsubscribed)
+            | (Confirmed, Bounced)
+            | (Confirmed, Complained)
+            | (Confirmed, Cleaned)
+            | (Unsubscribed, PendingConfirmation)
+            | (Unsubscribed, Cleaned)
+            | (Bounced, Cleaned)
+            | (Complained, Cleaned) => Ok(next),
+            (current, next) => Err(format!(
+                "A subscriber who is {} cannot become {}.",
+                current.as_str(),
+                next.as_str(),
+            )),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::subscription_status::SubscriptionStatus;
+    use crate::domain::subscription_status::SubscriptionStatus::*;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn a_pending_subscriber_can_confirm_then_leave() {
+        let confirmed = PendingConfirmation.transition(Confirmed).unwrap();
+        for next in [Unsubscribed, Bounced, Complained, Cleaned] {
+            assert_eq!(confirmed.transition(next).unwrap(), next);
+        }
+    }
+
+    #[test]
+    fn unsubscribed_people_can_sign_up_again() {
+        assert_ok!(Unsubscribed.transition(PendingConfirmation));
+        assert_err!(Unsubscribed.transition(Confirmed));
+    }
+
+    #[test]
+    fn bounces_and_complaints_only_lead_to_cleaning() {
+        for status in [Bounced, Complained] {
+            assert_ok!(status.transition(Cleaned));
+            assert_err!(status.transition(PendingConfirmation));
+            assert_err!(status.transition(Confirmed));
+        }
+    }
+
+    #[test]
+    fn cleaning_is_final() {
+        for next in SubscriptionStatus::ALL.iter() {
+            assert_err!(Cleaned.transition(*next));
+        }
+    }
+
+    #[test]
+    fn a_status_cannot_transition_to_itself() {
+        for status in SubscriptionStatus::ALL.iter() {
+            assert_err!(status.transition(*status));
+        }
+    }
+
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        assert_err!(SubscriptionStatus::parse("active"));
+        assert_err!(SubscriptionStatus::parse(""));
+    }
+
+    #[test]
+    fn statuses_round_trip_through_their_database_representation() {
+        for status in SubscriptionStatus::ALL.iter() {
+            assert_eq!(SubscriptionStatus::parse(status.as_str()).unwrap(), *status);
+        }
+    }
+}
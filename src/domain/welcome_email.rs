@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use crate::domain::merge_fields::render_merge_fields;
+
+/// The email sent to subscribers once they confirm. Every part may use merge fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WelcomeEmail {
+    pub enabled: bool,
+    pub subject: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub include_latest_issue: bool,
+}
+
+/// The most recent issue, appended to the welcome email when asked to.
+pub struct LatestIssue {
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+}
+
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+impl WelcomeEmail {
+    pub fn parse(
+        enabled: bool,
+        subject: &str,
+        text_content: &str,
+        html_content: &str,
+        include_latest_issue: bool,
+    ) -> Result<WelcomeEmail, String> {
+        if subject.trim().is_empty() {
+            return Err("The welcome email needs a subject.".into());
+        }
+        if text_content.trim().is_empty() || html_content.trim().is_empty() {
+            return Err("The welcome email needs both a plain text and an HTML body.".into());
+        }
+
+        Ok(Self {
+            enabled,
+            subject: subject.trim().to_owned(),
+            text_content: text_content.to_owned(),
+            html_content: html_content.to_owned(),
+            include_latest_issue,
+        })
+    }
+
+    /// The email for one subscriber, followed by `latest_issue` when included.
+    pub fn render(&self, values: &HashMap<String, String>, latest_issue: Option<&LatestIssue>) -> RenderedEmail {
+        let mut html_content = render_merge_fields(&self.html_content, values, true);
+        let mut text_content = render_merge_fields(&self.text_content, values, false);
+
+        if let (true, Some(issue)) = (self.include_latest_issue, latest_issue) {
+            html_content.push_str(&format!(
+                "<hr /><p>In case you missed it, our latest issue:</p><h2>{}</h2>{}",
+                htmlescape::encode_minimal(&render_merge_fields(&issue.title, values, false)),
+                render_merge_fields(&issue.html_content, values, true),
+            ));
+            text_content = format!(
+                "{}\n\n---\nIn case you missed it, our latest issue:\n\n{}\n\n{}",
+                text_content.trim_end(),
+                render_merge_fields(&issue.title, values, false),
+                render_merge_fields(&issue.text_content, values, false),
+            );
+        }
+
+        RenderedEmail {
+            subject: render_merge_fields(&self.subject, values, false),
+            html_content,
+            text_content,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::welcome_email::{LatestIssue, WelcomeEmail};
+    use claim::assert_err;
+    use std::collections::HashMap;
+
+    fn welcome_email(include_latest_issue: bool) -> WelcomeEmail {
+        WelcomeEmail::parse(
+            true,
+            "Welcome, {{ name | reader }}!",
+            "Hi {{ name }}",
+            "<p>Hi {{ name }}</p>",
+            include_latest_issue,
+        ).unwrap()
+    }
+
+    fn latest_issue() -> LatestIssue {
+        LatestIssue {
+            title: "Issue #12".into(),
+            text_content: "Latest news".into(),
+            html_content: "<p>Latest news</p>".into(),
+        }
+    }
+
+    #[test]
+    fn merge_fields_are_filled_in() {
+        let values: HashMap<String, String> = vec![("name".to_string(), "<Ada>".to_string())].into_iter().collect();
+
+        let email = welcome_email(false).render(&values, None);
+
+        assert_eq!(email.subject, "Welcome, <Ada>!");
+        assert_eq!(email.text_content, "Hi <Ada>");
+        assert_eq!(email.html_content, "<p>Hi &lt;Ada&gt;</p>");
+    }
+
+    #[test]
+    fn the_latest_issue_is_appended_when_included() {
+        let email = welcome_email(true).render(&HashMap::new(), Some(&latest_issue()));
+
+        assert!(email.subject.starts_with("Welcome, reader!"));
+        assert!(email.html_content.starts_with("<p>Hi </p><hr />"));
+        assert!(email.html_content.ends_with("<h2>Issue #12</h2><p>Latest news</p>"));
+        assert!(email.text_content.ends_with("Issue #12\n\nLatest news"));
+    }
+
+    #[test]
+    fn the_latest_issue_is_left_out_otherwise() {
+        let email = welcome_email(false).render(&HashMap::new(), Some(&latest_issue()));
+
+        assert!(!email.html_content.contains("Latest news"));
+        assert!(!email.text_content.contains("Latest news"));
+    }
+
+    #[test]
+    fn a_subject_and_both_bodies_are_required() {
+        assert_err!(WelcomeEmail::parse(true, " ", "Hi", "<p>Hi</p>", false));
+        assert_err!(WelcomeEmail::parse(true, "Welcome", "", "<p>Hi</p>", false));
+        assert_err!(WelcomeEmail::parse(true, "Welcome", "Hi", "", false));
+    }
+}
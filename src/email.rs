@@ -1 +1,10 @@
-pub mod email_client;
\ No newline at end of file
+pub mod email_client;
+pub mod transport;
+pub mod postmark_transport;
+pub mod sparkpost_transport;
+pub mod mime_message;
+pub mod attachment;
+pub mod message_options;
+pub mod tracking;
+pub mod circuit_breaker;
+pub mod footer;
\ No newline at end of file
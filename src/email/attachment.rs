@@ -0,0 +1,45 @@
+/// A file sent along with an email.
+///
+/// When `content_id` is set the attachment is meant to be displayed inline,
+/// referenced from the HTML body as `<img src="cid:{content_id}">`.
+#[derive(Debug, Clone)]
+pub struct EmailAttachment {
+    pub name: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+    pub content_id: Option<String>,
+}
+
+impl EmailAttachment {
+    pub fn new(name: String, content_type: String, content: Vec<u8>) -> Self {
+        Self {
+            name,
+            content_type,
+            content,
+            content_id: None,
+        }
+    }
+
+    pub fn inline(name: String, content_type: String, content: Vec<u8>, content_id: String) -> Self {
+        Self {
+            name,
+            content_type,
+            content,
+            content_id: Some(content_id),
+        }
+    }
+
+    pub fn is_inline(&self) -> bool {
+        self.content_id.is_some()
+    }
+
+    /// Size of the raw (not base64-encoded) content in bytes.
+    pub fn size(&self) -> u64 {
+        self.content.len() as u64
+    }
+}
+
+/// Total size of the raw content of a set of attachments in bytes.
+pub fn total_size(attachments: &[EmailAttachment]) -> u64 {
+    attachments.iter().map(EmailAttachment::size).sum()
+}
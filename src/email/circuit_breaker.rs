@@ -0,0 +1,231 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircuitState {
+    /// Calls go through; consecutive failures are counted.
+    Closed,
+    /// The provider is considered down; calls fail fast.
+    Open,
+    /// A single probe call is let through to find out whether the provider recovered.
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half-open",
+        }
+    }
+}
+
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Instant,
+    probe_in_flight: bool,
+}
+
+/// Stop calling a dependency that keeps failing, so that callers do not each wait for a timeout.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_duration: Duration,
+    circuit: Mutex<Circuit>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            failure_threshold,
+            open_duration,
+            circuit: Mutex::new(Circuit {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: Instant::now(),
+                probe_in_flight: false,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.circuit.lock().unwrap().state
+    }
+
+    /// Whether a call may go through right now.
+    ///
+    /// Once `open_duration` has elapsed an open circuit moves to half-open and lets exactly one probe through.
+    pub fn try_acquire(&self) -> Option<CallPermit<'_>> {
+        let mut circuit = self.circuit.lock().unwrap();
+        let acquired = match circuit.state {
+            CircuitState::Closed => true,
+            CircuitState::Open if circuit.opened_at.elapsed() >= self.open_duration => {
+                transition(&mut circuit, CircuitState::HalfOpen);
+                circuit.probe_in_flight = true;
+                true
+            }
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if !circuit.probe_in_flight => {
+                circuit.probe_in_flight = true;
+                true
+            }
+            CircuitState::HalfOpen => false,
+        };
+
+        if acquired {
+            Some(CallPermit { breaker: self, recorded: false })
+        } else {
+            None
+        }
+    }
+
+    fn record_success(&self) {
+        let mut circuit = self.circuit.lock().unwrap();
+        circuit.consecutive_failures = 0;
+        circuit.probe_in_flight = false;
+        if circuit.state != CircuitState::Closed {
+            transition(&mut circuit, CircuitState::Closed);
+        }
+    }
+
+    fn record_failure(&self) {
+        let mut circuit = self.circuit.lock().unwrap();
+        circuit.consecutive_failures += 1;
+        circuit.probe_in_flight = false;
+
+        let should_open = match circuit.state {
+            CircuitState::Closed => circuit.consecutive_failures >= self.failure_threshold,
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+        };
+        if should_open {
+            circuit.opened_at = Instant::now();
+            transition(&mut circuit, CircuitState::Open);
+        }
+    }
+}
+
+/// A call let through by the breaker, which must report how it went.
+///
+/// A permit dropped without an outcome, e.g. because the sending future was cancelled, counts as a failure:
+/// otherwise a half-open circuit would wait forever for the outcome of its probe.
+pub struct CallPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    recorded: bool,
+}
+
+impl CallPermit<'_> {
+    pub fn success(mut self) {
+        self.recorded = true;
+        self.breaker.record_success();
+    }
+
+    pub fn failure(mut self) {
+        self.recorded = true;
+        self.breaker.record_failure();
+    }
+}
+
+impl Drop for CallPermit<'_> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.breaker.record_failure();
+        }
+    }
+}
+
+fn transition(circuit: &mut Circuit, to: CircuitState) {
+    let from = circuit.state;
+    circuit.state = to;
+
+    match to {
+        CircuitState::Open => tracing::error!(
+            from = from.as_str(),
+            to = to.as_str(),
+            consecutive_failures = circuit.consecutive_failures,
+            "The email provider circuit breaker opened, calls will fail fast",
+        ),
+        _ => tracing::warn!(
+            from = from.as_str(),
+            to = to.as_str(),
+            "The email provider circuit breaker changed state",
+        ),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::email::circuit_breaker::{CircuitBreaker, CircuitState};
+    use std::time::Duration;
+
+    #[test]
+    fn the_circuit_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+
+        breaker.record_failure();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        breaker.try_acquire().unwrap().failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.try_acquire().is_none());
+    }
+
+    #[test]
+    fn a_success_resets_the_failure_count() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn a_single_probe_is_let_through_once_the_circuit_is_half_open() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(0));
+        breaker.record_failure();
+
+        let _probe = breaker.try_acquire().unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire().is_none());
+    }
+
+    #[test]
+    fn a_successful_probe_closes_the_circuit() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(0));
+        breaker.record_failure();
+
+        breaker.try_acquire().unwrap().success();
+
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire().is_some());
+    }
+
+    #[test]
+    fn a_failed_probe_opens_the_circuit_again() {
+        let breaker = CircuitBreaker::new(5, Duration::from_millis(0));
+        for _ in 0..5 {
+            breaker.record_failure();
+        }
+
+        breaker.try_acquire().unwrap().failure();
+
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn a_probe_dropped_without_an_outcome_counts_as_a_failure() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(0));
+        breaker.record_failure();
+
+        drop(breaker.try_acquire().unwrap());
+
+        assert_eq!(breaker.state(), CircuitState::Open);
+        // The next probe is let through instead of waiting for the dropped one
+        assert!(breaker.try_acquire().is_some());
+    }
+}
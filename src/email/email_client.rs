@@ -1,22 +1,16 @@
-use reqwest::Client;
-
 use crate::domain::subscriber_email::SubscriberEmail;
-
-#[derive(serde::Serialize)]
-#[serde(rename_all = "PascalCase")]
-struct SendEmailRequest<'a> {
-    from: &'a str,
-    to: &'a str,
-    subject: &'a str,
-    html_body: &'a str,
-    text_body: &'a str,
-}
+use crate::email::attachment::{self, EmailAttachment};
+use crate::email::circuit_breaker::{CircuitBreaker, CircuitState};
+use crate::email::message_options::{MessageDefaults, MessageOptions};
+use crate::email::transport::{EmailTransport, OutgoingEmail};
+use crate::errors::send_email_error::SendEmailError;
 
 pub struct EmailClient {
     sender: SubscriberEmail,
-    http_client: Client,
-    base_url: String,
-    authorization_token: String,
+    transport: Box<dyn EmailTransport>,
+    max_attachments_size: u64,
+    defaults: MessageDefaults,
+    circuit_breaker: CircuitBreaker,
 }
 
 impl EmailClient {
@@ -26,43 +20,67 @@ impl EmailClient {
         subject: &str, 
         html_content: &str, 
         text_content: &str,
-    ) -> Result<(), reqwest::Error> {
-        let url = format!("{}/email", &self.base_url);
+        attachments: &[EmailAttachment],
+        options: &MessageOptions,
+    ) -> Result<(), SendEmailError> {
+        let size = attachment::total_size(attachments);
+        if size > self.max_attachments_size {
+            return Err(SendEmailError::AttachmentsTooLarge { size, limit: self.max_attachments_size });
+        }
 
-        let request_body = SendEmailRequest {
+        let email = OutgoingEmail {
             from: self.sender.as_ref(),
             to: recipient.as_ref(),
-            subject: subject,
+            subject,
             html_body: html_content,
             text_body: text_content,
+            attachments,
+            reply_to: options.reply_to.as_deref().or(self.defaults.reply_to.as_deref()),
+            headers: &options.headers,
+            tag: options.tag.as_deref(),
+            metadata: &options.metadata,
+            message_stream: self.defaults.stream_id(options.message_stream),
         };
 
-        self
-            .http_client
-            .post(&url)
-            .header("X-Postmark-Server-Token", &self.authorization_token)
-            .json(&request_body)
-            .send()
-            .await?
-            .error_for_status()?;
-        
-        Ok(())
+        let permit = match self.circuit_breaker.try_acquire() {
+            Some(permit) => permit,
+            None => return Err(SendEmailError::CircuitOpen),
+        };
 
+        match self.transport.send(&email).await {
+            Ok(()) => {
+                permit.success();
+                Ok(())
+            }
+            Err(e) => {
+                // A 4xx means the provider is up and rejected this particular message
+                if e.status().is_none_or(|status| status.is_server_error()) {
+                    permit.failure();
+                } else {
+                    permit.success();
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    pub fn circuit_state(&self) -> CircuitState {
+        self.circuit_breaker.state()
     }
 
     pub fn new(
-        base_url: String, 
+        transport: Box<dyn EmailTransport>,
         sender: SubscriberEmail,
-        authorization_token: String,
-        timeout: std::time::Duration,
+        max_attachments_size: u64,
+        defaults: MessageDefaults,
+        circuit_breaker: CircuitBreaker,
     ) -> Self {
-        let http_client = Client::builder().timeout(timeout).build().unwrap();
-
         Self {
-            http_client,
-            base_url,
             sender,
-            authorization_token,
+            transport,
+            max_attachments_size,
+            defaults,
+            circuit_breaker,
         }
     }
 }
@@ -71,6 +89,11 @@ impl EmailClient {
 #[cfg(test)]
 mod tests {
     use crate::email::email_client::EmailClient;
+    use crate::email::attachment::EmailAttachment;
+    use crate::email::circuit_breaker::{CircuitBreaker, CircuitState};
+    use crate::email::message_options::{MessageDefaults, MessageOptions};
+    use crate::email::postmark_transport::PostmarkTransport;
+    use crate::errors::send_email_error::SendEmailError;
     use crate::domain::subscriber_email::SubscriberEmail;
     use fake::faker::internet::en::SafeEmail;
     use fake::{Faker, Fake};
@@ -98,6 +121,27 @@ mod tests {
         }
     }
 
+    struct AttachmentsBodyMatcher;
+
+    impl wiremock::Match for AttachmentsBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+
+            if let Ok(body) = result {
+                let attachments = body["Attachments"].as_array().cloned().unwrap_or_default();
+
+                attachments.len() == 2
+                    && attachments[0]["Name"] == "issue.pdf"
+                    && attachments[0]["Content"] == base64::encode([1u8; 16])
+                    && attachments[0]["ContentType"] == "application/pdf"
+                    && attachments[0].get("ContentID").is_none()
+                    && attachments[1]["ContentID"] == "cid:logo"
+            } else {
+                false
+            }
+        }
+    }
+
     fn subject() -> String {
         Sentence(1..2).fake()
     }
@@ -111,7 +155,24 @@ mod tests {
     }
 
     fn email_client(base_url: String) -> EmailClient {
-        EmailClient::new(base_url, email(), Faker.fake(), std::time::Duration::from_millis(200))
+        let transport = PostmarkTransport::new(base_url, Faker.fake(), std::time::Duration::from_millis(200));
+        EmailClient::new(Box::new(transport), email(), 1024, defaults(),
+            CircuitBreaker::new(2, std::time::Duration::from_secs(60)))
+    }
+
+    fn defaults() -> MessageDefaults {
+        MessageDefaults {
+            reply_to: Some("editor@example.com".into()),
+            transactional_stream: "outbound".into(),
+            broadcast_stream: "broadcast".into(),
+        }
+    }
+
+    fn attachments() -> Vec<EmailAttachment> {
+        vec![
+            EmailAttachment::new("issue.pdf".into(), "application/pdf".into(), vec![1u8; 16]),
+            EmailAttachment::inline("logo.png".into(), "image/png".into(), vec![2u8; 16], "logo".into()),
+        ]
     }
 
     
@@ -131,7 +192,101 @@ mod tests {
             .await;
 
 
-        let _ = email_client.send_email(&email(), &subject(), &content(), &content()).await;
+        let _ = email_client.send_email(&email(), &subject(), &content(), &content(), &[], &MessageOptions::transactional()).await;
+    }
+
+
+    #[tokio::test]
+    async fn send_email_sends_attachments_to_postmark() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(AttachmentsBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &attachments(), &MessageOptions::transactional()).await;
+
+        assert_ok!(outcome);
+    }
+
+
+    #[tokio::test]
+    async fn send_email_rejects_attachments_above_the_size_limit() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let attachment = EmailAttachment::new("big.pdf".into(), "application/pdf".into(), vec![0u8; 1025]);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[attachment], &MessageOptions::transactional()).await;
+
+        assert!(matches!(outcome, Err(SendEmailError::AttachmentsTooLarge { size: 1025, limit: 1024 })));
+    }
+
+
+    #[tokio::test]
+    async fn send_email_applies_the_default_reply_to_and_stream() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[], &MessageOptions::broadcast()).await
+            .unwrap();
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["ReplyTo"], "editor@example.com");
+        assert_eq!(body["MessageStream"], "broadcast");
+        assert!(body.get("Tag").is_none());
+        assert!(body.get("Metadata").is_none());
+    }
+
+
+    #[tokio::test]
+    async fn send_email_forwards_the_message_options() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut options = MessageOptions::transactional();
+        options.reply_to = Some("support@example.com".into());
+        options.headers.push(("X-Issue".into(), "42".into()));
+        options.tag = Some("issue-42".into());
+        options.metadata.insert("issue_id".into(), "42".into());
+
+        email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[], &options).await
+            .unwrap();
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["ReplyTo"], "support@example.com");
+        assert_eq!(body["Headers"], serde_json::json!([{"Name": "X-Issue", "Value": "42"}]));
+        assert_eq!(body["Tag"], "issue-42");
+        assert_eq!(body["Metadata"], serde_json::json!({"issue_id": "42"}));
+        assert_eq!(body["MessageStream"], "outbound");
     }
 
 
@@ -147,7 +302,7 @@ mod tests {
             .await;
 
         let outcome = email_client
-            .send_email(&email(), &subject(), &content(), &content()).await;
+            .send_email(&email(), &subject(), &content(), &content(), &[], &MessageOptions::transactional()).await;
 
         assert_ok!(outcome);
     }
@@ -165,7 +320,7 @@ mod tests {
 
 
         let outcome = email_client
-            .send_email(&email(), &subject(), &content(), &content()).await;
+            .send_email(&email(), &subject(), &content(), &content(), &[], &MessageOptions::transactional()).await;
 
         assert_err!(outcome);
     }
@@ -185,8 +340,53 @@ mod tests {
             .await;
 
         let outcome = email_client
-            .send_email(&email(), &subject(), &content(), &content()).await;
+            .send_email(&email(), &subject(), &content(), &content(), &[], &MessageOptions::transactional()).await;
 
         assert_err!(outcome);
     }
-}
\ No newline at end of file
+
+
+    #[tokio::test]
+    async fn send_email_fails_fast_once_the_circuit_is_open() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        for _ in 0..2 {
+            let outcome = email_client
+                .send_email(&email(), &subject(), &content(), &content(), &[], &MessageOptions::transactional()).await;
+            assert!(matches!(outcome, Err(SendEmailError::RequestError(_))));
+        }
+        assert_eq!(email_client.circuit_state(), CircuitState::Open);
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[], &MessageOptions::transactional()).await;
+
+        assert!(matches!(outcome, Err(SendEmailError::CircuitOpen)));
+    }
+
+    #[tokio::test]
+    async fn client_errors_do_not_open_the_circuit() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        for _ in 0..3 {
+            let outcome = email_client
+                .send_email(&email(), &subject(), &content(), &content(), &[], &MessageOptions::transactional()).await;
+            assert_err!(outcome);
+        }
+
+        assert_eq!(email_client.circuit_state(), CircuitState::Closed);
+    }
+}
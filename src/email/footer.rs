@@ -0,0 +1,45 @@
+/// Append the manage-your-preferences link to both bodies of an outgoing issue.
+pub fn with_preferences_footer(html: &str, text: &str, preferences_url: &str) -> (String, String) {
+    let footer = format!(
+        r#"<p style="font-size:small">You can <a href="{}">change your delivery preferences</a> at any time.</p>"#,
+        htmlescape::encode_minimal(preferences_url),
+    );
+    let html = match html.rfind("</body>") {
+        Some(position) => format!("{}{}{}", &html[..position], footer, &html[position..]),
+        None => format!("{}{}", html, footer),
+    };
+    let text = format!("{}\n\n--\nChange your delivery preferences: {}\n", text.trim_end(), preferences_url);
+
+    (html, text)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::email::footer::with_preferences_footer;
+
+    const URL: &str = "https://example.com/subscriptions/preferences?token=abc";
+
+    #[test]
+    fn the_link_goes_before_the_closing_body_tag() {
+        let (html, _) = with_preferences_footer("<html><body><p>Hi</p></body></html>", "Hi", URL);
+
+        assert!(html.ends_with("</p></body></html>"));
+        assert!(html.contains(r#"href="https://example.com/subscriptions/preferences?token=abc""#));
+    }
+
+    #[test]
+    fn fragments_without_a_body_get_the_link_appended() {
+        let (html, _) = with_preferences_footer("<p>Hi</p>", "Hi", URL);
+
+        assert!(html.starts_with("<p>Hi</p><p"));
+    }
+
+    #[test]
+    fn the_plain_text_body_gets_the_link_too() {
+        let (_, text) = with_preferences_footer("<p>Hi</p>", "Hi\n", URL);
+
+        assert!(text.starts_with("Hi\n\n--\n"));
+        assert!(text.contains(URL));
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+/// Postmark keeps transactional and bulk mail on separate message streams so that
+/// a newsletter blast cannot hurt the deliverability of confirmation emails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageStream {
+    Transactional,
+    Broadcast,
+}
+
+/// Per-message knobs on top of the sender, recipient, subject and bodies.
+///
+/// Unset fields fall back to the `MessageDefaults` of the `EmailClient`.
+#[derive(Debug, Clone)]
+pub struct MessageOptions {
+    pub reply_to: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub tag: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+    pub message_stream: MessageStream,
+}
+
+impl MessageOptions {
+    pub fn transactional() -> Self {
+        Self::on_stream(MessageStream::Transactional)
+    }
+
+    pub fn broadcast() -> Self {
+        Self::on_stream(MessageStream::Broadcast)
+    }
+
+    fn on_stream(message_stream: MessageStream) -> Self {
+        Self {
+            reply_to: None,
+            headers: Vec::new(),
+            tag: None,
+            metadata: BTreeMap::new(),
+            message_stream,
+        }
+    }
+}
+
+/// Values applied to every message unless `MessageOptions` overrides them.
+#[derive(Debug, Clone)]
+pub struct MessageDefaults {
+    pub reply_to: Option<String>,
+    pub transactional_stream: String,
+    pub broadcast_stream: String,
+}
+
+impl MessageDefaults {
+    pub fn stream_id(&self, stream: MessageStream) -> &str {
+        match stream {
+            MessageStream::Transactional => &self.transactional_stream,
+            MessageStream::Broadcast => &self.broadcast_stream,
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::email::attachment::EmailAttachment;
+use crate::email::transport::OutgoingEmail;
+
+const LINE_LENGTH: usize = 76;
+
+/// Render a complete RFC 2045 MIME message.
+///
+/// Used by transports that, unlike Postmark, expect a raw message rather than a JSON payload.
+/// The layout is:
+///
+/// ```text
+/// multipart/mixed
+/// ├── multipart/related
+/// │   ├── multipart/alternative
+/// │   │   ├── text/plain
+/// │   │   └── text/html
+/// │   └── inline attachments (Content-ID)
+/// └── regular attachments
+/// ```
+/// Postmark-only options, the tag, metadata and message stream, are left to the transport.
+pub fn build_mime_message(email: &OutgoingEmail<'_>) -> String {
+    let mixed_boundary = boundary("mixed");
+    let related_boundary = boundary("related");
+    let alternative_boundary = boundary("alternative");
+
+    let mut message = String::new();
+    writeln_crlf(&mut message, &format!("From: {}", encode_header(email.from)));
+    writeln_crlf(&mut message, &format!("To: {}", encode_header(email.to)));
+    if let Some(reply_to) = email.reply_to {
+        writeln_crlf(&mut message, &format!("Reply-To: {}", encode_header(reply_to)));
+    }
+    writeln_crlf(&mut message, &format!("Subject: {}", encode_header(email.subject)));
+    for (name, value) in email.headers {
+        writeln_crlf(&mut message, &format!("{}: {}", single_line(name), encode_header(value)));
+    }
+    writeln_crlf(&mut message, "MIME-Version: 1.0");
+    writeln_crlf(&mut message, &format!("Content-Type: multipart/mixed; boundary=\"{}\"", mixed_boundary));
+    writeln_crlf(&mut message, "");
+
+    writeln_crlf(&mut message, &format!("--{}", mixed_boundary));
+    writeln_crlf(&mut message, &format!("Content-Type: multipart/related; boundary=\"{}\"", related_boundary));
+    writeln_crlf(&mut message, "");
+
+    writeln_crlf(&mut message, &format!("--{}", related_boundary));
+    writeln_crlf(&mut message, &format!("Content-Type: multipart/alternative; boundary=\"{}\"", alternative_boundary));
+    writeln_crlf(&mut message, "");
+    write_text_part(&mut message, &alternative_boundary, "text/plain", email.text_body);
+    write_text_part(&mut message, &alternative_boundary, "text/html", email.html_body);
+    writeln_crlf(&mut message, &format!("--{}--", alternative_boundary));
+
+    for attachment in email.attachments.iter().filter(|a| a.is_inline()) {
+        write_attachment_part(&mut message, &related_boundary, attachment);
+    }
+    writeln_crlf(&mut message, &format!("--{}--", related_boundary));
+
+    for attachment in email.attachments.iter().filter(|a| !a.is_inline()) {
+        write_attachment_part(&mut message, &mixed_boundary, attachment);
+    }
+    writeln_crlf(&mut message, &format!("--{}--", mixed_boundary));
+
+    message
+}
+
+fn boundary(kind: &str) -> String {
+    format!("=_{}_{}", kind, Uuid::new_v4().to_simple())
+}
+
+fn writeln_crlf(message: &mut String, line: &str) {
+    write!(message, "{}\r\n", line).unwrap();
+}
+
+/// Encode a header value as an RFC 2047 encoded-word if it contains non-ASCII characters.
+fn encode_header(value: &str) -> String {
+    let value = single_line(value);
+    if value.is_ascii() {
+        value
+    } else {
+        format!("=?UTF-8?B?{}?=", base64::encode(value))
+    }
+}
+
+/// Line breaks in a header would start new headers of their own.
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+fn write_text_part(message: &mut String, boundary: &str, content_type: &str, content: &str) {
+    writeln_crlf(message, &format!("--{}", boundary));
+    writeln_crlf(message, &format!("Content-Type: {}; charset=utf-8", content_type));
+    writeln_crlf(message, "Content-Transfer-Encoding: base64");
+    writeln_crlf(message, "");
+    write_base64_body(message, content.as_bytes());
+}
+
+fn write_attachment_part(message: &mut String, boundary: &str, attachment: &EmailAttachment) {
+    let name = encode_header(&attachment.name);
+
+    writeln_crlf(message, &format!("--{}", boundary));
+    writeln_crlf(message, &format!("Content-Type: {}; name=\"{}\"", attachment.content_type, name));
+    writeln_crlf(message, "Content-Transfer-Encoding: base64");
+    match &attachment.content_id {
+        Some(content_id) => {
+            writeln_crlf(message, &format!("Content-ID: <{}>", content_id));
+            writeln_crlf(message, &format!("Content-Disposition: inline; filename=\"{}\"", name));
+        }
+        None => {
+            writeln_crlf(message, &format!("Content-Disposition: attachment; filename=\"{}\"", name));
+        }
+    }
+    writeln_crlf(message, "");
+    write_base64_body(message, &attachment.content);
+}
+
+fn write_base64_body(message: &mut String, content: &[u8]) {
+    let encoded = base64::encode(content);
+    // base64 output is pure ASCII, so splitting on byte offsets is safe.
+    for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+        writeln_crlf(message, std::str::from_utf8(line).unwrap());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::email::attachment::EmailAttachment;
+    use crate::email::mime_message::build_mime_message;
+    use crate::email::transport::OutgoingEmail;
+
+    fn pdf() -> EmailAttachment {
+        EmailAttachment::new("issue.pdf".into(), "application/pdf".into(), vec![1u8; 200])
+    }
+
+    fn logo() -> EmailAttachment {
+        EmailAttachment::inline("logo.png".into(), "image/png".into(), vec![2u8; 10], "logo".into())
+    }
+
+    fn message(subject: &str, html_body: &str, attachments: &[EmailAttachment], headers: &[(String, String)]) -> String {
+        build_mime_message(&OutgoingEmail {
+            from: "a@example.com",
+            to: "b@example.com",
+            subject,
+            html_body,
+            text_body: "Hi",
+            attachments,
+            reply_to: Some("editor@example.com"),
+            headers,
+            tag: None,
+            metadata: &BTreeMap::new(),
+            message_stream: "outbound",
+        })
+    }
+
+    #[test]
+    fn a_message_without_attachments_has_both_bodies() {
+        let message = message("Hi", "<p>Hi</p>", &[], &[]);
+
+        assert!(message.contains("Content-Type: text/plain; charset=utf-8"));
+        assert!(message.contains("Content-Type: text/html; charset=utf-8"));
+        assert!(!message.contains("Content-Disposition"));
+    }
+
+    #[test]
+    fn inline_attachments_carry_a_content_id() {
+        let message = message("Hi", "<img src=\"cid:logo\">", &[logo()], &[]);
+
+        assert!(message.contains("Content-ID: <logo>"));
+        assert!(message.contains("Content-Disposition: inline; filename=\"logo.png\""));
+    }
+
+    #[test]
+    fn regular_attachments_are_marked_as_attachments() {
+        let message = message("Hi", "<p>Hi</p>", &[pdf()], &[]);
+
+        assert!(message.contains("Content-Disposition: attachment; filename=\"issue.pdf\""));
+    }
+
+    #[test]
+    fn base64_lines_do_not_exceed_76_characters() {
+        let message = message("Hi", "<p>Hi</p>", &[pdf()], &[]);
+
+        assert!(message.split("\r\n").filter(|l| !l.contains(':')).all(|l| l.len() <= 76));
+    }
+
+    #[test]
+    fn non_ascii_subjects_are_encoded() {
+        let message = message("Grüße", "<p>Hi</p>", &[], &[]);
+
+        assert!(message.contains("Subject: =?UTF-8?B?"));
+    }
+
+    #[test]
+    fn the_reply_to_and_custom_headers_are_added() {
+        let headers = vec![("X-Issue".to_string(), "42".to_string())];
+        let message = message("Hi", "<p>Hi</p>", &[], &headers);
+
+        assert!(message.contains("Reply-To: editor@example.com\r\n"));
+        assert!(message.contains("X-Issue: 42\r\n"));
+    }
+
+    #[test]
+    fn line_breaks_cannot_smuggle_in_headers() {
+        let message = message("Hi\r\nBcc: victim@example.com", "<p>Hi</p>", &[], &[]);
+
+        assert!(message.contains("Subject: Hi  Bcc: victim@example.com\r\n"));
+        assert!(!message.contains("\r\nBcc:"));
+    }
+}
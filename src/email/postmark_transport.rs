@@ -0,0 +1,104 @@
+use reqwest::Client;
+use std::collections::BTreeMap;
+
+use crate::email::attachment::EmailAttachment;
+use crate::email::transport::{EmailTransport, OutgoingEmail};
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<AttachmentRequest<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<HeaderRequest<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<&'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: &'a BTreeMap<String, String>,
+    message_stream: &'a str,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct HeaderRequest<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct AttachmentRequest<'a> {
+    name: &'a str,
+    content: String,
+    content_type: &'a str,
+    #[serde(rename = "ContentID", skip_serializing_if = "Option::is_none")]
+    content_id: Option<String>,
+}
+
+impl<'a> From<&'a EmailAttachment> for AttachmentRequest<'a> {
+    fn from(attachment: &'a EmailAttachment) -> Self {
+        Self {
+            name: &attachment.name,
+            content: base64::encode(&attachment.content),
+            content_type: &attachment.content_type,
+            // Postmark expects the `cid:` prefix for inline attachments
+            content_id: attachment.content_id.as_ref().map(|id| format!("cid:{}", id)),
+        }
+    }
+}
+
+
+/// Sends through the Postmark email API, attachments included in the JSON payload.
+pub struct PostmarkTransport {
+    http_client: Client,
+    base_url: String,
+    authorization_token: String,
+}
+
+impl PostmarkTransport {
+    pub fn new(base_url: String, authorization_token: String, timeout: std::time::Duration) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+
+        Self { http_client, base_url, authorization_token }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for PostmarkTransport {
+    async fn send(&self, email: &OutgoingEmail<'_>) -> Result<(), reqwest::Error> {
+        let url = format!("{}/email", &self.base_url);
+
+        let request_body = SendEmailRequest {
+            from: email.from,
+            to: email.to,
+            subject: email.subject,
+            html_body: email.html_body,
+            text_body: email.text_body,
+            attachments: email.attachments.iter().map(AttachmentRequest::from).collect(),
+            reply_to: email.reply_to,
+            headers: email.headers.iter()
+                .map(|(name, value)| HeaderRequest { name, value })
+                .collect(),
+            tag: email.tag,
+            metadata: email.metadata,
+            message_stream: email.message_stream,
+        };
+
+        self.http_client
+            .post(&url)
+            .header("X-Postmark-Server-Token", &self.authorization_token)
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use reqwest::Client;
+use std::collections::BTreeMap;
+
+use crate::email::mime_message::build_mime_message;
+use crate::email::transport::{EmailTransport, OutgoingEmail};
+
+#[derive(serde::Serialize)]
+struct TransmissionRequest<'a> {
+    recipients: Vec<RecipientRequest<'a>>,
+    content: ContentRequest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    campaign_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: &'a BTreeMap<String, String>,
+}
+
+#[derive(serde::Serialize)]
+struct RecipientRequest<'a> {
+    address: &'a str,
+}
+
+#[derive(serde::Serialize)]
+struct ContentRequest {
+    email_rfc822: String,
+}
+
+
+/// Sends through the SparkPost transmissions API, which takes the message as raw MIME.
+///
+/// SparkPost has no message streams, the tag becomes the campaign.
+pub struct SparkPostTransport {
+    http_client: Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl SparkPostTransport {
+    pub fn new(base_url: String, api_key: String, timeout: std::time::Duration) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+
+        Self { http_client, base_url, api_key }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SparkPostTransport {
+    async fn send(&self, email: &OutgoingEmail<'_>) -> Result<(), reqwest::Error> {
+        let url = format!("{}/api/v1/transmissions", &self.base_url);
+
+        let request_body = TransmissionRequest {
+            recipients: vec![RecipientRequest { address: email.to }],
+            content: ContentRequest { email_rfc822: build_mime_message(email) },
+            campaign_id: email.tag,
+            metadata: email.metadata,
+        };
+
+        self.http_client
+            .post(&url)
+            .header("Authorization", &self.api_key)
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::email::attachment::EmailAttachment;
+    use crate::email::sparkpost_transport::SparkPostTransport;
+    use crate::email::transport::{EmailTransport, OutgoingEmail};
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+    use claim::{assert_err, assert_ok};
+
+    struct RawMessageMatcher;
+
+    impl wiremock::Match for RawMessageMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+
+            if let Ok(body) = result {
+                let message = body["content"]["email_rfc822"].as_str().unwrap_or_default();
+
+                body["recipients"][0]["address"] == "b@example.com"
+                    && body["campaign_id"] == "welcome"
+                    && message.contains("To: b@example.com\r\n")
+                    && message.contains("Content-Disposition: attachment; filename=\"issue.pdf\"")
+            } else {
+                false
+            }
+        }
+    }
+
+    fn transport(base_url: String) -> SparkPostTransport {
+        SparkPostTransport::new(base_url, "api-key".into(), std::time::Duration::from_millis(200))
+    }
+
+    async fn send(transport: &SparkPostTransport) -> Result<(), reqwest::Error> {
+        let attachments = vec![EmailAttachment::new("issue.pdf".into(), "application/pdf".into(), vec![1u8; 16])];
+        transport.send(&OutgoingEmail {
+            from: "a@example.com",
+            to: "b@example.com",
+            subject: "Hi",
+            html_body: "<p>Hi</p>",
+            text_body: "Hi",
+            attachments: &attachments,
+            reply_to: None,
+            headers: &[],
+            tag: Some("welcome"),
+            metadata: &BTreeMap::new(),
+            message_stream: "outbound",
+        }).await
+    }
+
+
+    #[tokio::test]
+    async fn the_message_is_sent_as_raw_mime() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(path("/api/v1/transmissions"))
+            .and(method("POST"))
+            .and(header("Authorization", "api-key"))
+            .and(RawMessageMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_ok!(send(&transport(mock_server.uri())).await);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_reported() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(path("/api/v1/transmissions"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_err!(send(&transport(mock_server.uri())).await);
+    }
+}
@@ -0,0 +1,118 @@
+use uuid::Uuid;
+
+/// Prepare the HTML body of an issue for a single delivery: absolute links go through
+/// the click tracker and a 1x1 pixel reports opens.
+pub fn instrument_html(html: &str, base_url: &str, delivery_id: Uuid) -> String {
+    let html = track_links(html, base_url, delivery_id);
+    let pixel = format!(
+        r#"<img src="{}/issues/{}/open" width="1" height="1" alt="" />"#,
+        base_url, delivery_id
+    );
+
+    match html.rfind("</body>") {
+        Some(position) => format!("{}{}{}", &html[..position], pixel, &html[position..]),
+        None => format!("{}{}", html, pixel),
+    }
+}
+
+pub fn click_tracking_url(base_url: &str, delivery_id: Uuid, url: &str) -> String {
+    format!("{}/issues/{}/click?url={}", base_url, delivery_id, urlencoding::encode(url))
+}
+
+/// Whether `url` is the target of one of the links of `html`.
+///
+/// The click tracker only redirects to links that are part of the issue, so it cannot be used
+/// as an open redirect.
+pub fn links_to(html: &str, url: &str) -> bool {
+    html.contains(&format!("href=\"{}\"", url)) || html.contains(&format!("href='{}'", url))
+}
+
+fn track_links(html: &str, base_url: &str, delivery_id: Uuid) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find("href=") {
+        let (before, after) = rest.split_at(start + "href=".len());
+        output.push_str(before);
+
+        let quote = match after.chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => {
+                rest = after;
+                continue;
+            }
+        };
+
+        match after[1..].find(quote) {
+            Some(end) => {
+                let url = &after[1..end + 1];
+                let target = if url.starts_with("http://") || url.starts_with("https://") {
+                    click_tracking_url(base_url, delivery_id, url)
+                } else {
+                    url.to_string()
+                };
+                output.push(quote);
+                output.push_str(&target);
+                output.push(quote);
+                rest = &after[end + 2..];
+            }
+            None => {
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+
+    output
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::email::tracking::{instrument_html, links_to};
+    use uuid::Uuid;
+
+    #[test]
+    fn absolute_links_are_rewritten() {
+        let id = Uuid::new_v4();
+        let html = instrument_html(r#"<a href="https://example.com/a?b=c">x</a>"#, "http://app", id);
+
+        assert!(html.contains(&format!(
+            r#"<a href="http://app/issues/{}/click?url=https%3A%2F%2Fexample.com%2Fa%3Fb%3Dc">x</a>"#,
+            id
+        )));
+    }
+
+    #[test]
+    fn relative_and_mailto_links_are_left_alone() {
+        let html = instrument_html(r#"<a href='mailto:a@b.c'>x</a><a href="/a">y</a>"#, "http://app", Uuid::new_v4());
+
+        assert!(html.contains(r#"<a href='mailto:a@b.c'>x</a><a href="/a">y</a>"#));
+    }
+
+    #[test]
+    fn the_pixel_is_added_before_the_end_of_the_body() {
+        let id = Uuid::new_v4();
+        let html = instrument_html("<html><body><p>Hi</p></body></html>", "http://app", id);
+
+        assert!(html.ends_with(&format!(
+            r#"<img src="http://app/issues/{}/open" width="1" height="1" alt="" /></body></html>"#,
+            id
+        )));
+    }
+
+    #[test]
+    fn unterminated_attributes_do_not_panic() {
+        let html = instrument_html(r#"<a href="https://example.com"#, "http://app", Uuid::new_v4());
+
+        assert!(html.starts_with(r#"<a href="https://example.com"#));
+    }
+
+    #[test]
+    fn only_links_of_the_issue_are_recognised() {
+        let html = r#"<a href="https://example.com">x</a>"#;
+
+        assert!(links_to(html, "https://example.com"));
+        assert!(!links_to(html, "https://evil.example.com"));
+    }
+}
@@ -0,0 +1,27 @@
+use std::collections::BTreeMap;
+
+use crate::email::attachment::EmailAttachment;
+
+/// A message as handed over to a transport, with the `MessageDefaults` already applied.
+pub struct OutgoingEmail<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    pub subject: &'a str,
+    pub html_body: &'a str,
+    pub text_body: &'a str,
+    pub attachments: &'a [EmailAttachment],
+    pub reply_to: Option<&'a str>,
+    pub headers: &'a [(String, String)],
+    pub tag: Option<&'a str>,
+    pub metadata: &'a BTreeMap<String, String>,
+    pub message_stream: &'a str,
+}
+
+/// Delivers messages through an email provider.
+///
+/// Errors are left as they come from the HTTP client, so that `EmailClient` can tell a provider
+/// that is down from one that rejected a particular message.
+#[async_trait::async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, email: &OutgoingEmail<'_>) -> Result<(), reqwest::Error>;
+}
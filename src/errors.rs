@@ -2,4 +2,8 @@ pub mod store_token_error;
 pub mod subscribe_error;
 pub mod helper;
 pub mod publish_error;
-pub mod auth_error;
\ No newline at end of file
+pub mod auth_error;
+pub mod send_email_error;
+
+pub mod rate_limit_error;
+pub mod status_change_error;
\ No newline at end of file
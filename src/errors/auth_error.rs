@@ -1,6 +1,5 @@
-use actix_web::{ResponseError, HttpResponse};
-
 use super::helper::error_chain_fmt;
+use super::rate_limit_error::RateLimitError;
 
 #[derive(Debug, thiserror::Error)]
 pub enum AuthError {
@@ -15,6 +14,8 @@ pub enum AuthError {
 pub enum LoginError {
     #[error("Authentication failed")]
     AuthError(#[source] anyhow::Error),
+    #[error("Too many login attempts, please try again later.")]
+    RateLimited(#[source] RateLimitError),
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error)
 }
@@ -2,7 +2,7 @@ pub fn error_chain_fmt(
     e: &impl std::error::Error, 
     f: &mut std::fmt::Formatter<'_>
 ) -> std::fmt::Result {
-    write!(f, "{}\n", e)?;
+    writeln!(f, "{}", e)?;
     let mut current = e.source();
     while let Some(cause) = current {
         write!(f, "Caused by \n\t{}", cause)?;
@@ -0,0 +1,33 @@
+use std::time::Duration;
+use actix_web::http::header::{ContentType, RETRY_AFTER};
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+
+
+#[derive(Debug, thiserror::Error)]
+#[error("Too many requests, please try again later.")]
+pub struct RateLimitError {
+    pub retry_after: Duration,
+}
+
+impl RateLimitError {
+    /// Whole seconds, rounded up so that a client retrying on time is let through.
+    pub fn retry_after_seconds(&self) -> u64 {
+        let seconds = self.retry_after.as_secs();
+        if self.retry_after.subsec_nanos() > 0 { seconds + 1 } else { seconds.max(1) }
+    }
+}
+
+
+impl ResponseError for RateLimitError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::TooManyRequests()
+            .insert_header((RETRY_AFTER, self.retry_after_seconds().to_string()))
+            .content_type(ContentType::plaintext())
+            .body(self.to_string())
+    }
+}
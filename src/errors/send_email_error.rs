@@ -0,0 +1,17 @@
+use crate::errors::helper::error_chain_fmt;
+
+#[derive(thiserror::Error)]
+pub enum SendEmailError {
+    #[error("The attachments add up to {size} bytes, above the {limit} bytes limit.")]
+    AttachmentsTooLarge { size: u64, limit: u64 },
+    #[error("The email provider is unavailable, the circuit breaker is open.")]
+    CircuitOpen,
+    #[error("Failed to send the email request.")]
+    RequestError(#[from] reqwest::Error),
+}
+
+impl std::fmt::Debug for SendEmailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
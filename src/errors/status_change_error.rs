@@ -0,0 +1,20 @@
+use crate::errors::helper::error_chain_fmt;
+
+
+#[derive(thiserror::Error)]
+pub enum StatusChangeError {
+    /// The lifecycle does not allow the transition.
+    #[error("{0}")]
+    NotAllowed(String),
+    #[error("The subscriber does not exist.")]
+    NotFound,
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+
+impl std::fmt::Debug for StatusChangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
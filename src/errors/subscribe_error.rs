@@ -1,12 +1,16 @@
-use actix_web::{http::StatusCode, ResponseError};
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use actix_web::http::header::ContentType;
 
 use crate::errors::helper::error_chain_fmt;
+use crate::errors::rate_limit_error::RateLimitError;
 
 
 #[derive(thiserror::Error)]
 pub enum SubscribeError {
     #[error("{0}")]
     ValidationError(String),
+    #[error(transparent)]
+    RateLimited(#[from] RateLimitError),
     // #[error(transparent)]
     // UnexpectedError(#[from] Box<dyn std::error::Error>),
     #[error(transparent)]
@@ -25,8 +29,19 @@ impl ResponseError for SubscribeError {
     fn status_code(&self) -> StatusCode {
         match self {
             SubscribeError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            SubscribeError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
             // SubscribeError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             SubscribeError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
-}
\ No newline at end of file
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            // Carries the Retry-After header
+            SubscribeError::RateLimited(e) => e.error_response(),
+            _ => HttpResponse::build(self.status_code())
+                .content_type(ContentType::plaintext())
+                .body(self.to_string()),
+        }
+    }
+}
@@ -1 +1,17 @@
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod newsletter_issue;
+pub mod signed_token;
+pub mod preferences;
+pub mod consent;
+pub mod suppression;
+pub mod subscriber_data;
+pub mod subscriber_export;
+pub mod subscriber_attributes;
+pub mod subscriber_listing;
+pub mod subscriber_admin;
+pub mod subscription_status;
+pub mod welcome_email;
+pub mod automation;
+pub mod tags;
+pub mod referrals;
+pub mod client_ip;
\ No newline at end of file
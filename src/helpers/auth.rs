@@ -1,4 +1,3 @@
-use actix_web::http::header::{HeaderMap};
 use secrecy::{Secret};
 use anyhow::Context;
 use pbkdf2::password_hash::{PasswordVerifier, PasswordHash};
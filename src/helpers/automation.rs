@@ -0,0 +1,309 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::automation::{SequenceStep, SequenceTrigger, StepCondition};
+
+pub struct SequenceSummary {
+    pub sequence_id: Uuid,
+    pub name: String,
+    pub trigger: SequenceTrigger,
+    pub steps: i64,
+    pub active_enrollments: i64,
+}
+
+/// What happened at a step so far.
+pub struct StepStats {
+    pub position: i16,
+    pub sent: i64,
+    pub skipped: i64,
+    pub failed: i64,
+    /// Enrollments due to run the step next.
+    pub waiting: i64,
+}
+
+pub struct Enrollment {
+    pub enrollment_id: Uuid,
+    pub sequence_name: String,
+    pub status: String,
+    pub enrolled_at: DateTime<Utc>,
+    pub next_position: i16,
+    pub next_step_at: Option<DateTime<Utc>>,
+}
+
+
+/// Returns `false` when another sequence has the name.
+#[tracing::instrument(name = "Insert a sequence", skip(pool))]
+pub async fn insert_sequence(pool: &PgPool, name: &str, trigger: &SequenceTrigger) -> Result<bool, sqlx::Error> {
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO sequences (sequence_id, name, trigger, trigger_tag, created_at)
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (name) DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        name,
+        trigger.kind(),
+        trigger.tag(),
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(inserted.rows_affected() == 1)
+}
+
+
+#[tracing::instrument(name = "Get sequences", skip(pool))]
+pub async fn get_sequences(pool: &PgPool) -> Result<Vec<SequenceSummary>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            s.sequence_id,
+            s.name,
+            s.trigger,
+            s.trigger_tag,
+            (SELECT count(*) FROM sequence_steps WHERE sequence_id = s.sequence_id) AS "steps!",
+            (
+                SELECT count(*) FROM sequence_enrollments
+                WHERE sequence_id = s.sequence_id AND status = 'active'
+            ) AS "active_enrollments!"
+        FROM sequences s
+        ORDER BY s.name
+        "#,
+    )
+        .fetch_all(pool)
+        .await
+        .context("Failed to retrieve the sequences.")?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(SequenceSummary {
+                sequence_id: row.sequence_id,
+                name: row.name,
+                trigger: SequenceTrigger::from_columns(&row.trigger, row.trigger_tag).map_err(anyhow::Error::msg)?,
+                steps: row.steps,
+                active_enrollments: row.active_enrollments,
+            })
+        })
+        .collect()
+}
+
+
+/// Name and trigger of a sequence.
+#[tracing::instrument(name = "Get a sequence", skip(pool))]
+pub async fn get_sequence(pool: &PgPool, sequence_id: Uuid) -> Result<Option<(String, SequenceTrigger)>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"SELECT name, trigger, trigger_tag FROM sequences WHERE sequence_id = $1"#,
+        sequence_id,
+    )
+        .fetch_optional(pool)
+        .await
+        .context("Failed to retrieve a sequence.")?;
+
+    match row {
+        Some(row) => {
+            let trigger = SequenceTrigger::from_columns(&row.trigger, row.trigger_tag).map_err(anyhow::Error::msg)?;
+            Ok(Some((row.name, trigger)))
+        }
+        None => Ok(None),
+    }
+}
+
+
+/// The steps of a sequence, in order.
+#[tracing::instrument(name = "Get the steps of a sequence", skip(pool))]
+pub async fn get_steps(pool: &PgPool, sequence_id: Uuid) -> Result<Vec<SequenceStep>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT position, delay_days, subject, text_content, html_content,
+            condition_kind, condition_key, condition_value
+        FROM sequence_steps
+        WHERE sequence_id = $1
+        ORDER BY position
+        "#,
+        sequence_id,
+    )
+        .fetch_all(pool)
+        .await
+        .context("Failed to retrieve the steps of a sequence.")?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(SequenceStep {
+                position: row.position,
+                delay_days: row.delay_days,
+                subject: row.subject,
+                text_content: row.text_content,
+                html_content: row.html_content,
+                condition: StepCondition::from_columns(row.condition_kind, row.condition_key, row.condition_value)
+                    .map_err(anyhow::Error::msg)?,
+            })
+        })
+        .collect()
+}
+
+
+/// Returns `false` when another step took the position in the meantime.
+#[tracing::instrument(name = "Insert a sequence step", skip(pool, step))]
+pub async fn insert_step(pool: &PgPool, sequence_id: Uuid, step: &SequenceStep) -> Result<bool, sqlx::Error> {
+    let (condition_key, condition_value) = match &step.condition {
+        Some(StepCondition::Attribute { key, value }) => (Some(key.as_str()), Some(value.as_str())),
+        _ => (None, None),
+    };
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO sequence_steps (
+            sequence_id, position, delay_days, subject, text_content, html_content,
+            condition_kind, condition_key, condition_value
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (sequence_id, position) DO NOTHING
+        "#,
+        sequence_id,
+        step.position,
+        step.delay_days,
+        step.subject,
+        step.text_content,
+        step.html_content,
+        step.condition.as_ref().map(|condition| condition.kind()),
+        condition_key,
+        condition_value,
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(inserted.rows_affected() == 1)
+}
+
+
+#[tracing::instrument(name = "Get the statistics of a sequence", skip(pool))]
+pub async fn get_step_stats(pool: &PgPool, sequence_id: Uuid) -> Result<Vec<StepStats>, sqlx::Error> {
+    sqlx::query_as!(
+        StepStats,
+        r#"
+        SELECT
+            step.position,
+            count(*) FILTER (WHERE run.outcome = 'sent') AS "sent!",
+            count(*) FILTER (WHERE run.outcome = 'skipped') AS "skipped!",
+            count(*) FILTER (WHERE run.outcome = 'failed') AS "failed!",
+            (
+                SELECT count(*) FROM sequence_enrollments e
+                WHERE e.sequence_id = step.sequence_id AND e.status = 'active' AND e.next_position = step.position
+            ) AS "waiting!"
+        FROM sequence_steps step
+        LEFT JOIN sequence_enrollments enrollment ON enrollment.sequence_id = step.sequence_id
+        LEFT JOIN sequence_step_runs run
+            ON run.enrollment_id = enrollment.enrollment_id AND run.position = step.position
+        WHERE step.sequence_id = $1
+        GROUP BY step.sequence_id, step.position
+        ORDER BY step.position
+        "#,
+        sequence_id,
+    )
+        .fetch_all(pool)
+        .await
+}
+
+
+/// Enroll the subscriber in the sequences started by `trigger`.
+///
+/// Sequences the subscriber went through already and sequences without steps are left out,
+/// as are subscribers who are not confirmed. Returns the number of new enrollments.
+#[tracing::instrument(name = "Enroll a subscriber in sequences", skip(transaction))]
+pub async fn enroll_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    trigger: &SequenceTrigger,
+) -> Result<u64, sqlx::Error> {
+    let enrolled = sqlx::query!(
+        r#"
+        INSERT INTO sequence_enrollments (
+            enrollment_id, sequence_id, subscriber_id, status, enrolled_at, next_position, next_step_at
+        )
+        SELECT
+            uuid_generate_v4(), s.sequence_id, $1, 'active', now(),
+            first_step.position, now() + make_interval(days => first_step.delay_days)
+        FROM sequences s
+        JOIN LATERAL (
+            SELECT position, delay_days FROM sequence_steps
+            WHERE sequence_id = s.sequence_id
+            ORDER BY position
+            LIMIT 1
+        ) first_step ON true
+        WHERE s.trigger = $2
+            AND s.trigger_tag IS NOT DISTINCT FROM $3
+            AND EXISTS (SELECT 1 FROM subscriptions WHERE id = $1 AND status = 'confirmed')
+        ON CONFLICT (sequence_id, subscriber_id) DO NOTHING
+        "#,
+        subscriber_id,
+        trigger.kind(),
+        trigger.tag(),
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(enrolled.rows_affected())
+}
+
+
+/// Enroll the subscriber in the sequences of the tags they have, e.g. when they confirm after being tagged.
+#[tracing::instrument(name = "Enroll a subscriber in the sequences of their tags", skip(transaction))]
+pub async fn enroll_in_tag_sequences(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let tags = sqlx::query!(
+        r#"
+        SELECT t.name FROM tags t
+        JOIN subscription_tags st ON st.tag_id = t.tag_id
+        WHERE st.subscriber_id = $1
+        "#,
+        subscriber_id,
+    )
+        .fetch_all(&mut *transaction)
+        .await?;
+
+    let mut enrolled = 0;
+    for tag in tags {
+        enrolled += enroll_subscriber(transaction, subscriber_id, &SequenceTrigger::Tag(tag.name)).await?;
+    }
+    Ok(enrolled)
+}
+
+
+#[tracing::instrument(name = "Get the enrollments of a subscriber", skip(pool))]
+pub async fn get_enrollments(pool: &PgPool, subscriber_id: Uuid) -> Result<Vec<Enrollment>, sqlx::Error> {
+    sqlx::query_as!(
+        Enrollment,
+        r#"
+        SELECT e.enrollment_id, s.name AS sequence_name, e.status, e.enrolled_at, e.next_position, e.next_step_at
+        FROM sequence_enrollments e
+        JOIN sequences s ON s.sequence_id = e.sequence_id
+        WHERE e.subscriber_id = $1
+        ORDER BY e.enrolled_at
+        "#,
+        subscriber_id,
+    )
+        .fetch_all(pool)
+        .await
+}
+
+
+/// Returns `false` when the enrollment is not active, e.g. it went through every step already.
+#[tracing::instrument(name = "Stop an enrollment", skip(pool))]
+pub async fn stop_enrollment(pool: &PgPool, subscriber_id: Uuid, enrollment_id: Uuid) -> Result<bool, sqlx::Error> {
+    let stopped = sqlx::query!(
+        r#"
+        UPDATE sequence_enrollments
+        SET status = 'stopped', next_step_at = NULL, finished_at = now()
+        WHERE enrollment_id = $1 AND subscriber_id = $2 AND status = 'active'
+        "#,
+        enrollment_id,
+        subscriber_id,
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(stopped.rows_affected() == 1)
+}
@@ -0,0 +1,104 @@
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::http::header::HeaderMap;
+use actix_web::{web, HttpRequest};
+use ipnet::IpNet;
+
+/// The proxies in front of the application, e.g. the load balancer of the hosting platform.
+///
+/// Their `X-Forwarded-For` header is believed, anyone else could forge it.
+#[derive(Clone, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    pub fn new(networks: Vec<IpNet>) -> Self {
+        Self(networks)
+    }
+
+    fn trusts(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(&ip))
+    }
+
+    /// The address the request came from, looking through the trusted proxies.
+    ///
+    /// Each proxy appends the address it was reached from, so the rightmost untrusted entry is the
+    /// client. Entries to its left were written by the client.
+    pub fn client_ip(&self, peer: Option<SocketAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let mut client = peer?.ip();
+        if !self.trusts(client) {
+            return Some(client);
+        }
+
+        let forwarded = headers
+            .get_all("X-Forwarded-For")
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+        for hop in forwarded.into_iter().rev() {
+            match hop.trim().parse() {
+                Ok(ip) => client = ip,
+                Err(_) => break,
+            }
+            if !self.trusts(client) {
+                break;
+            }
+        }
+        Some(client)
+    }
+}
+
+
+/// The address of the client behind `request`, with the `TrustedProxies` the application runs with.
+pub fn client_ip(request: &HttpRequest) -> Option<IpAddr> {
+    match request.app_data::<web::Data<TrustedProxies>>() {
+        Some(proxies) => proxies.client_ip(request.peer_addr(), request.headers()),
+        None => request.peer_addr().map(|addr| addr.ip()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+    use actix_web::test::TestRequest;
+    use crate::helpers::client_ip::TrustedProxies;
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap()])
+    }
+
+    fn ip(address: &str) -> Option<IpAddr> {
+        Some(address.parse().unwrap())
+    }
+
+    fn client_ip(peer: &str, forwarded_for: Option<&str>) -> Option<IpAddr> {
+        let peer: SocketAddr = format!("{}:4000", peer).parse().unwrap();
+        let request = TestRequest::default().peer_addr(peer);
+        let request = match forwarded_for {
+            Some(value) => request.insert_header(("X-Forwarded-For", value)),
+            None => request,
+        }.to_http_request();
+        proxies().client_ip(request.peer_addr(), request.headers())
+    }
+
+    #[test]
+    fn the_header_of_an_untrusted_peer_is_ignored() {
+        assert_eq!(client_ip("203.0.113.7", Some("198.51.100.1")), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn the_client_is_read_from_a_trusted_proxy() {
+        assert_eq!(client_ip("10.1.2.3", Some("198.51.100.1")), ip("198.51.100.1"));
+    }
+
+    #[test]
+    fn entries_forged_by_the_client_are_skipped() {
+        assert_eq!(client_ip("10.1.2.3", Some("192.0.2.66, 198.51.100.1, 10.4.5.6")), ip("198.51.100.1"));
+    }
+
+    #[test]
+    fn a_trusted_proxy_without_a_usable_header_is_the_client() {
+        assert_eq!(client_ip("10.1.2.3", None), ip("10.1.2.3"));
+        assert_eq!(client_ip("10.1.2.3", Some("garbage")), ip("10.1.2.3"));
+    }
+}
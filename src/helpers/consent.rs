@@ -0,0 +1,114 @@
+use actix_web::http::header::USER_AGENT;
+use actix_web::HttpRequest;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::helpers::client_ip::client_ip;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ConsentAction {
+    Subscribe,
+    Confirm,
+    /// Added by an admin from a list exported by another tool.
+    Import,
+    /// Added by an admin on the subscriber's behalf.
+    Admin,
+}
+
+impl ConsentAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsentAction::Subscribe => "subscribe",
+            ConsentAction::Confirm => "confirm",
+            ConsentAction::Import => "import",
+            ConsentAction::Admin => "admin",
+        }
+    }
+}
+
+/// Where a consent action came from.
+#[derive(Debug)]
+pub struct ConsentContext {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// Identifier of the form or page the action was taken on.
+    pub source: String,
+    /// Version of the consent wording shown to the subscriber.
+    pub consent_version: String,
+}
+
+impl ConsentContext {
+    pub fn from_request(request: &HttpRequest, source: &str, consent_version: &str) -> Self {
+        let source = match source.trim() {
+            "" => "api",
+            source => source,
+        };
+
+        Self {
+            ip_address: client_ip(request).map(|ip| ip.to_string()),
+            user_agent: request.headers()
+                .get(USER_AGENT)
+                .and_then(|h| h.to_str().ok())
+                .map(str::to_owned),
+            source: source.to_owned(),
+            consent_version: consent_version.to_owned(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct ConsentEvent {
+    pub action: String,
+    pub occurred_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub source: String,
+    pub consent_version: String,
+}
+
+
+#[tracing::instrument(name = "Record a consent event", skip(transaction))]
+pub async fn record_consent_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    action: ConsentAction,
+    context: &ConsentContext,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO consent_events (
+            consent_event_id, subscriber_id, action, occurred_at, ip_address, user_agent, source, consent_version
+        )
+        VALUES ($1, $2, $3, now(), $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        subscriber_id,
+        action.as_str(),
+        context.ip_address,
+        context.user_agent,
+        context.source,
+        context.consent_version,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(name = "Get consent events", skip(pool))]
+pub async fn get_consent_events(pool: &PgPool, subscriber_id: Uuid) -> Result<Vec<ConsentEvent>, sqlx::Error> {
+    sqlx::query_as!(
+        ConsentEvent,
+        r#"
+        SELECT action, occurred_at, ip_address, user_agent, source, consent_version
+        FROM consent_events
+        WHERE subscriber_id = $1
+        ORDER BY occurred_at
+        "#,
+        subscriber_id,
+    )
+        .fetch_all(pool)
+        .await
+}
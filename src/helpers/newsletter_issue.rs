@@ -0,0 +1,254 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::new_issue::NewIssue;
+use crate::domain::subject_test::VariantResult;
+
+
+pub struct NewsletterIssue {
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+}
+
+pub struct ConfirmedRecipient {
+    pub id: Uuid,
+    pub time_zone: Option<String>,
+}
+
+pub struct QueuedDelivery {
+    pub subscriber_id: Uuid,
+    pub variant: i16,
+    pub execute_after: DateTime<Utc>,
+}
+
+
+#[tracing::instrument(name = "Save newsletter issue", skip(transaction, new_issue))]
+pub async fn insert_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    new_issue: &NewIssue,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    let (metric, decide_at, sample_percentage) = match &new_issue.subject_test {
+        Some(test) => (
+            Some(test.metric.as_str()),
+            Some(Utc::now() + test.window),
+            Some(test.sample_percentage() as i16),
+        ),
+        None => (None, None, None),
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            published_at,
+            subject_test_metric,
+            subject_test_sample_percentage,
+            subject_test_decide_at,
+            local_send_time
+        )
+        VALUES ($1, $2, $3, $4, now(), $5, $6, $7, $8)
+        "#,
+        newsletter_issue_id,
+        new_issue.title,
+        new_issue.text_content,
+        new_issue.html_content,
+        metric,
+        sample_percentage,
+        decide_at,
+        new_issue.local_send_time,
+    )
+        .execute(&mut *transaction)
+        .await?;
+
+    let subjects = match &new_issue.subject_test {
+        Some(test) => test.subjects().to_vec(),
+        None => vec![new_issue.title.clone()],
+    };
+
+    for (variant, subject) in subjects.iter().enumerate() {
+        sqlx::query!(
+            r#"
+            INSERT INTO newsletter_issue_variants (newsletter_issue_id, variant, subject)
+            VALUES ($1, $2, $3)
+            "#,
+            newsletter_issue_id,
+            variant as i16,
+            subject,
+        )
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    Ok(newsletter_issue_id)
+}
+
+
+#[tracing::instrument(name = "Get confirmed recipients", skip(transaction))]
+pub async fn get_confirmed_recipients(
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<ConfirmedRecipient>, sqlx::Error> {
+    sqlx::query_as!(
+        ConfirmedRecipient,
+        r#"SELECT id, time_zone FROM subscriptions WHERE status = 'confirmed'"#,
+    )
+        .fetch_all(transaction)
+        .await
+}
+
+
+#[tracing::instrument(name = "Enqueue delivery tasks", skip(transaction, deliveries))]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    deliveries: &[QueuedDelivery],
+) -> Result<(), sqlx::Error> {
+    for delivery in deliveries {
+        sqlx::query!(
+            r#"
+            INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_id, variant, execute_after)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+            newsletter_issue_id,
+            delivery.subscriber_id,
+            delivery.variant,
+            delivery.execute_after,
+        )
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    Ok(())
+}
+
+
+/// Queue the winning subject line for every confirmed subscriber who has not received the issue yet.
+///
+/// Sample members whose send failed are left out too, the provider may have delivered it anyway.
+#[tracing::instrument(name = "Enqueue the remainder of a subject test", skip(transaction))]
+pub async fn enqueue_remainder(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    winning_variant: i16,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_id, variant)
+        SELECT $1, id, $2
+        FROM subscriptions
+        WHERE status = 'confirmed'
+            AND id NOT IN (
+                SELECT subscriber_id FROM issue_deliveries
+                WHERE newsletter_issue_id = $1 AND subscriber_id IS NOT NULL
+            )
+            AND id NOT IN (
+                SELECT subscriber_id FROM issue_delivery_failures WHERE newsletter_issue_id = $1
+            )
+        ON CONFLICT DO NOTHING
+        "#,
+        newsletter_issue_id,
+        winning_variant,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
+
+
+/// Queue a correction for the confirmed subscribers who already received the issue it corrects.
+///
+/// Returns how many recipients were queued.
+#[tracing::instrument(name = "Enqueue a correction", skip(transaction))]
+pub async fn enqueue_correction(
+    transaction: &mut Transaction<'_, Postgres>,
+    correction_issue_id: Uuid,
+    corrected_issue_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE newsletter_issues SET corrects_issue_id = $2 WHERE newsletter_issue_id = $1"#,
+        correction_issue_id,
+        corrected_issue_id,
+    )
+        .execute(&mut *transaction)
+        .await?;
+
+    let queued = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_id, variant)
+        SELECT DISTINCT $1::uuid, d.subscriber_id, 0::smallint
+        FROM issue_deliveries d
+        JOIN subscriptions s ON s.id = d.subscriber_id
+        WHERE d.newsletter_issue_id = $2 AND s.status = 'confirmed'
+        ON CONFLICT DO NOTHING
+        "#,
+        correction_issue_id,
+        corrected_issue_id,
+    )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+
+    Ok(queued)
+}
+
+
+#[tracing::instrument(name = "Get newsletter issue", skip(pool))]
+pub async fn get_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    variant: i16,
+) -> Result<NewsletterIssue, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        SELECT v.subject AS title, i.text_content, i.html_content
+        FROM newsletter_issues i
+        JOIN newsletter_issue_variants v ON v.newsletter_issue_id = i.newsletter_issue_id
+        WHERE i.newsletter_issue_id = $1 AND v.variant = $2
+        "#,
+        newsletter_issue_id,
+        variant,
+    )
+        .fetch_one(pool)
+        .await
+        .context("Failed to retrieve a newsletter issue.")?;
+
+    Ok(issue)
+}
+
+
+#[tracing::instrument(name = "Get subject test results", skip(pool))]
+pub async fn get_variant_results(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Vec<VariantResult>, sqlx::Error> {
+    let results = sqlx::query_as!(
+        VariantResult,
+        r#"
+        SELECT
+            v.variant,
+            COUNT(d.delivery_id) AS "delivered!",
+            COUNT(d.opened_at) AS "opened!",
+            COUNT(d.clicked_at) AS "clicked!"
+        FROM newsletter_issue_variants v
+        LEFT JOIN issue_deliveries d
+            ON d.newsletter_issue_id = v.newsletter_issue_id AND d.variant = v.variant
+        WHERE v.newsletter_issue_id = $1
+        GROUP BY v.variant
+        ORDER BY v.variant
+        "#,
+        newsletter_issue_id,
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(results)
+}
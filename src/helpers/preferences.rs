@@ -0,0 +1,16 @@
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::helpers::signed_token::sign_subscriber_id;
+
+/// Purpose of the signed tokens embedded in preference links.
+pub const PREFERENCES_TOKEN_PURPOSE: &str = "preferences";
+
+/// A link to the preference page of a subscriber, usable without logging in.
+pub fn preferences_url(base_url: &str, hmac_secret: &Secret<String>, subscriber_id: Uuid) -> String {
+    format!(
+        "{}/subscriptions/preferences?token={}",
+        base_url,
+        sign_subscriber_id(hmac_secret, PREFERENCES_TOKEN_PURPOSE, subscriber_id),
+    )
+}
@@ -0,0 +1,253 @@
+use sqlx::{Acquire, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::referral::{generate_referral_code, ReferralReward};
+use crate::utils::is_unique_violation;
+
+/// Codes are random, a clash is rare and several in a row point at something else.
+const REFERRAL_CODE_ATTEMPTS: u32 = 5;
+
+/// How many people a subscriber brought in.
+pub struct ReferralStats {
+    pub referral_code: Option<String>,
+    /// Referrals who confirmed their subscription.
+    pub credited: i64,
+    /// Referrals who signed up but did not confirm yet.
+    pub pending: i64,
+}
+
+pub struct Referrer {
+    pub subscriber_id: Uuid,
+    pub email: String,
+    pub referral_code: Option<String>,
+    pub credited: i64,
+    pub pending: i64,
+}
+
+
+/// Give the subscriber a referral code, unless they have one already.
+#[tracing::instrument(name = "Assign a referral code", skip(transaction))]
+pub async fn assign_referral_code(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let mut attempt = 1;
+    loop {
+        // A savepoint, so that a code that is taken already leaves the transaction usable
+        let mut savepoint = transaction.begin().await?;
+        let outcome = sqlx::query!(
+            r#"UPDATE subscriptions SET referral_code = $2 WHERE id = $1 AND referral_code IS NULL"#,
+            subscriber_id,
+            generate_referral_code(),
+        )
+            .execute(&mut savepoint)
+            .await;
+
+        match outcome {
+            Ok(_) => return savepoint.commit().await,
+            Err(e) if is_unique_violation(&e) && attempt < REFERRAL_CODE_ATTEMPTS => {
+                tracing::warn!(attempt, "The generated referral code is taken already, generating another one.");
+                savepoint.rollback().await?;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+
+/// Record who referred a new subscriber.
+///
+/// Only confirmed subscribers refer, unknown codes are ignored so that a stale link still signs people up.
+#[tracing::instrument(name = "Record the referrer of a new subscriber", skip(transaction))]
+pub async fn record_referrer(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    referral_code: &str,
+) -> Result<bool, sqlx::Error> {
+    let recorded = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET referred_by = referrer.id
+        FROM subscriptions referrer
+        WHERE subscriptions.id = $1
+            AND subscriptions.referred_by IS NULL
+            AND referrer.referral_code = $2
+            AND referrer.status = 'confirmed'
+            AND referrer.id <> $1
+        "#,
+        subscriber_id,
+        referral_code,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(recorded.rows_affected() == 1)
+}
+
+
+/// Credit the referral of a subscriber who just confirmed.
+///
+/// Returns the referrer, once per referral.
+#[tracing::instrument(name = "Credit a referral", skip(transaction))]
+pub async fn credit_referral(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let credited = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET referral_credited_at = now()
+        WHERE id = $1 AND referred_by IS NOT NULL AND referral_credited_at IS NULL
+        RETURNING referred_by AS "referred_by!"
+        "#,
+        subscriber_id,
+    )
+        .fetch_optional(transaction)
+        .await?;
+
+    Ok(credited.map(|c| c.referred_by))
+}
+
+
+/// Mark the rewards the referrer reached as sent and return them.
+///
+/// Rewards are claimed in the transaction crediting the referral, so that each is sent once.
+/// Referrers who left the list get nothing.
+#[tracing::instrument(name = "Claim the referral rewards of a referrer", skip(transaction))]
+pub async fn claim_referral_rewards(
+    transaction: &mut Transaction<'_, Postgres>,
+    referrer_id: Uuid,
+) -> Result<Vec<ReferralReward>, sqlx::Error> {
+    sqlx::query_as!(
+        ReferralReward,
+        r#"
+        WITH reached AS (
+            INSERT INTO referral_rewards_sent (subscriber_id, milestone, sent_at)
+            SELECT $1, r.milestone, now()
+            FROM referral_rewards r
+            WHERE r.milestone <= (
+                SELECT count(*) FROM subscriptions
+                WHERE referred_by = $1 AND referral_credited_at IS NOT NULL
+            )
+                AND EXISTS (SELECT 1 FROM subscriptions WHERE id = $1 AND status = 'confirmed')
+            ON CONFLICT (subscriber_id, milestone) DO NOTHING
+            RETURNING milestone
+        )
+        SELECT r.milestone, r.subject, r.text_content, r.html_content
+        FROM referral_rewards r
+        JOIN reached ON reached.milestone = r.milestone
+        ORDER BY r.milestone
+        "#,
+        referrer_id,
+    )
+        .fetch_all(transaction)
+        .await
+}
+
+
+/// Undo `claim_referral_rewards` for a reward that could not be sent after all.
+#[tracing::instrument(name = "Release a referral reward", skip(pool))]
+pub async fn release_referral_reward(pool: &PgPool, referrer_id: Uuid, milestone: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM referral_rewards_sent WHERE subscriber_id = $1 AND milestone = $2"#,
+        referrer_id,
+        milestone,
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(name = "Get the referral statistics of a subscriber", skip(pool))]
+pub async fn get_referral_stats(pool: &PgPool, subscriber_id: Uuid) -> Result<Option<ReferralStats>, sqlx::Error> {
+    sqlx::query_as!(
+        ReferralStats,
+        r#"
+        SELECT
+            s.referral_code,
+            count(referral.id) FILTER (WHERE referral.referral_credited_at IS NOT NULL) AS "credited!",
+            count(referral.id) FILTER (
+                WHERE referral.referral_credited_at IS NULL AND referral.status = 'pending_confirmation'
+            ) AS "pending!"
+        FROM subscriptions s
+        LEFT JOIN subscriptions referral ON referral.referred_by = s.id
+        WHERE s.id = $1
+        GROUP BY s.id
+        "#,
+        subscriber_id,
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+
+/// Subscribers who referred at least one other, most credited referrals first.
+#[tracing::instrument(name = "Get the top referrers", skip(pool))]
+pub async fn get_top_referrers(pool: &PgPool, limit: i64) -> Result<Vec<Referrer>, sqlx::Error> {
+    sqlx::query_as!(
+        Referrer,
+        r#"
+        SELECT
+            s.id AS subscriber_id,
+            s.email,
+            s.referral_code,
+            count(*) FILTER (WHERE referral.referral_credited_at IS NOT NULL) AS "credited!",
+            count(*) FILTER (
+                WHERE referral.referral_credited_at IS NULL AND referral.status = 'pending_confirmation'
+            ) AS "pending!"
+        FROM subscriptions s
+        JOIN subscriptions referral ON referral.referred_by = s.id
+        GROUP BY s.id
+        ORDER BY 4 DESC, 5 DESC, s.email
+        LIMIT $1
+        "#,
+        limit,
+    )
+        .fetch_all(pool)
+        .await
+}
+
+
+#[tracing::instrument(name = "Get the referral rewards", skip(pool))]
+pub async fn get_referral_rewards(pool: &PgPool) -> Result<Vec<ReferralReward>, sqlx::Error> {
+    sqlx::query_as!(
+        ReferralReward,
+        r#"SELECT milestone, subject, text_content, html_content FROM referral_rewards ORDER BY milestone"#,
+    )
+        .fetch_all(pool)
+        .await
+}
+
+
+/// Returns `false` when there is a reward for the milestone already.
+#[tracing::instrument(name = "Insert a referral reward", skip(pool, reward))]
+pub async fn insert_referral_reward(pool: &PgPool, reward: &ReferralReward) -> Result<bool, sqlx::Error> {
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO referral_rewards (milestone, subject, text_content, html_content, created_at)
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (milestone) DO NOTHING
+        "#,
+        reward.milestone,
+        reward.subject,
+        reward.text_content,
+        reward.html_content,
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(inserted.rows_affected() == 1)
+}
+
+
+#[tracing::instrument(name = "Delete a referral reward", skip(pool))]
+pub async fn delete_referral_reward(pool: &PgPool, milestone: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(r#"DELETE FROM referral_rewards WHERE milestone = $1"#, milestone)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Sign `payload` for a given `purpose`: `{payload}.{hex encoded HMAC}`.
+///
+/// The purpose is part of the MAC, so a token minted for one flow cannot be replayed against another.
+pub fn sign(secret: &Secret<String>, purpose: &str, payload: &str) -> String {
+    let tag = mac(secret, purpose, payload).finalize().into_bytes();
+    format!("{}.{}", payload, hex::encode(tag))
+}
+
+/// Return the payload of `token` if it was signed by us for `purpose`.
+pub fn verify(secret: &Secret<String>, purpose: &str, token: &str) -> Option<String> {
+    let separator = token.rfind('.')?;
+    let (payload, tag) = (&token[..separator], &token[separator + 1..]);
+    let tag = hex::decode(tag).ok()?;
+
+    mac(secret, purpose, payload).verify_slice(&tag).ok()?;
+    Some(payload.to_string())
+}
+
+pub fn sign_subscriber_id(secret: &Secret<String>, purpose: &str, subscriber_id: Uuid) -> String {
+    sign(secret, purpose, &subscriber_id.to_string())
+}
+
+pub fn verify_subscriber_id(secret: &Secret<String>, purpose: &str, token: &str) -> Option<Uuid> {
+    verify(secret, purpose, token).and_then(|payload| Uuid::parse_str(&payload).ok())
+}
+
+fn mac(secret: &Secret<String>, purpose: &str, payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.expose_secret().as_bytes()).unwrap();
+    mac.update(purpose.as_bytes());
+    mac.update(b":");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::signed_token::{sign, sign_subscriber_id, verify, verify_subscriber_id};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    fn secret() -> Secret<String> {
+        Secret::new("a-very-secret-key".to_string())
+    }
+
+    #[test]
+    fn a_signed_payload_is_verified() {
+        let token = sign(&secret(), "test", "payload");
+        assert_eq!(verify(&secret(), "test", &token), Some("payload".to_string()));
+    }
+
+    #[test]
+    fn a_tampered_payload_is_rejected() {
+        let token = sign(&secret(), "test", "payload").replacen("payload", "payloae", 1);
+        assert_eq!(verify(&secret(), "test", &token), None);
+    }
+
+    #[test]
+    fn a_token_for_another_purpose_is_rejected() {
+        let token = sign(&secret(), "preferences", "payload");
+        assert_eq!(verify(&secret(), "erasure", &token), None);
+    }
+
+    #[test]
+    fn a_token_signed_with_another_key_is_rejected() {
+        let token = sign(&Secret::new("another-key".to_string()), "test", "payload");
+        assert_eq!(verify(&secret(), "test", &token), None);
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert_eq!(verify(&secret(), "test", "no-separator"), None);
+        assert_eq!(verify(&secret(), "test", "payload.not-hex"), None);
+    }
+
+    #[test]
+    fn subscriber_ids_roundtrip() {
+        let id = Uuid::new_v4();
+        let token = sign_subscriber_id(&secret(), "test", id);
+        assert_eq!(verify_subscriber_id(&secret(), "test", &token), Some(id));
+    }
+}
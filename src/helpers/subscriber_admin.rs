@@ -0,0 +1,149 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::domain::subscriber_name::SubscriberName;
+use crate::domain::subscription_status::SubscriptionStatus;
+use crate::errors::status_change_error::StatusChangeError;
+use crate::helpers::referrals::assign_referral_code;
+use crate::helpers::subscriber_data::{erase_subscriber, Erasure};
+use crate::helpers::subscription_status::{change_status, record_initial_status};
+
+pub struct SubscriberSummary {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+}
+
+
+/// Whether a subscriber other than `except` has the same address, ignoring case.
+#[tracing::instrument(name = "Check whether an address is taken", skip(transaction, email))]
+pub async fn is_email_taken(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &SubscriberEmail,
+    except: Option<Uuid>,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM subscriptions
+            WHERE lower(normalized_email) = lower($1) AND ($2::uuid IS NULL OR id <> $2)
+        ) AS "taken!"
+        "#,
+        email.normalized(),
+        except,
+    )
+        .fetch_one(transaction)
+        .await?;
+
+    Ok(row.taken)
+}
+
+
+#[tracing::instrument(name = "Insert a subscriber on someone's behalf", skip(transaction, email, name))]
+pub async fn insert_subscriber_by_admin(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &SubscriberEmail,
+    name: &SubscriberName,
+    status: SubscriptionStatus,
+) -> Result<Uuid, sqlx::Error> {
+    let subscriber = sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (email, normalized_email, name, subscribed_at, status)
+        VALUES ($1, $2, $3, now(), $4)
+        RETURNING id
+        "#,
+        email.as_ref(),
+        email.normalized(),
+        name.as_ref(),
+        status as SubscriptionStatus,
+    )
+        .fetch_one(&mut *transaction)
+        .await?;
+    record_initial_status(transaction, subscriber.id, status, "admin").await?;
+    if status == SubscriptionStatus::Confirmed {
+        assign_referral_code(transaction, subscriber.id).await?;
+    }
+
+    Ok(subscriber.id)
+}
+
+
+/// Returns `false` when the subscriber does not exist.
+#[tracing::instrument(name = "Correct the details of a subscriber", skip(transaction, email, name))]
+pub async fn update_subscriber_details(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    email: &SubscriberEmail,
+    name: &SubscriberName,
+) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query!(
+        r#"UPDATE subscriptions SET email = $2, normalized_email = $3, name = $4 WHERE id = $1"#,
+        subscriber_id,
+        email.as_ref(),
+        email.normalized(),
+        name.as_ref(),
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(updated.rows_affected() == 1)
+}
+
+
+#[tracing::instrument(name = "Get a selection of subscribers", skip(pool))]
+pub async fn get_subscriber_summaries(
+    pool: &PgPool,
+    subscriber_ids: &[Uuid],
+) -> Result<Vec<SubscriberSummary>, sqlx::Error> {
+    sqlx::query_as!(
+        SubscriberSummary,
+        r#"SELECT id, email, name, status::text AS "status!" FROM subscriptions WHERE id = ANY($1) ORDER BY email"#,
+        subscriber_ids,
+    )
+        .fetch_all(pool)
+        .await
+}
+
+
+/// Returns how many subscribers were unsubscribed.
+///
+/// Subscribers the lifecycle does not allow to unsubscribe, such as those who already left, are skipped.
+#[tracing::instrument(name = "Unsubscribe subscribers", skip(pool))]
+pub async fn unsubscribe_subscribers(pool: &PgPool, subscriber_ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let mut unsubscribed = 0;
+    for subscriber_id in subscriber_ids {
+        match change_status(&mut transaction, *subscriber_id, SubscriptionStatus::Unsubscribed, "admin").await {
+            Ok(_) => unsubscribed += 1,
+            Err(StatusChangeError::NotAllowed(_)) | Err(StatusChangeError::NotFound) => {}
+            Err(StatusChangeError::Database(e)) => return Err(e),
+        }
+    }
+    // Issues already queued are not delivered any more
+    sqlx::query!(r#"DELETE FROM issue_delivery_queue WHERE subscriber_id = ANY($1)"#, subscriber_ids)
+        .execute(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok(unsubscribed)
+}
+
+
+/// Delete the records of the subscribers, who may sign up again later.
+///
+/// Their addresses are not suppressed, unlike an erasure request.
+#[tracing::instrument(name = "Delete subscribers", skip(pool))]
+pub async fn delete_subscribers(pool: &PgPool, subscriber_ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let mut deleted = 0;
+    for subscriber_id in subscriber_ids {
+        if erase_subscriber(&mut transaction, *subscriber_id, Erasure::Deleted).await? {
+            deleted += 1;
+        }
+    }
+    transaction.commit().await?;
+
+    Ok(deleted)
+}
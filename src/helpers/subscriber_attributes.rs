@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::subscriber_attribute::{AttributeDefinition, AttributeKind, AttributeValue, NewAttribute};
+
+/// The value of an attribute, as shown to admins.
+pub struct StoredAttribute {
+    pub key: String,
+    pub label: String,
+    pub value: String,
+}
+
+
+#[tracing::instrument(name = "Get attribute definitions", skip(pool))]
+pub async fn get_attribute_definitions(pool: &PgPool) -> Result<Vec<AttributeDefinition>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT attribute_id, key, label, kind, options, required
+        FROM subscriber_attributes
+        ORDER BY created_at, key
+        "#,
+    )
+        .fetch_all(pool)
+        .await?;
+
+    rows.into_iter()
+        .map(|r| {
+            Ok(AttributeDefinition {
+                attribute_id: r.attribute_id,
+                key: r.key,
+                label: r.label,
+                kind: AttributeKind::parse(&r.kind).map_err(anyhow::Error::msg)?,
+                options: r.options,
+                required: r.required,
+            })
+        })
+        .collect()
+}
+
+
+/// Returns `false` when an attribute with the same key exists already.
+#[tracing::instrument(name = "Insert attribute definition", skip(pool))]
+pub async fn insert_attribute_definition(pool: &PgPool, attribute: &NewAttribute) -> Result<bool, sqlx::Error> {
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO subscriber_attributes (attribute_id, key, label, kind, options, required, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (key) DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        attribute.key,
+        attribute.label,
+        attribute.kind.as_str(),
+        &attribute.options,
+        attribute.required,
+        Utc::now(),
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(inserted.rows_affected() == 1)
+}
+
+
+/// The values of the attribute are deleted along with it.
+#[tracing::instrument(name = "Delete attribute definition", skip(pool))]
+pub async fn delete_attribute_definition(pool: &PgPool, attribute_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(r#"DELETE FROM subscriber_attributes WHERE attribute_id = $1"#, attribute_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(name = "Save attribute values", skip(transaction, values))]
+pub async fn save_attribute_values(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    values: &[AttributeValue],
+) -> Result<(), sqlx::Error> {
+    for value in values {
+        sqlx::query!(
+            r#"
+            INSERT INTO subscriber_attribute_values (subscriber_id, attribute_id, value)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (subscriber_id, attribute_id) DO UPDATE SET value = EXCLUDED.value
+            "#,
+            subscriber_id,
+            value.attribute_id,
+            value.value,
+        )
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    Ok(())
+}
+
+
+#[tracing::instrument(name = "Get attribute values of a subscriber", skip(pool))]
+pub async fn get_attribute_values(pool: &PgPool, subscriber_id: Uuid) -> Result<Vec<StoredAttribute>, sqlx::Error> {
+    sqlx::query_as!(
+        StoredAttribute,
+        r#"
+        SELECT a.key, a.label, v.value
+        FROM subscriber_attribute_values v
+        JOIN subscriber_attributes a ON a.attribute_id = v.attribute_id
+        WHERE v.subscriber_id = $1
+        ORDER BY a.created_at, a.key
+        "#,
+        subscriber_id,
+    )
+        .fetch_all(pool)
+        .await
+}
+
+
+/// The values available to the merge fields of an email: name, email and every custom attribute.
+#[tracing::instrument(name = "Get merge values of a subscriber", skip(pool))]
+pub async fn get_merge_values(pool: &PgPool, subscriber_id: Uuid) -> Result<HashMap<String, String>, sqlx::Error> {
+    let subscriber = sqlx::query!(r#"SELECT email, name FROM subscriptions WHERE id = $1"#, subscriber_id)
+        .fetch_optional(pool)
+        .await?;
+
+    let mut values: HashMap<String, String> = get_attribute_values(pool, subscriber_id)
+        .await?
+        .into_iter()
+        .map(|attribute| (attribute.key, attribute.value))
+        .collect();
+    if let Some(subscriber) = subscriber {
+        values.insert("email".into(), subscriber.email);
+        values.insert("name".into(), subscriber.name);
+    }
+
+    Ok(values)
+}
@@ -0,0 +1,185 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::helpers::consent::{get_consent_events, ConsentEvent};
+use crate::helpers::subscriber_attributes::get_attribute_values;
+use crate::helpers::subscription_status::{get_subscription_events, SubscriptionEvent};
+use crate::helpers::suppression::suppress;
+use crate::helpers::tags::get_subscriber_tags;
+
+/// Everything we hold about a subscriber, as handed out for a data access request.
+#[derive(serde::Serialize)]
+pub struct SubscriberExport {
+    pub subscription: SubscriptionRecord,
+    pub tags: Vec<String>,
+    pub attributes: Vec<AttributeRecord>,
+    pub confirmation_tokens: Vec<TokenRecord>,
+    pub consent_events: Vec<ConsentEvent>,
+    pub status_history: Vec<SubscriptionEvent>,
+    pub deliveries: Vec<DeliveryRecord>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriptionRecord {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub time_zone: Option<String>,
+    pub referral_code: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct AttributeRecord {
+    pub label: String,
+    pub value: String,
+}
+
+/// Token values are left out, they are credentials rather than personal data.
+#[derive(serde::Serialize)]
+pub struct TokenRecord {
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(serde::Serialize)]
+pub struct DeliveryRecord {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub delivered_at: DateTime<Utc>,
+    pub opened_at: Option<DateTime<Utc>>,
+    pub clicked_at: Option<DateTime<Utc>>,
+}
+
+
+#[tracing::instrument(name = "Export subscriber data", skip(pool))]
+pub async fn export_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<Option<SubscriberExport>, sqlx::Error> {
+    let subscription = sqlx::query_as!(
+        SubscriptionRecord,
+        r#"
+        SELECT id, email, name, status::text AS "status!", subscribed_at, time_zone, referral_code
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id,
+    )
+        .fetch_optional(pool)
+        .await?;
+    let subscription = match subscription {
+        Some(subscription) => subscription,
+        None => return Ok(None),
+    };
+
+    let tags = get_subscriber_tags(pool, subscriber_id).await?;
+
+    Ok(Some(SubscriberExport {
+        subscription,
+        tags,
+        attributes: get_attribute_values(pool, subscriber_id)
+            .await?
+            .into_iter()
+            .map(|attribute| AttributeRecord { label: attribute.label, value: attribute.value })
+            .collect(),
+        confirmation_tokens: get_confirmation_tokens(pool, subscriber_id).await?,
+        consent_events: get_consent_events(pool, subscriber_id).await?,
+        status_history: get_subscription_events(pool, subscriber_id).await?,
+        deliveries: get_deliveries(pool, subscriber_id).await?,
+    }))
+}
+
+
+#[tracing::instrument(name = "Get confirmation tokens of a subscriber", skip(pool))]
+pub async fn get_confirmation_tokens(pool: &PgPool, subscriber_id: Uuid) -> Result<Vec<TokenRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        TokenRecord,
+        r#"
+        SELECT created_at, expires_at, consumed_at FROM subscription_tokens
+        WHERE subscriber_id = $1
+        ORDER BY created_at
+        "#,
+        subscriber_id,
+    )
+        .fetch_all(pool)
+        .await
+}
+
+
+#[tracing::instrument(name = "Get deliveries to a subscriber", skip(pool))]
+pub async fn get_deliveries(pool: &PgPool, subscriber_id: Uuid) -> Result<Vec<DeliveryRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        DeliveryRecord,
+        r#"
+        SELECT d.newsletter_issue_id, v.subject AS title, d.delivered_at, d.opened_at, d.clicked_at
+        FROM issue_deliveries d
+        JOIN newsletter_issue_variants v
+            ON v.newsletter_issue_id = d.newsletter_issue_id AND v.variant = d.variant
+        WHERE d.subscriber_id = $1
+        ORDER BY d.delivered_at
+        "#,
+        subscriber_id,
+    )
+        .fetch_all(pool)
+        .await
+}
+
+
+/// Why the data of a subscriber is erased.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Erasure {
+    /// The subscriber asked to be forgotten.
+    Requested,
+    /// An admin deleted the subscriber, who may sign up again later.
+    Deleted,
+    /// The subscriber did not confirm within the retention period.
+    Expired,
+}
+
+
+/// Delete the personal data of a subscriber, consent history included.
+///
+/// This is the only way to remove consent events, the table rejects any other deletion. Deliveries
+/// are kept without the subscriber, so issue statistics stay intact. When the subscriber asked for
+/// the erasure, the address is suppressed so that it does not come back through an import.
+#[tracing::instrument(name = "Erase subscriber data", skip(transaction))]
+pub async fn erase_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    erasure: Erasure,
+) -> Result<bool, sqlx::Error> {
+    let subscriber = sqlx::query!(
+        r#"SELECT normalized_email FROM subscriptions WHERE id = $1 FOR UPDATE"#,
+        subscriber_id,
+    )
+        .fetch_optional(&mut *transaction)
+        .await?;
+    let email = match subscriber {
+        Some(subscriber) => subscriber.normalized_email,
+        None => return Ok(false),
+    };
+
+    if erasure == Erasure::Requested {
+        suppress(transaction, &email, "erasure").await?;
+    }
+    sqlx::query!(r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#, subscriber_id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM issue_delivery_queue WHERE subscriber_id = $1"#, subscriber_id)
+        .execute(&mut *transaction)
+        .await?;
+    // Lets the append-only trigger of `consent_events` through, until the end of the transaction
+    sqlx::query!(r#"SELECT set_config('zero2prod.erasing_subscriber', 'on', true)"#)
+        .fetch_one(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM consent_events WHERE subscriber_id = $1"#, subscriber_id)
+        .execute(&mut *transaction)
+        .await?;
+    // Tags and attribute values are deleted by cascade, `issue_deliveries.subscriber_id` is set to NULL
+    sqlx::query!(r#"DELETE FROM subscriptions WHERE id = $1"#, subscriber_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    Ok(true)
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+use actix_web::web::Bytes;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::subscriber_export::{ExportFilter, ExportFormat};
+use crate::domain::subscription_status::SubscriptionStatus;
+
+/// Rows fetched per query, so that memory use does not grow with the size of the list.
+const BATCH_SIZE: i64 = 1000;
+
+const CSV_HEADER: &[&str] = &[
+    "id", "email", "name", "status", "subscribed_at", "time_zone", "tags",
+    "consented_at", "consent_source", "consent_version",
+];
+
+#[derive(serde::Serialize)]
+pub struct ExportedSubscriber {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub time_zone: Option<String>,
+    pub tags: Vec<String>,
+    /// Custom attributes by key, only those the subscriber has a value for.
+    pub attributes: BTreeMap<String, String>,
+    /// The latest entry of the consent trail.
+    pub consented_at: Option<DateTime<Utc>>,
+    pub consent_source: Option<String>,
+    pub consent_version: Option<String>,
+}
+
+struct ExportRow {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    time_zone: Option<String>,
+    tags: Vec<String>,
+    attribute_keys: Vec<String>,
+    attribute_values: Vec<String>,
+    consented_at: Option<DateTime<Utc>>,
+    consent_source: Option<String>,
+    consent_version: Option<String>,
+}
+
+impl From<ExportRow> for ExportedSubscriber {
+    fn from(row: ExportRow) -> Self {
+        Self {
+            id: row.id,
+            email: row.email,
+            name: row.name,
+            status: row.status,
+            subscribed_at: row.subscribed_at,
+            time_zone: row.time_zone,
+            tags: row.tags,
+            attributes: row.attribute_keys.into_iter().zip(row.attribute_values).collect(),
+            consented_at: row.consented_at,
+            consent_source: row.consent_source,
+            consent_version: row.consent_version,
+        }
+    }
+}
+
+struct Cursor {
+    after: Option<Uuid>,
+    first: bool,
+}
+
+
+/// Stream the subscribers matching `filter`, one batch at a time.
+///
+/// Batches are read with keyset pagination on the subscriber id, so the export does not hold a
+/// transaction open nor load the whole table. CSV exports get one column per key of `attribute_keys`.
+pub fn export_subscribers(
+    pool: PgPool,
+    filter: ExportFilter,
+    format: ExportFormat,
+    attribute_keys: Vec<String>,
+) -> impl Stream<Item = Result<Bytes, anyhow::Error>> {
+    futures::stream::unfold(Some(Cursor { after: None, first: true }), move |cursor| {
+        let pool = pool.clone();
+        let filter = filter.clone();
+        let attribute_keys = attribute_keys.clone();
+        async move {
+            let cursor = cursor?;
+            let batch = match get_batch(&pool, &filter, cursor.after).await {
+                Ok(batch) => batch,
+                Err(e) => {
+                    tracing::error!(error.cause_chain = ?e, "Failed to read a batch of the subscriber export.");
+                    return Some((Err(e.into()), None));
+                }
+            };
+            // The CSV header goes out even when nobody matches
+            if batch.is_empty() && !cursor.first {
+                return None;
+            }
+
+            let next = match batch.last() {
+                Some(last) if batch.len() as i64 == BATCH_SIZE => Some(Cursor { after: Some(last.id), first: false }),
+                _ => None,
+            };
+            Some((encode(&batch, format, &attribute_keys, cursor.first), next))
+        }
+    })
+}
+
+
+fn encode(
+    batch: &[ExportedSubscriber],
+    format: ExportFormat,
+    attribute_keys: &[String],
+    with_header: bool,
+) -> Result<Bytes, anyhow::Error> {
+    let mut buffer = Vec::new();
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut buffer);
+            if with_header {
+                writer.write_record(CSV_HEADER.iter().copied().chain(attribute_keys.iter().map(String::as_str)))?;
+            }
+            for subscriber in batch {
+                let attributes = attribute_keys
+                    .iter()
+                    .map(|key| subscriber.attributes.get(key).cloned().unwrap_or_default());
+                writer.write_record(vec![
+                    subscriber.id.to_string(),
+                    subscriber.email.clone(),
+                    subscriber.name.clone(),
+                    subscriber.status.clone(),
+                    subscriber.subscribed_at.to_rfc3339(),
+                    subscriber.time_zone.clone().unwrap_or_default(),
+                    subscriber.tags.join(", "),
+                    subscriber.consented_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                    subscriber.consent_source.clone().unwrap_or_default(),
+                    subscriber.consent_version.clone().unwrap_or_default(),
+                ].into_iter().chain(attributes))?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Ndjson => {
+            for subscriber in batch {
+                serde_json::to_writer(&mut buffer, subscriber)?;
+                buffer.push(b'\n');
+            }
+        }
+    }
+    Ok(Bytes::from(buffer))
+}
+
+
+#[tracing::instrument(name = "Get a batch of the subscriber export", skip(pool, filter))]
+async fn get_batch(
+    pool: &PgPool,
+    filter: &ExportFilter,
+    after: Option<Uuid>,
+) -> Result<Vec<ExportedSubscriber>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        ExportRow,
+        r#"
+        SELECT
+            s.id,
+            s.email,
+            s.name,
+            s.status::text AS "status!",
+            s.subscribed_at,
+            s.time_zone,
+            COALESCE(
+                array_agg(t.name ORDER BY t.name) FILTER (WHERE t.name IS NOT NULL),
+                '{}'
+            ) AS "tags!",
+            ARRAY(
+                SELECT a.key FROM subscriber_attribute_values v
+                JOIN subscriber_attributes a ON a.attribute_id = v.attribute_id
+                WHERE v.subscriber_id = s.id
+                ORDER BY a.key
+            ) AS "attribute_keys!",
+            ARRAY(
+                SELECT v.value FROM subscriber_attribute_values v
+                JOIN subscriber_attributes a ON a.attribute_id = v.attribute_id
+                WHERE v.subscriber_id = s.id
+                ORDER BY a.key
+            ) AS "attribute_values!",
+            c.occurred_at AS "consented_at?",
+            c.source AS "consent_source?",
+            c.consent_version AS "consent_version?"
+        FROM subscriptions s
+        LEFT JOIN subscription_tags st ON st.subscriber_id = s.id
+        LEFT JOIN tags t ON t.tag_id = st.tag_id
+        LEFT JOIN LATERAL (
+            SELECT occurred_at, source, consent_version FROM consent_events
+            WHERE subscriber_id = s.id
+            ORDER BY occurred_at DESC
+            LIMIT 1
+        ) c ON true
+        WHERE ($1::uuid IS NULL OR s.id > $1)
+            AND ($2::subscription_status IS NULL OR s.status = $2)
+            AND ($3::text IS NULL OR EXISTS (
+                SELECT 1 FROM subscription_tags tagged
+                JOIN tags tag ON tag.tag_id = tagged.tag_id
+                WHERE tagged.subscriber_id = s.id AND tag.name = $3
+            ))
+            AND ($4::timestamptz IS NULL OR s.subscribed_at >= $4)
+            AND ($5::timestamptz IS NULL OR s.subscribed_at < $5)
+            AND ($6::text IS NULL OR EXISTS (
+                SELECT 1 FROM subscriber_attribute_values v
+                JOIN subscriber_attributes a ON a.attribute_id = v.attribute_id
+                WHERE v.subscriber_id = s.id AND a.key = $6 AND v.value = $7
+            ))
+        GROUP BY s.id, c.occurred_at, c.source, c.consent_version
+        ORDER BY s.id
+        LIMIT $8
+        "#,
+        after,
+        filter.status as Option<SubscriptionStatus>,
+        filter.tag,
+        filter.subscribed_from,
+        filter.subscribed_until,
+        filter.attribute.as_ref().map(|(key, _)| key),
+        filter.attribute.as_ref().map(|(_, value)| value),
+        BATCH_SIZE,
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().map(ExportedSubscriber::from).collect())
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::subscriber_listing::{ListCursor, SortColumn, SortDirection, SubscriberListQuery};
+
+#[derive(sqlx::FromRow)]
+pub struct ListedSubscriber {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+    /// The value of the sort column, as text, see `ListCursor`.
+    pub sort_key: String,
+}
+
+impl ListedSubscriber {
+    pub fn cursor(&self) -> ListCursor {
+        ListCursor { sort_key: self.sort_key.clone(), id: self.id }
+    }
+}
+
+
+/// The sort column as the text of a cursor, the expression to order by and the cursor cast back to its type.
+///
+/// Fixed fragments only, nothing the client sends ends up in the SQL.
+fn sort_expressions(sort: SortColumn) -> (&'static str, &'static str, &'static str) {
+    match sort {
+        SortColumn::SubscribedAt => (
+            r#"to_char(s.subscribed_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US"Z"')"#,
+            "s.subscribed_at",
+            "$6::text::timestamptz",
+        ),
+        SortColumn::Email => ("lower(s.email)", "lower(s.email)", "$6::text"),
+        SortColumn::Name => ("lower(s.name)", "lower(s.name)", "$6::text"),
+        SortColumn::Status => ("s.status::text", "s.status", "$6::text::subscription_status"),
+    }
+}
+
+
+/// Up to `limit` subscribers matching `query`, starting after its cursor.
+///
+/// The keyset condition and the order are on the sort column and the id, so that the query walks
+/// the matching index instead of sorting the whole table.
+#[tracing::instrument(name = "List subscribers", skip(pool))]
+pub async fn list_subscribers(
+    pool: &PgPool,
+    query: &SubscriberListQuery,
+    limit: i64,
+) -> Result<Vec<ListedSubscriber>, sqlx::Error> {
+    let (sort_key, order_by, after) = sort_expressions(query.sort);
+    let (comparator, direction) = match query.direction {
+        SortDirection::Ascending => (">", "ASC"),
+        SortDirection::Descending => ("<", "DESC"),
+    };
+    let sql = format!(
+        r#"
+        SELECT s.id, s.email, s.name, s.status::text AS status, s.subscribed_at, {sort_key} AS sort_key
+        FROM subscriptions s
+        WHERE ($1::text IS NULL OR lower(s.email) LIKE $1 OR lower(s.name) LIKE $1)
+            AND ($2::subscription_status IS NULL OR s.status = $2)
+            AND ($3::text IS NULL OR EXISTS (
+                SELECT 1 FROM subscription_tags tagged
+                JOIN tags tag ON tag.tag_id = tagged.tag_id
+                WHERE tagged.subscriber_id = s.id AND tag.name = $3
+            ))
+            AND ($4::timestamptz IS NULL OR s.subscribed_at >= $4)
+            AND ($5::timestamptz IS NULL OR s.subscribed_at < $5)
+            AND ($6::text IS NULL OR ({order_by}, s.id) {comparator} ({after}, $7::uuid))
+        ORDER BY {order_by} {direction}, s.id {direction}
+        LIMIT $8
+        "#,
+        sort_key = sort_key,
+        order_by = order_by,
+        after = after,
+        comparator = comparator,
+        direction = direction,
+    );
+
+    sqlx::query_as::<_, ListedSubscriber>(&sql)
+        .bind(query.search_pattern())
+        .bind(query.filter.status)
+        .bind(&query.filter.tag)
+        .bind(query.filter.subscribed_from)
+        .bind(query.filter.subscribed_until)
+        .bind(query.after.as_ref().map(|cursor| &cursor.sort_key))
+        .bind(query.after.as_ref().map(|cursor| cursor.id))
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
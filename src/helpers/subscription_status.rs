@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::subscription_status::SubscriptionStatus;
+use crate::errors::status_change_error::StatusChangeError;
+
+#[derive(serde::Serialize)]
+pub struct SubscriptionEvent {
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub reason: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+
+/// Move a subscriber to `next`, recording the change in their history.
+///
+/// Every status change goes through here, so that the lifecycle rules hold and the history
+/// is complete. Returns the previous status.
+#[tracing::instrument(name = "Change the status of a subscriber", skip(transaction))]
+pub async fn change_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    next: SubscriptionStatus,
+    reason: &str,
+) -> Result<SubscriptionStatus, StatusChangeError> {
+    // Locked, so that concurrent changes are checked against each other
+    let current = sqlx::query!(
+        r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions WHERE id = $1 FOR UPDATE"#,
+        subscriber_id,
+    )
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(StatusChangeError::NotFound)?
+        .status;
+    current.transition(next).map_err(StatusChangeError::NotAllowed)?;
+
+    sqlx::query!(
+        r#"UPDATE subscriptions SET status = $2 WHERE id = $1"#,
+        subscriber_id,
+        next as SubscriptionStatus,
+    )
+        .execute(&mut *transaction)
+        .await?;
+    append_event(transaction, subscriber_id, Some(current), next, reason).await?;
+
+    Ok(current)
+}
+
+
+/// Start the history of a subscriber who was just inserted with `status`.
+#[tracing::instrument(name = "Record the initial status of a subscriber", skip(transaction))]
+pub async fn record_initial_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    status: SubscriptionStatus,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    append_event(transaction, subscriber_id, None, status, reason).await
+}
+
+
+async fn append_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    from_status: Option<SubscriptionStatus>,
+    to_status: SubscriptionStatus,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_events (
+            subscription_event_id, subscriber_id, from_status, to_status, reason, occurred_at
+        )
+        VALUES ($1, $2, $3, $4, $5, now())
+        "#,
+        Uuid::new_v4(),
+        subscriber_id,
+        from_status as Option<SubscriptionStatus>,
+        to_status as SubscriptionStatus,
+        reason,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(name = "Get the status history of a subscriber", skip(pool))]
+pub async fn get_subscription_events(pool: &PgPool, subscriber_id: Uuid) -> Result<Vec<SubscriptionEvent>, sqlx::Error> {
+    sqlx::query_as!(
+        SubscriptionEvent,
+        r#"
+        SELECT from_status::text, to_status::text AS "to_status!", reason, occurred_at
+        FROM subscription_events
+        WHERE subscriber_id = $1
+        ORDER BY occurred_at
+        "#,
+        subscriber_id,
+    )
+        .fetch_all(pool)
+        .await
+}
@@ -0,0 +1,66 @@
+use sha2::{Digest, Sha256};
+use sqlx::{Postgres, Transaction};
+
+/// Hex encoded SHA-256 of a normalized address, so that suppressions do not keep the address itself.
+///
+/// The address is lowercased first, matching the case-insensitive uniqueness of subscribers.
+pub fn email_hash(email: &str) -> String {
+    hex::encode(Sha256::digest(email.trim().to_lowercase().as_bytes()))
+}
+
+
+#[tracing::instrument(name = "Suppress an email address", skip(transaction, email))]
+pub async fn suppress(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO suppressions (email_hash, reason, suppressed_at)
+        VALUES ($1, $2, now())
+        ON CONFLICT DO NOTHING
+        "#,
+        email_hash(email),
+        reason,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(name = "Check whether an email address is suppressed", skip(transaction, email))]
+pub async fn is_suppressed(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM suppressions WHERE email_hash = $1) AS "suppressed!""#,
+        email_hash(email),
+    )
+        .fetch_one(transaction)
+        .await?;
+
+    Ok(row.suppressed)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::suppression::email_hash;
+
+    #[test]
+    fn the_hash_ignores_case_and_surrounding_whitespace() {
+        assert_eq!(email_hash(" Ursula@Example.com "), email_hash("ursula@example.com"));
+    }
+
+    #[test]
+    fn the_hash_does_not_contain_the_address() {
+        let hash = email_hash("ursula@example.com");
+
+        assert_eq!(hash.len(), 64);
+        assert!(!hash.contains("ursula"));
+    }
+}
@@ -0,0 +1,64 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+
+/// The id of the tag, created on first use.
+#[tracing::instrument(name = "Upsert a tag", skip(transaction))]
+pub async fn upsert_tag(transaction: &mut Transaction<'_, Postgres>, name: &str) -> Result<Uuid, sqlx::Error> {
+    let tag = sqlx::query!(
+        r#"
+        INSERT INTO tags (tag_id, name) VALUES ($1, $2)
+        ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
+        RETURNING tag_id
+        "#,
+        Uuid::new_v4(),
+        name,
+    )
+        .fetch_one(transaction)
+        .await?;
+
+    Ok(tag.tag_id)
+}
+
+
+/// Returns `false` when the subscriber had the tag already.
+#[tracing::instrument(name = "Tag a subscriber", skip(transaction))]
+pub async fn tag_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    tag_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let tagged = sqlx::query!(
+        r#"
+        INSERT INTO subscription_tags (subscriber_id, tag_id) VALUES ($1, $2)
+        ON CONFLICT (subscriber_id, tag_id) DO NOTHING
+        "#,
+        subscriber_id,
+        tag_id,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(tagged.rows_affected() == 1)
+}
+
+
+#[tracing::instrument(name = "Get the tags of a subscriber", skip(pool))]
+pub async fn get_subscriber_tags(pool: &PgPool, subscriber_id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    let tags = sqlx::query!(
+        r#"
+        SELECT t.name FROM tags t
+        JOIN subscription_tags st ON st.tag_id = t.tag_id
+        WHERE st.subscriber_id = $1
+        ORDER BY t.name
+        "#,
+        subscriber_id,
+    )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| r.name)
+        .collect();
+
+    Ok(tags)
+}
@@ -0,0 +1,84 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::welcome_email::{LatestIssue, WelcomeEmail};
+
+
+#[tracing::instrument(name = "Get the welcome email", skip(pool))]
+pub async fn get_welcome_email(pool: &PgPool) -> Result<WelcomeEmail, sqlx::Error> {
+    sqlx::query_as!(
+        WelcomeEmail,
+        r#"SELECT enabled, subject, text_content, html_content, include_latest_issue FROM welcome_email"#,
+    )
+        .fetch_one(pool)
+        .await
+}
+
+
+#[tracing::instrument(name = "Save the welcome email", skip(pool, welcome_email))]
+pub async fn save_welcome_email(pool: &PgPool, welcome_email: &WelcomeEmail) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE welcome_email
+        SET enabled = $1, subject = $2, text_content = $3, html_content = $4, include_latest_issue = $5,
+            updated_at = now()
+        "#,
+        welcome_email.enabled,
+        welcome_email.subject,
+        welcome_email.text_content,
+        welcome_email.html_content,
+        welcome_email.include_latest_issue,
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+
+/// The most recent issue that was not cancelled.
+#[tracing::instrument(name = "Get the latest issue", skip(pool))]
+pub async fn get_latest_issue(pool: &PgPool) -> Result<Option<LatestIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        LatestIssue,
+        r#"
+        SELECT title, text_content, html_content
+        FROM newsletter_issues
+        WHERE delivery_status <> 'cancelled'
+        ORDER BY published_at DESC
+        LIMIT 1
+        "#,
+    )
+        .fetch_optional(pool)
+        .await
+}
+
+
+/// Mark the welcome email as sent to the subscriber.
+///
+/// Returns `false` when it was sent already, so that whoever gets `true` sends it, once.
+#[tracing::instrument(name = "Claim the welcome email of a subscriber", skip(transaction))]
+pub async fn claim_welcome_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let claimed = sqlx::query!(
+        r#"UPDATE subscriptions SET welcome_sent_at = now() WHERE id = $1 AND welcome_sent_at IS NULL"#,
+        subscriber_id,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(claimed.rows_affected() == 1)
+}
+
+
+/// Undo `claim_welcome_email` when the email could not be sent after all.
+#[tracing::instrument(name = "Release the welcome email of a subscriber", skip(pool))]
+pub async fn release_welcome_email(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(r#"UPDATE subscriptions SET welcome_sent_at = NULL WHERE id = $1"#, subscriber_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
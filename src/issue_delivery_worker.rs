@@ -0,0 +1,233 @@
+use std::time::Duration;
+use secrecy::Secret;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+use crate::configuration::settings::Settings;
+use crate::domain::merge_fields::render_merge_fields;
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::email::email_client::EmailClient;
+use crate::email::footer::with_preferences_footer;
+use crate::email::message_options::MessageOptions;
+use crate::email::tracking::instrument_html;
+use crate::errors::send_email_error::SendEmailError;
+use crate::helpers::newsletter_issue::get_issue;
+use crate::helpers::preferences::preferences_url;
+use crate::helpers::subscriber_attributes::get_merge_values;
+use crate::startup::application::get_connection_pool;
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+    /// The email provider circuit breaker is open, the task stays in the queue.
+    ProviderUnavailable,
+}
+
+struct DeliveryTask {
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    subscriber_email: String,
+    variant: i16,
+}
+
+
+pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    let email_client = configuration.email_client.client();
+
+    worker_loop(
+        connection_pool,
+        email_client,
+        configuration.application.base_url,
+        configuration.application.hmac_secret,
+    ).await
+}
+
+
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: String,
+    hmac_secret: Secret<String>,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_execute_task(&pool, &email_client, &base_url, &hmac_secret).await {
+            Ok(ExecutionOutcome::EmptyQueue) | Ok(ExecutionOutcome::ProviderUnavailable) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+
+#[tracing::instrument(
+    skip_all,
+    fields(
+        newsletter_issue_id=tracing::field::Empty,
+        subscriber_id=tracing::field::Empty,
+    ),
+    err
+)]
+pub async fn try_execute_task(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let task = dequeue_task(pool).await?;
+    if task.is_none() {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    }
+    let (mut transaction, task) = task.unwrap();
+
+    Span::current()
+        .record("newsletter_issue_id", &display(task.newsletter_issue_id))
+        .record("subscriber_id", &display(task.subscriber_id));
+
+    match SubscriberEmail::parse(task.subscriber_email.clone()) {
+        Ok(email) => {
+            let issue = get_issue(pool, task.newsletter_issue_id, task.variant).await?;
+            let merge_values = get_merge_values(pool, task.subscriber_id).await?;
+            let title = render_merge_fields(&issue.title, &merge_values, false);
+            let delivery_id = Uuid::new_v4();
+            // The footer is added after instrumentation so that the preferences link is not click tracked
+            let (html_content, text_content) = with_preferences_footer(
+                &instrument_html(&render_merge_fields(&issue.html_content, &merge_values, true), base_url, delivery_id),
+                &render_merge_fields(&issue.text_content, &merge_values, false),
+                &preferences_url(base_url, hmac_secret, task.subscriber_id),
+            );
+
+            let mut options = MessageOptions::broadcast();
+            options.tag = Some(task.newsletter_issue_id.to_string());
+            options.metadata.insert("issue_id".into(), task.newsletter_issue_id.to_string());
+            options.metadata.insert("subscriber_id".into(), task.subscriber_id.to_string());
+
+            match email_client
+                .send_email(&email, &title, &html_content, &text_content, &[], &options)
+                .await
+            {
+                Ok(()) => {
+                    record_delivery(&mut transaction, delivery_id, &task).await?;
+                }
+                Err(SendEmailError::CircuitOpen) => {
+                    // Dropping the transaction releases the task for a later attempt
+                    return Ok(ExecutionOutcome::ProviderUnavailable);
+                }
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to deliver issue to a confirmed subscriber. Skipping.",
+                    );
+                    record_failure(&mut transaction, &task).await?;
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!(
+                error.message = %e,
+                "Skipping a confirmed subscriber. Their stored contact details are invalid",
+            );
+            record_failure(&mut transaction, &task).await?;
+        }
+    }
+
+    delete_task(transaction, task.newsletter_issue_id, task.subscriber_id).await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_task(pool: &PgPool) -> Result<Option<(PgTransaction, DeliveryTask)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let task = sqlx::query_as!(
+        DeliveryTask,
+        r#"
+        SELECT q.newsletter_issue_id, q.subscriber_id, s.email AS subscriber_email, q.variant
+        FROM issue_delivery_queue q
+        JOIN subscriptions s ON s.id = q.subscriber_id
+        JOIN newsletter_issues i ON i.newsletter_issue_id = q.newsletter_issue_id
+        WHERE q.execute_after <= now()
+            AND i.delivery_status = 'sending'
+        FOR UPDATE OF q
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+        .fetch_optional(&mut transaction)
+        .await?;
+
+    Ok(task.map(|t| (transaction, t)))
+}
+
+
+#[tracing::instrument(skip_all)]
+async fn record_delivery(
+    transaction: &mut PgTransaction,
+    delivery_id: Uuid,
+    task: &DeliveryTask,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_deliveries (delivery_id, newsletter_issue_id, subscriber_id, variant, delivered_at)
+        VALUES ($1, $2, $3, $4, now())
+        "#,
+        delivery_id,
+        task.newsletter_issue_id,
+        task.subscriber_id,
+        task.variant,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
+
+
+/// Remember who the issue could not be sent to, `enqueue_remainder` leaves them out.
+#[tracing::instrument(skip_all)]
+async fn record_failure(transaction: &mut PgTransaction, task: &DeliveryTask) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_failures (newsletter_issue_id, subscriber_id, failed_at)
+        VALUES ($1, $2, now())
+        ON CONFLICT DO NOTHING
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_id,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    mut transaction: PgTransaction,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1 AND subscriber_id = $2
+        "#,
+        newsletter_issue_id,
+        subscriber_id,
+    )
+        .execute(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
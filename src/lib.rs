@@ -1,4 +1,5 @@
 #![allow(clippy::toplevel_ref_arg)]
+#![allow(clippy::async_yields_async)]
 pub mod configuration;
 pub mod domain;
 pub mod email;
@@ -8,4 +9,9 @@ pub mod routes;
 pub mod startup;
 pub mod telemetry;
 pub mod session_state;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod issue_delivery_worker;
+pub mod subject_test_worker;
+pub mod subscription_cleanup_worker;
+pub mod rate_limiting;
+pub mod automation_worker;
\ No newline at end of file
@@ -1,3 +1,8 @@
+use std::fmt::{Debug, Display};
+use zero2prod::automation_worker::run_automation_worker_until_stopped;
+use zero2prod::issue_delivery_worker::run_worker_until_stopped;
+use zero2prod::subject_test_worker::run_subject_test_worker_until_stopped;
+use zero2prod::subscription_cleanup_worker::run_cleanup_worker_until_stopped;
 use zero2prod::telemetry::init_subscriber;
 use zero2prod::{startup::application::Application, telemetry::get_subscriber};
 use zero2prod::configuration::settings::get_configuration;
@@ -9,9 +14,37 @@ async fn main() -> anyhow::Result<()> {
     init_subscriber(subscriber);
     
     let configuration = get_configuration().expect("Failed to read configurations");
-    let application = Application::build(configuration).await?;
-    application.run_until_stopped().await?;
+    let application = Application::build(configuration.clone()).await?;
+    let application_task = application.run_until_stopped();
+    let delivery_worker_task = run_worker_until_stopped(configuration.clone());
+    let subject_test_worker_task = run_subject_test_worker_until_stopped(configuration.clone());
+    let cleanup_worker_task = run_cleanup_worker_until_stopped(configuration.clone());
+    let automation_worker_task = run_automation_worker_until_stopped(configuration);
+
+    tokio::select! {
+        o = application_task => report_exit("API", o),
+        o = delivery_worker_task => report_exit("Delivery worker", o),
+        o = subject_test_worker_task => report_exit("Subject test worker", o),
+        o = cleanup_worker_task => report_exit("Subscription cleanup worker", o),
+        o = automation_worker_task => report_exit("Automation worker", o),
+    };
 
     Ok(())
 }
 
+
+fn report_exit(task_name: &str, outcome: Result<(), impl Debug + Display>) {
+    match outcome {
+        Ok(()) => {
+            tracing::info!("{} has exited", task_name)
+        }
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} failed",
+                task_name
+            )
+        }
+    }
+}
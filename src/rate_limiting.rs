@@ -0,0 +1,3 @@
+pub mod limiter;
+pub mod middleware;
+pub mod store;
\ No newline at end of file
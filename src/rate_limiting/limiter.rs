@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::errors::rate_limit_error::RateLimitError;
+use crate::helpers::suppression::email_hash;
+use crate::rate_limiting::store::RateLimitStore;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limit {
+    pub requests: u32,
+    pub window: Duration,
+}
+
+/// The limits of a single route. Each one is checked separately.
+#[derive(Debug, Clone, Default)]
+pub struct RouteLimits {
+    pub per_ip: Option<Limit>,
+    /// Per email address or username the request is about.
+    pub per_target: Option<Limit>,
+    /// Across every client.
+    pub per_route: Option<Limit>,
+}
+
+/// Sliding window rate limits of the public endpoints.
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    routes: HashMap<String, RouteLimits>,
+}
+
+impl RateLimiter {
+    pub fn new(store: Arc<dyn RateLimitStore>, routes: HashMap<String, RouteLimits>) -> Self {
+        Self { store, routes }
+    }
+
+    /// The limits known before looking at the request body, applied by the `RateLimit` middleware.
+    pub async fn check_request(&self, path: &str, ip: &str) -> Result<(), RateLimitError> {
+        let limits = match self.routes.get(path) {
+            Some(limits) => limits,
+            None => return Ok(()),
+        };
+
+        if let Some(limit) = limits.per_ip {
+            self.hit(&format!("rate_limit:ip:{}:{}", path, ip), limit).await?;
+        }
+        if let Some(limit) = limits.per_route {
+            self.hit(&format!("rate_limit:route:{}", path), limit).await?;
+        }
+        Ok(())
+    }
+
+    /// The limit on the email address or username a request is about, applied by handlers once they parsed it.
+    pub async fn check_target(&self, path: &str, target: &str) -> Result<(), RateLimitError> {
+        match self.routes.get(path).and_then(|limits| limits.per_target) {
+            // Hashed, so that the store does not hold addresses
+            Some(limit) => self.hit(&format!("rate_limit:target:{}:{}", path, email_hash(target)), limit).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn hit(&self, key: &str, limit: Limit) -> Result<(), RateLimitError> {
+        match self.store.hit(key, limit.requests, limit.window).await {
+            Ok(None) => Ok(()),
+            Ok(Some(retry_after)) => Err(RateLimitError { retry_after }),
+            // An unavailable store is no reason to turn everyone away
+            Err(e) => {
+                tracing::warn!(error.message = %e, key = %key, "Rate limit check failed");
+                Ok(())
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::rate_limiting::limiter::{Limit, RateLimiter, RouteLimits};
+    use crate::rate_limiting::store::{InMemoryRateLimitStore, RateLimitStore};
+    use claim::{assert_err, assert_ok};
+
+    fn limit(requests: u32) -> Option<Limit> {
+        Some(Limit { requests, window: Duration::from_secs(60) })
+    }
+
+    fn limiter(limits: RouteLimits) -> RateLimiter {
+        let mut routes = HashMap::new();
+        routes.insert("/subscriptions".to_string(), limits);
+        RateLimiter::new(Arc::new(InMemoryRateLimitStore::default()), routes)
+    }
+
+    struct BrokenStore;
+
+    #[async_trait::async_trait]
+    impl RateLimitStore for BrokenStore {
+        async fn hit(&self, _key: &str, _limit: u32, _window: Duration) -> Result<Option<Duration>, anyhow::Error> {
+            Err(anyhow::anyhow!("Redis is down"))
+        }
+    }
+
+    #[tokio::test]
+    async fn each_ip_has_its_own_budget() {
+        let limiter = limiter(RouteLimits { per_ip: limit(1), ..RouteLimits::default() });
+
+        assert_ok!(limiter.check_request("/subscriptions", "10.0.0.1").await);
+        assert_err!(limiter.check_request("/subscriptions", "10.0.0.1").await);
+        assert_ok!(limiter.check_request("/subscriptions", "10.0.0.2").await);
+    }
+
+    #[tokio::test]
+    async fn the_route_limit_is_shared_by_every_ip() {
+        let limiter = limiter(RouteLimits { per_route: limit(1), ..RouteLimits::default() });
+
+        assert_ok!(limiter.check_request("/subscriptions", "10.0.0.1").await);
+        assert_err!(limiter.check_request("/subscriptions", "10.0.0.2").await);
+    }
+
+    #[tokio::test]
+    async fn targets_are_matched_regardless_of_case() {
+        let limiter = limiter(RouteLimits { per_target: limit(1), ..RouteLimits::default() });
+
+        assert_ok!(limiter.check_target("/subscriptions", "ursula@domain.com").await);
+        assert_err!(limiter.check_target("/subscriptions", " Ursula@Domain.com").await);
+        assert_ok!(limiter.check_target("/subscriptions", "le.guin@domain.com").await);
+    }
+
+    #[tokio::test]
+    async fn routes_without_limits_are_not_limited() {
+        let limiter = limiter(RouteLimits { per_ip: limit(0), ..RouteLimits::default() });
+
+        assert_ok!(limiter.check_request("/login", "10.0.0.1").await);
+        assert_ok!(limiter.check_target("/subscriptions", "ursula@domain.com").await);
+    }
+
+    #[tokio::test]
+    async fn a_failing_store_lets_requests_through() {
+        let mut routes = HashMap::new();
+        routes.insert("/subscriptions".to_string(), RouteLimits { per_ip: limit(0), ..RouteLimits::default() });
+        let limiter = RateLimiter::new(Arc::new(BrokenStore), routes);
+
+        assert_ok!(limiter.check_request("/subscriptions", "10.0.0.1").await);
+    }
+}
@@ -0,0 +1,79 @@
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::{web, Error};
+use futures::future::LocalBoxFuture;
+
+use crate::helpers::client_ip::TrustedProxies;
+use crate::rate_limiting::limiter::RateLimiter;
+
+/// Apply the per IP and per route limits of `RateLimiter` to form submissions.
+///
+/// Limited requests get a 429 with a `Retry-After` header and never reach their handler.
+pub struct RateLimit {
+    limiter: web::Data<RateLimiter>,
+    trusted_proxies: web::Data<TrustedProxies>,
+}
+
+impl RateLimit {
+    pub fn new(limiter: web::Data<RateLimiter>, trusted_proxies: web::Data<TrustedProxies>) -> Self {
+        Self { limiter, trusted_proxies }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limiter: self.limiter.clone(),
+            trusted_proxies: self.trusted_proxies.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: web::Data<RateLimiter>,
+    trusted_proxies: web::Data<TrustedProxies>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let limiter = self.limiter.clone();
+        let trusted_proxies = self.trusted_proxies.clone();
+
+        Box::pin(async move {
+            // Pages are free to load, only submitting the forms costs us emails and password hashes
+            if req.method() == Method::POST {
+                // Forwarding headers only count from trusted proxies, any client could forge them to dodge the limit
+                let ip = trusted_proxies.client_ip(req.peer_addr(), req.headers())
+                    .map_or_else(|| "unknown".to_owned(), |ip| ip.to_string());
+                limiter.check_request(req.path(), &ip).await?;
+            }
+            service.call(req).await
+        })
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use redis::aio::MultiplexedConnection;
+use redis::Script;
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+/// Drop the hits that left the window, then record this one if there is room for it.
+///
+/// Returns 0 when the hit was recorded, otherwise the milliseconds until the oldest hit leaves the window.
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local key = KEYS[1]
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local limit = tonumber(ARGV[3])
+
+redis.call('ZREMRANGEBYSCORE', key, '-inf', now - window)
+if redis.call('ZCARD', key) < limit then
+    redis.call('ZADD', key, now, ARGV[4])
+    redis.call('PEXPIRE', key, window)
+    return 0
+end
+
+local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+if oldest[2] == nil then
+    return window
+end
+return math.max(1, tonumber(oldest[2]) + window - now)
+"#;
+
+/// Keys kept in memory before the idle ones are swept.
+const MIN_SWEEP_SIZE: usize = 1024;
+
+/// Keeps the hits of sliding window rate limits.
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Record a hit on `key`, unless it already had `limit` hits within the last `window`.
+    ///
+    /// Returns how long to wait before the key can be hit again, `None` if this hit was let through.
+    async fn hit(&self, key: &str, limit: u32, window: Duration) -> Result<Option<Duration>, anyhow::Error>;
+}
+
+
+/// Shares the counters between every instance of the application.
+pub struct RedisRateLimitStore {
+    connection: MultiplexedConnection,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    pub async fn connect(redis_uri: &Secret<String>) -> Result<Self, anyhow::Error> {
+        let client = redis::Client::open(redis_uri.expose_secret().as_str())?;
+        let connection = client.get_multiplexed_tokio_connection().await?;
+        Ok(Self { connection, script: Script::new(SLIDING_WINDOW_SCRIPT) })
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn hit(&self, key: &str, limit: u32, window: Duration) -> Result<Option<Duration>, anyhow::Error> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        let retry_after_ms: u64 = self.script
+            .key(key)
+            .arg(now)
+            .arg(window.as_millis() as u64)
+            .arg(limit)
+            .arg(Uuid::new_v4().to_string())
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+
+        Ok(match retry_after_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        })
+    }
+}
+
+
+/// Counters local to the process, for tests and single instance deployments.
+pub struct InMemoryRateLimitStore {
+    logs: Mutex<HitLogs>,
+}
+
+struct HitLogs {
+    by_key: HashMap<String, HitLog>,
+    /// Idle keys are swept once this many are kept, so that the sweeps stay rare.
+    sweep_size: usize,
+}
+
+struct HitLog {
+    window: Duration,
+    hits: VecDeque<Instant>,
+}
+
+impl HitLog {
+    fn prune(&mut self, now: Instant) {
+        while matches!(self.hits.front(), Some(oldest) if now.duration_since(*oldest) >= self.window) {
+            self.hits.pop_front();
+        }
+    }
+}
+
+impl Default for InMemoryRateLimitStore {
+    fn default() -> Self {
+        let logs = HitLogs { by_key: HashMap::new(), sweep_size: MIN_SWEEP_SIZE };
+        Self { logs: Mutex::new(logs) }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn hit(&self, key: &str, limit: u32, window: Duration) -> Result<Option<Duration>, anyhow::Error> {
+        let now = Instant::now();
+        let mut logs = self.logs.lock().unwrap();
+        // Keys whose hits all left their window are forgotten, or every client ever seen would stay in memory
+        if logs.by_key.len() >= logs.sweep_size {
+            logs.by_key.retain(|_, log| {
+                log.prune(now);
+                !log.hits.is_empty()
+            });
+            logs.sweep_size = MIN_SWEEP_SIZE.max(logs.by_key.len() * 2);
+        }
+        let log = logs.by_key.entry(key.to_owned()).or_insert_with(|| HitLog { window, hits: VecDeque::new() });
+        log.prune(now);
+
+        if log.hits.len() < limit as usize {
+            log.hits.push_back(now);
+            return Ok(None);
+        }
+
+        let retry_after = log.hits.front()
+            .map(|oldest| window - now.duration_since(*oldest))
+            .unwrap_or(window);
+        Ok(Some(retry_after))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::rate_limiting::store::{InMemoryRateLimitStore, RateLimitStore, MIN_SWEEP_SIZE};
+
+    #[tokio::test]
+    async fn hits_beyond_the_limit_are_refused_until_the_window_slides() {
+        let store = InMemoryRateLimitStore::default();
+        let window = Duration::from_millis(200);
+
+        assert_eq!(store.hit("key", 2, window).await.unwrap(), None);
+        assert_eq!(store.hit("key", 2, window).await.unwrap(), None);
+        let retry_after = store.hit("key", 2, window).await.unwrap().unwrap();
+        assert!(retry_after <= window);
+
+        tokio::time::sleep(window).await;
+        assert_eq!(store.hit("key", 2, window).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn keys_are_counted_separately() {
+        let store = InMemoryRateLimitStore::default();
+        let window = Duration::from_secs(60);
+
+        assert_eq!(store.hit("first", 1, window).await.unwrap(), None);
+        assert_eq!(store.hit("second", 1, window).await.unwrap(), None);
+        assert!(store.hit("first", 1, window).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn idle_keys_are_swept_once_enough_are_kept() {
+        let store = InMemoryRateLimitStore::default();
+        let window = Duration::from_millis(100);
+
+        for i in 0..MIN_SWEEP_SIZE {
+            store.hit(&format!("idle-{}", i), 1, window).await.unwrap();
+        }
+        tokio::time::sleep(window).await;
+        store.hit("active", 1, window).await.unwrap();
+
+        let logs = store.logs.lock().unwrap();
+        assert_eq!(logs.by_key.len(), 1);
+        assert!(logs.by_key.contains_key("active"));
+    }
+
+    #[tokio::test]
+    async fn keys_in_use_are_not_swept() {
+        let store = InMemoryRateLimitStore::default();
+        let window = Duration::from_secs(60);
+
+        for i in 0..MIN_SWEEP_SIZE {
+            store.hit(&format!("busy-{}", i), 1, window).await.unwrap();
+        }
+        store.hit("another", 1, window).await.unwrap();
+
+        assert!(store.hit("busy-0", 1, window).await.unwrap().is_some());
+        assert_eq!(store.logs.lock().unwrap().sweep_size, 2 * MIN_SWEEP_SIZE);
+    }
+
+    #[tokio::test]
+    async fn refused_hits_do_not_extend_the_wait() {
+        let store = InMemoryRateLimitStore::default();
+        let window = Duration::from_millis(200);
+
+        store.hit("key", 1, window).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        store.hit("key", 1, window).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(110)).await;
+
+        assert_eq!(store.hit("key", 1, window).await.unwrap(), None);
+    }
+}
@@ -6,10 +6,13 @@ mod newsletter;
 mod auth;
 mod pages;
 mod dashboard;
+mod tracking;
+mod preferences;
 
 pub use health_check::route::health_check;
 pub use subscriptions::route::subscribe;
-pub use subscriptions_confirm::route::confirm;
+pub use subscriptions::resend::resend_confirmation;
+pub use subscriptions_confirm::route::{confirm, confirm_form};
 pub use newsletter::route::publish_newsletter;
 pub use pages::home::home;
 pub use auth::login::login_form;
@@ -17,4 +20,23 @@ pub use auth::login::login;
 pub use dashboard::admin_dashboard::admin_dashboard;
 pub use dashboard::password::change_password;
 pub use dashboard::password::change_password_form;
-pub use dashboard::logout::log_out;
\ No newline at end of file
+pub use dashboard::logout::log_out;
+pub use dashboard::newsletter::publish_newsletter_form;
+pub use dashboard::newsletter_issue::newsletter_issue;
+pub use tracking::route::{track_open, track_click};
+pub use preferences::route::{preferences_form, update_preferences};
+pub use dashboard::issue_delivery::{change_issue_delivery, send_correction};
+pub use dashboard::subscriber::{subscriber_details, add_subscriber_tag, export_subscriber_data, erase_subscriber_data};
+pub use preferences::data::{export_my_data, erase_my_data_form, erase_my_data};
+pub use dashboard::subscriber_import::{import_subscribers_form, import_subscribers, MAX_IMPORT_BYTES};
+pub use dashboard::subscriber_export::{export_subscribers_form, download_subscribers};
+pub use dashboard::subscriber_attributes::{subscriber_attributes, add_subscriber_attribute, delete_subscriber_attribute};
+pub use dashboard::subscriber_list::browse_subscribers;
+pub use dashboard::subscriber_management::{
+    new_subscriber_form, add_subscriber, edit_subscriber_form, edit_subscriber, bulk_action,
+};
+pub use dashboard::welcome_email::{welcome_email_form, update_welcome_email};
+pub use dashboard::sequences::{
+    sequences, add_sequence, sequence_details, add_sequence_step, stop_subscriber_enrollment,
+};
+pub use dashboard::referrals::{referrals, add_referral_reward, remove_referral_reward};
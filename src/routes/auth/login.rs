@@ -1,8 +1,8 @@
 use actix_web::cookie::Cookie;
 use actix_web::error::InternalError;
-use actix_web::{HttpResponse, web};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
 use actix_web::http::header::{ContentType, LOCATION};
-use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages, Level};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
 use actix_session::Session;
 use secrecy::{Secret};
 use std::fmt::Write;
@@ -10,6 +10,7 @@ use sqlx::PgPool;
 
 use crate::errors::auth_error::{AuthError, LoginError};
 use crate::helpers::auth::{Credentials, validate_credentials};
+use crate::rate_limiting::limiter::RateLimiter;
 
 
 #[derive(serde::Deserialize)]
@@ -67,14 +68,20 @@ pub async fn login_form(
 
 
 #[tracing::instrument(
-    skip(form, pool, session),
+    skip(form, request, pool, session, rate_limiter),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
     form: web::Form<FormData>, 
+    request: HttpRequest,
     pool: web::Data<PgPool>,
-    session: Session
+    session: Session,
+    rate_limiter: web::Data<RateLimiter>,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
+    rate_limiter.check_target(request.path(), &form.username).await.map_err(|e| {
+        let response = e.error_response();
+        InternalError::from_response(LoginError::RateLimited(e), response)
+    })?;
 
     let credentials = Credentials {
         username: form.0.username,
@@ -90,7 +97,6 @@ pub async fn login(
             session.insert("user_id", user_id).map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
         }
         Err(e) => {
-            let query_string = format!("error={}", urlencoding::Encoded::new(e.to_string()));
             let e =  match e {
                 AuthError::InvalidCredentials(_) => LoginError::AuthError(e.into()),
                 AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
@@ -98,11 +104,6 @@ pub async fn login(
 
             FlashMessage::error(e.to_string()).send();
 
-            let response = HttpResponse::SeeOther()
-                // .cookie(Cookie::new("_flash", e.to_string()))
-                .insert_header((LOCATION, "/login"))
-                .finish();
-
             return Err(login_redirect(e))
         }
     }
@@ -113,15 +114,6 @@ pub async fn login(
 }
 
 
-fn login_credentials(e: LoginError) -> InternalError<LoginError> {
-    FlashMessage::error(e.to_string()).send();
-    let response = HttpResponse::SeeOther()
-        .insert_header((LOCATION, "/login"))
-        .finish();
-
-    InternalError::from_response(e, response)
-}
-
 fn login_redirect(e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
     let response = HttpResponse::SeeOther().insert_header((LOCATION, "/login")).finish();
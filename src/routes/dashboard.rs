@@ -1,3 +1,15 @@
 pub mod admin_dashboard;
 pub mod password;
-pub mod logout;
\ No newline at end of file
+pub mod logout;
+pub mod newsletter;
+pub mod newsletter_issue;
+pub mod issue_delivery;
+pub mod subscriber;
+pub mod subscriber_import;
+pub mod subscriber_export;
+pub mod subscriber_attributes;
+pub mod subscriber_list;
+pub mod subscriber_management;
+pub mod welcome_email;
+pub mod sequences;
+pub mod referrals;
\ No newline at end of file
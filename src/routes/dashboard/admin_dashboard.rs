@@ -1,10 +1,12 @@
 use anyhow::Context;
 use actix_web::http::header::{ContentType, LOCATION};
 use actix_web::{web, HttpResponse};
-use actix_session::Session;
+use actix_web_flash_messages::IncomingFlashMessages;
 use sqlx::PgPool;
+use std::fmt::Write;
 use uuid::Uuid;
 
+use crate::email::email_client::EmailClient;
 use crate::session_state::TypedSession;
 
 fn e500<T>(e: T) -> actix_web::error::InternalError<T> {
@@ -14,7 +16,9 @@ fn e500<T>(e: T) -> actix_web::error::InternalError<T> {
 
 pub async fn admin_dashboard(
     session: TypedSession,
-    pool: web::Data<PgPool>
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    flash_messages: IncomingFlashMessages,
 ) -> Result<HttpResponse, actix_web::Error> {
     let username = if let Some(user_id) = session.get_user_id().map_err(e500)? {
         get_username(user_id, &pool).await.map_err(e500)?
@@ -24,6 +28,11 @@ pub async fn admin_dashboard(
             .finish());
     };
 
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(format!(
@@ -34,9 +43,19 @@ pub async fn admin_dashboard(
             <title>Admin dashboard</title>
             </head>
             <body>
+                {}
                 <p>Welcome {}!</p>
+                <p>Email provider circuit breaker: {}</p>
                 <p>Available actions:</p>
                 <ol>
+                    <li><a href="/admin/newsletters">Send a newsletter issue</a></li>
+                    <li><a href="/admin/subscribers">Browse subscribers</a></li>
+                    <li><a href="/admin/subscribers/import">Import subscribers</a></li>
+                    <li><a href="/admin/subscribers/export">Export subscribers</a></li>
+                    <li><a href="/admin/attributes">Subscriber attributes</a></li>
+                    <li><a href="/admin/welcome-email">Welcome email</a></li>
+                    <li><a href="/admin/sequences">Sequences</a></li>
+                    <li><a href="/admin/referrals">Referrals</a></li>
                     <li><a href="/admin/password">Change password</a></li>
                     <li>
                         <a href="javascript: document.logoutForm.submit()">Logout</a>
@@ -48,7 +67,9 @@ pub async fn admin_dashboard(
                 </ol>
             </body>
             </html>"#,
-            username
+            msg_html,
+            username,
+            email_client.circuit_state().as_str(),
         )))
 }
 
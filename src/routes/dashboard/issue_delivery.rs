@@ -0,0 +1,185 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::delivery_status::{DeliveryAction, DeliveryStatus};
+use crate::domain::new_issue::NewIssue;
+use crate::helpers::newsletter_issue::{enqueue_correction, insert_newsletter_issue};
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+
+#[derive(serde::Deserialize)]
+pub struct DeliveryActionForm {
+    action: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct CorrectionForm {
+    title: String,
+    text_content: String,
+    html_content: String,
+}
+
+
+/// Pause, resume or cancel the delivery of an issue.
+///
+/// Workers check the status of an issue before every email, so the change is picked up by the next task.
+#[tracing::instrument(name = "Change the delivery of a newsletter issue", skip(session, pool, form))]
+pub async fn change_issue_delivery(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    issue_id: web::Path<Uuid>,
+    form: web::Form<DeliveryActionForm>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let issue_id = issue_id.into_inner();
+    let issue_page = format!("/admin/newsletters/{}", issue_id);
+
+    let mut transaction = pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+
+    let current = match lock_delivery_status(&mut transaction, issue_id).await.map_err(e500)? {
+        Some(status) => status,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let next = match DeliveryAction::parse(&form.action).and_then(|action| current.apply(action)) {
+        Ok(next) => next,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(&issue_page));
+        }
+    };
+
+    set_delivery_status(&mut transaction, issue_id, next).await.map_err(e500)?;
+    let message = match next {
+        DeliveryStatus::Paused => "Delivery paused.".to_string(),
+        DeliveryStatus::Sending => "Delivery resumed.".to_string(),
+        DeliveryStatus::Cancelled => {
+            let dropped = drop_pending_deliveries(&mut transaction, issue_id).await.map_err(e500)?;
+            format!("Delivery cancelled, {} pending emails will not be sent.", dropped)
+        }
+    };
+    transaction.commit()
+        .await
+        .context("Failed to commit the new delivery status of a newsletter issue")
+        .map_err(e500)?;
+
+    FlashMessage::info(message).send();
+    Ok(see_other(&issue_page))
+}
+
+
+/// Publish a correction that only goes out to the subscribers who already received the issue.
+#[tracing::instrument(name = "Send a correction for a newsletter issue", skip(session, pool, form))]
+pub async fn send_correction(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    issue_id: web::Path<Uuid>,
+    form: web::Form<CorrectionForm>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let issue_id = issue_id.into_inner();
+    let issue_page = format!("/admin/newsletters/{}", issue_id);
+
+    let form = form.into_inner();
+    let correction = match NewIssue::parse(form.title, form.text_content, form.html_content, None, None) {
+        Ok(correction) => correction,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(&issue_page));
+        }
+    };
+
+    let mut transaction = pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+
+    if lock_delivery_status(&mut transaction, issue_id).await.map_err(e500)?.is_none() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let correction_id = insert_newsletter_issue(&mut transaction, &correction)
+        .await
+        .context("Failed to store the correction")
+        .map_err(e500)?;
+    let recipients = enqueue_correction(&mut transaction, correction_id, issue_id)
+        .await
+        .context("Failed to enqueue the correction")
+        .map_err(e500)?;
+
+    if recipients == 0 {
+        // Dropping the transaction discards the correction
+        FlashMessage::error("Nobody has received this issue yet, there is nothing to correct.").send();
+        return Ok(see_other(&issue_page));
+    }
+
+    transaction.commit()
+        .await
+        .context("Failed to commit SQL transaction to store a correction")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!("The correction has been accepted and will go out to {} recipients.", recipients)).send();
+    Ok(see_other(&format!("/admin/newsletters/{}", correction_id)))
+}
+
+
+async fn lock_delivery_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+) -> Result<Option<DeliveryStatus>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"SELECT delivery_status FROM newsletter_issues WHERE newsletter_issue_id = $1 FOR UPDATE"#,
+        issue_id,
+    )
+        .fetch_optional(transaction)
+        .await
+        .context("Failed to retrieve the delivery status of a newsletter issue")?;
+
+    row.map(|r| DeliveryStatus::parse(&r.delivery_status).map_err(anyhow::Error::msg))
+        .transpose()
+}
+
+
+async fn set_delivery_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    status: DeliveryStatus,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"UPDATE newsletter_issues SET delivery_status = $2 WHERE newsletter_issue_id = $1"#,
+        issue_id,
+        status.as_str(),
+    )
+        .execute(transaction)
+        .await
+        .context("Failed to update the delivery status of a newsletter issue")?;
+
+    Ok(())
+}
+
+
+async fn drop_pending_deliveries(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+) -> Result<u64, anyhow::Error> {
+    let dropped = sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE newsletter_issue_id = $1"#,
+        issue_id,
+    )
+        .execute(transaction)
+        .await
+        .context("Failed to drop the pending deliveries of a newsletter issue")?
+        .rows_affected();
+
+    Ok(dropped)
+}
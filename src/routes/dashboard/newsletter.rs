@@ -0,0 +1,77 @@
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+use actix_web_flash_messages::IncomingFlashMessages;
+use std::fmt::Write;
+
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+
+pub async fn publish_newsletter_form(
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Publish Newsletter Issue</title>
+        </head>
+        <body>
+            {}
+            <form action="/newsletters" method="post">
+            <label>Title
+            <input type="text" placeholder="Enter the issue title" name="title" />
+            </label>
+            <br />
+            <label>Plain text content
+            <textarea placeholder="Enter the content in plain text" name="text_content" rows="20" cols="50"></textarea>
+            </label>
+            <br />
+            <label>HTML content
+            <textarea placeholder="Enter the content in HTML format" name="html_content" rows="20" cols="50"></textarea>
+            </label>
+            <br />
+            <label>Deliver at this local time in each subscriber's time zone (optional)
+            <input type="time" name="local_send_time" />
+            </label>
+            <br />
+            <fieldset>
+                <legend>Subject line A/B test (optional)</legend>
+                <label>Alternative subject lines, one per line
+                <textarea name="subject_variants" rows="3" cols="50"></textarea>
+                </label>
+                <br />
+                <label>Sample size per subject line (%)
+                <input type="number" name="sample_percentage" min="1" max="49" />
+                </label>
+                <br />
+                <label>Pick the winner by
+                <select name="test_metric">
+                    <option value="open">Open rate</option>
+                    <option value="click">Click rate</option>
+                </select>
+                </label>
+                <br />
+                <label>Test window (minutes)
+                <input type="number" name="test_window_minutes" min="1" />
+                </label>
+            </fieldset>
+            <br />
+            <button type="submit">Publish</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>"#, msg_html
+    )))
+}
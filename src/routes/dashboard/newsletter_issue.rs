@@ -0,0 +1,222 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::domain::delivery_status::DeliveryStatus;
+use crate::domain::subject_test::SubjectTestMetric;
+use crate::helpers::newsletter_issue::get_variant_results;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+
+struct IssueOverview {
+    title: String,
+    published_at: chrono::DateTime<chrono::Utc>,
+    subject_test_metric: Option<String>,
+    subject_test_decide_at: Option<chrono::DateTime<chrono::Utc>>,
+    winning_variant: Option<i16>,
+    local_send_time: Option<chrono::NaiveTime>,
+    queued: i64,
+    scheduled: i64,
+    next_delivery_at: Option<chrono::DateTime<chrono::Utc>>,
+    delivery_status: String,
+    corrects_issue_id: Option<Uuid>,
+    sent: i64,
+}
+
+
+pub async fn newsletter_issue(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    issue_id: web::Path<Uuid>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let issue_id = issue_id.into_inner();
+
+    let issue = match get_issue_overview(&pool, issue_id).await.map_err(e500)? {
+        Some(issue) => issue,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let results = get_variant_results(&pool, issue_id).await.map_err(e500)?;
+    let subjects = get_subjects(&pool, issue_id).await.map_err(e500)?;
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let test_html = match (&issue.subject_test_metric, issue.subject_test_decide_at) {
+        (Some(metric), Some(decide_at)) => {
+            let metric = SubjectTestMetric::parse(metric).map_err(e500)?;
+            let status = match issue.winning_variant {
+                Some(variant) => format!("Winner: variant {}, sent to the remainder of the list.", variant),
+                None => format!("The winner will be picked by {} rate at {}.", metric.as_str(), decide_at.to_rfc2822()),
+            };
+            format!("<p>Subject line A/B test. {}</p>", status)
+        }
+        _ => String::new(),
+    };
+
+    let schedule_html = match (issue.local_send_time, issue.next_delivery_at) {
+        (Some(local_send_time), Some(next_delivery_at)) if issue.scheduled > 0 => format!(
+            "<p>Delivered at {} in each subscriber's time zone. {} emails are scheduled, the next cohort goes out at {}.</p>",
+            local_send_time.format("%H:%M"), issue.scheduled, next_delivery_at.to_rfc2822(),
+        ),
+        (Some(local_send_time), _) => format!(
+            "<p>Delivered at {} in each subscriber's time zone.</p>",
+            local_send_time.format("%H:%M"),
+        ),
+        _ => String::new(),
+    };
+
+    let status = DeliveryStatus::parse(&issue.delivery_status).map_err(e500)?;
+    let mut controls_html = String::new();
+    let actions: &[(&str, &str)] = match status {
+        DeliveryStatus::Sending => &[("pause", "Pause delivery"), ("cancel", "Cancel delivery")],
+        DeliveryStatus::Paused => &[("resume", "Resume delivery"), ("cancel", "Cancel delivery")],
+        DeliveryStatus::Cancelled => &[],
+    };
+    for (action, label) in actions {
+        writeln!(
+            controls_html,
+            r#"<form action="/admin/newsletters/{}/delivery" method="post" style="display:inline">
+                <input type="hidden" name="action" value="{}">
+                <button type="submit">{}</button>
+            </form>"#,
+            issue_id, action, label,
+        ).unwrap();
+    }
+    if issue.sent > 0 {
+        writeln!(
+            controls_html,
+            r#"<form action="/admin/newsletters/{}/correction" method="post">
+                <p>Send a correction to the {} subscribers who already received this issue.</p>
+                <label>Title <input type="text" name="title"></label><br />
+                <label>Plain text content <textarea name="text_content"></textarea></label><br />
+                <label>HTML content <textarea name="html_content"></textarea></label><br />
+                <button type="submit">Send correction</button>
+            </form>"#,
+            issue_id, issue.sent,
+        ).unwrap();
+    }
+    let correction_html = match issue.corrects_issue_id {
+        Some(corrected) => format!(r#"<p>Correction of <a href="/admin/newsletters/{0}">{0}</a>.</p>"#, corrected),
+        None => String::new(),
+    };
+
+    let mut rows_html = String::new();
+    for result in &results {
+        let subject = subjects.iter()
+            .find(|(variant, _)| *variant == result.variant)
+            .map(|(_, subject)| htmlescape::encode_minimal(subject))
+            .unwrap_or_default();
+        let winner = if issue.winning_variant == Some(result.variant) { " (winner)" } else { "" };
+
+        writeln!(
+            rows_html,
+            "<tr><td>{}{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}%</td><td>{:.1}%</td></tr>",
+            subject, winner, result.delivered, result.opened, result.clicked,
+            result.open_rate() * 100.0, result.click_rate() * 100.0,
+        ).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Newsletter Issue</title>
+        </head>
+        <body>
+            {}
+            <h1>{}</h1>
+            {}
+            <p>Published at {}. Delivery is {}: {} emails sent, {} waiting in the delivery queue.</p>
+            {}
+            {}
+            {}
+            <table>
+                <thead>
+                    <tr><th>Subject</th><th>Delivered</th><th>Opened</th><th>Clicked</th><th>Open rate</th><th>Click rate</th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        htmlescape::encode_minimal(&issue.title),
+        correction_html,
+        issue.published_at.to_rfc2822(),
+        status.as_str(),
+        issue.sent,
+        issue.queued,
+        schedule_html,
+        test_html,
+        controls_html,
+        rows_html,
+    )))
+}
+
+
+#[tracing::instrument(name = "Get newsletter issue overview", skip(pool))]
+async fn get_issue_overview(pool: &PgPool, issue_id: Uuid) -> Result<Option<IssueOverview>, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        IssueOverview,
+        r#"
+        SELECT
+            title,
+            published_at,
+            subject_test_metric,
+            subject_test_decide_at,
+            winning_variant,
+            local_send_time,
+            (SELECT COUNT(*) FROM issue_delivery_queue q WHERE q.newsletter_issue_id = i.newsletter_issue_id) AS "queued!",
+            (
+                SELECT COUNT(*) FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id AND q.execute_after > now()
+            ) AS "scheduled!",
+            (
+                SELECT MIN(execute_after) FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id AND q.execute_after > now()
+            ) AS next_delivery_at,
+            delivery_status,
+            corrects_issue_id,
+            (SELECT COUNT(*) FROM issue_deliveries d WHERE d.newsletter_issue_id = i.newsletter_issue_id) AS "sent!"
+        FROM newsletter_issues i
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id,
+    )
+        .fetch_optional(pool)
+        .await
+        .context("Failed to retrieve a newsletter issue.")?;
+
+    Ok(issue)
+}
+
+
+#[tracing::instrument(name = "Get newsletter issue subject lines", skip(pool))]
+async fn get_subjects(pool: &PgPool, issue_id: Uuid) -> Result<Vec<(i16, String)>, anyhow::Error> {
+    let subjects = sqlx::query!(
+        r#"SELECT variant, subject FROM newsletter_issue_variants WHERE newsletter_issue_id = $1"#,
+        issue_id,
+    )
+        .fetch_all(pool)
+        .await
+        .context("Failed to retrieve the subject lines of a newsletter issue.")?
+        .into_iter()
+        .map(|r| (r.variant, r.subject))
+        .collect();
+
+    Ok(subjects)
+}
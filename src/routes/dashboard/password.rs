@@ -1,4 +1,3 @@
-use actix_session::Session;
 use actix_web::http::header::ContentType;
 use actix_web::HttpResponse;
 use actix_web::web;
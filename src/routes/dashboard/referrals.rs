@@ -0,0 +1,165 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::domain::referral::ReferralReward;
+use crate::helpers::referrals::{
+    delete_referral_reward, get_referral_rewards, get_top_referrers, insert_referral_reward,
+};
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+const TOP_REFERRERS: i64 = 50;
+
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    milestone: String,
+    subject: String,
+    text_content: String,
+    html_content: String,
+}
+
+
+pub async fn referrals(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let referrers = get_top_referrers(&pool, TOP_REFERRERS).await.map_err(e500)?;
+    let mut referrers_html = String::new();
+    for referrer in &referrers {
+        writeln!(
+            referrers_html,
+            r#"<tr><td><a href="/admin/subscribers/{}">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+            referrer.subscriber_id,
+            htmlescape::encode_minimal(&referrer.email),
+            htmlescape::encode_minimal(referrer.referral_code.as_deref().unwrap_or("-")),
+            referrer.credited,
+            referrer.pending,
+        ).unwrap();
+    }
+
+    let rewards = get_referral_rewards(&pool).await.map_err(e500)?;
+    let mut rewards_html = String::new();
+    for reward in &rewards {
+        writeln!(
+            rewards_html,
+            r#"<tr><td>{}</td><td>{}</td>
+            <td><form action="/admin/referrals/rewards/{}/delete" method="post"><button type="submit">Delete</button></form></td></tr>"#,
+            reward.milestone,
+            htmlescape::encode_minimal(&reward.subject),
+            reward.milestone,
+        ).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Referrals</title>
+        </head>
+        <body>
+            {}
+            <p>Confirmed subscribers share a link to the signup page with their referral code.
+            A referral is credited once the new subscriber confirms.</p>
+            <h2>Top referrers</h2>
+            <table>
+                <thead>
+                    <tr><th>Subscriber</th><th>Code</th><th>Credited</th><th>Pending</th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>Rewards</h2>
+            <p>Sent once to a referrer when their credited referrals reach the milestone.</p>
+            <table>
+                <thead>
+                    <tr><th>Milestone</th><th>Subject</th><th></th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>New reward</h2>
+            <p>The subject and bodies can use merge fields such as <code>{{{{ name | there }}}}</code>,
+            as well as <code>{{{{ referral_count }}}}</code> and <code>{{{{ share_url }}}}</code>.</p>
+            <form action="/admin/referrals/rewards" method="post">
+            <label>Referrals <input type="number" name="milestone" min="1" /></label>
+            <br />
+            <label>Subject <input type="text" name="subject" /></label>
+            <br />
+            <label>Plain text
+            <textarea name="text_content" rows="10" cols="60"></textarea>
+            </label>
+            <br />
+            <label>HTML
+            <textarea name="html_content" rows="10" cols="60"></textarea>
+            </label>
+            <br />
+            <button type="submit">Add reward</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        referrers_html,
+        rewards_html,
+    )))
+}
+
+
+#[tracing::instrument(name = "Add a referral reward", skip(session, pool, form))]
+pub async fn add_referral_reward(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let reward = match ReferralReward::parse(&form.milestone, &form.subject, &form.text_content, &form.html_content) {
+        Ok(reward) => reward,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/referrals"));
+        }
+    };
+
+    if insert_referral_reward(&pool, &reward).await.map_err(e500)? {
+        FlashMessage::info(format!("The reward for {} referrals has been added.", reward.milestone)).send();
+    } else {
+        FlashMessage::error(format!("There is already a reward for {} referrals.", reward.milestone)).send();
+    }
+    Ok(see_other("/admin/referrals"))
+}
+
+
+#[tracing::instrument(name = "Delete a referral reward", skip(session, pool))]
+pub async fn remove_referral_reward(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    milestone: web::Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    delete_referral_reward(&pool, milestone.into_inner()).await.map_err(e500)?;
+    FlashMessage::info("The reward has been deleted.").send();
+    Ok(see_other("/admin/referrals"))
+}
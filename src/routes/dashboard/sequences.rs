@@ -0,0 +1,312 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::domain::automation::{SequenceStep, SequenceTrigger, StepCondition};
+use crate::helpers::automation::{
+    get_sequence, get_sequences, get_step_stats, get_steps, insert_sequence, insert_step, stop_enrollment,
+};
+use crate::helpers::subscriber_attributes::get_attribute_definitions;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+
+#[derive(serde::Deserialize)]
+pub struct SequenceFormData {
+    name: String,
+    trigger: String,
+    #[serde(default)]
+    trigger_tag: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct StepFormData {
+    delay_days: String,
+    subject: String,
+    text_content: String,
+    html_content: String,
+    #[serde(default)]
+    condition_kind: String,
+    #[serde(default)]
+    condition_key: String,
+    #[serde(default)]
+    condition_value: String,
+}
+
+
+pub async fn sequences(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let sequences = get_sequences(&pool).await.map_err(e500)?;
+    let mut rows_html = String::new();
+    for sequence in &sequences {
+        writeln!(
+            rows_html,
+            r#"<tr><td><a href="/admin/sequences/{}">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+            sequence.sequence_id,
+            htmlescape::encode_minimal(&sequence.name),
+            htmlescape::encode_minimal(&sequence.trigger.describe()),
+            sequence.steps,
+            sequence.active_enrollments,
+        ).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Sequences</title>
+        </head>
+        <body>
+            {}
+            <p>A sequence sends its steps one after the other, each a number of days after enrollment.
+            Confirmed subscribers are enrolled when they confirm or when they are given the tag of the sequence.</p>
+            <table>
+                <thead>
+                    <tr><th>Name</th><th>Trigger</th><th>Steps</th><th>Active enrollments</th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>New sequence</h2>
+            <form action="/admin/sequences" method="post">
+            <label>Name <input type="text" name="name" placeholder="Onboarding" /></label>
+            <br />
+            <label>Trigger
+            <select name="trigger">
+                <option value="confirmation">On confirmation</option>
+                <option value="tag">When tagged</option>
+            </select>
+            </label>
+            <br />
+            <label>Tag <input type="text" name="trigger_tag" placeholder="trial" /></label>
+            <br />
+            <button type="submit">Add sequence</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        rows_html,
+    )))
+}
+
+
+#[tracing::instrument(name = "Add a sequence", skip(session, pool, form))]
+pub async fn add_sequence(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    form: web::Form<SequenceFormData>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let name = form.name.trim();
+    if name.is_empty() {
+        FlashMessage::error("The sequence needs a name.").send();
+        return Ok(see_other("/admin/sequences"));
+    }
+    let trigger = match SequenceTrigger::parse(&form.trigger, &form.trigger_tag) {
+        Ok(trigger) => trigger,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/sequences"));
+        }
+    };
+
+    if insert_sequence(&pool, name, &trigger).await.map_err(e500)? {
+        FlashMessage::info(format!("The {} sequence has been added, it enrolls subscribers once it has a step.", name))
+            .send();
+    } else {
+        FlashMessage::error(format!("There is already a sequence named {}.", name)).send();
+    }
+    Ok(see_other("/admin/sequences"))
+}
+
+
+pub async fn sequence_details(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    sequence_id: web::Path<Uuid>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let sequence_id = sequence_id.into_inner();
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let (name, trigger) = match get_sequence(&pool, sequence_id).await.map_err(e500)? {
+        Some(sequence) => sequence,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let steps = get_steps(&pool, sequence_id).await.map_err(e500)?;
+    let stats = get_step_stats(&pool, sequence_id).await.map_err(e500)?;
+
+    let mut steps_html = String::new();
+    for step in &steps {
+        let step_stats = stats.iter().find(|stats| stats.position == step.position);
+        writeln!(
+            steps_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            step.position + 1,
+            step.delay_days,
+            htmlescape::encode_minimal(&step.subject),
+            htmlescape::encode_minimal(
+                &step.condition.as_ref().map(|condition| condition.describe()).unwrap_or_else(|| "-".into()),
+            ),
+            step_stats.map(|stats| stats.sent).unwrap_or(0),
+            step_stats.map(|stats| stats.skipped).unwrap_or(0),
+            step_stats.map(|stats| stats.failed).unwrap_or(0),
+            step_stats.map(|stats| stats.waiting).unwrap_or(0),
+        ).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Sequence</title>
+        </head>
+        <body>
+            {}
+            <h1>{}</h1>
+            <p>{}.</p>
+            <table>
+                <thead>
+                    <tr><th>Step</th><th>Day</th><th>Subject</th><th>Condition</th>
+                    <th>Sent</th><th>Skipped</th><th>Failed</th><th>Waiting</th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>New step</h2>
+            <p>The subject and bodies can use merge fields such as <code>{{{{ name | there }}}}</code>.
+            Subscribers who do not meet the condition skip the step and move on to the next one.</p>
+            <form action="/admin/sequences/{}/steps" method="post">
+            <label>Days after enrollment <input type="number" name="delay_days" min="0" value="{}" /></label>
+            <br />
+            <label>Subject <input type="text" name="subject" /></label>
+            <br />
+            <label>Plain text
+            <textarea name="text_content" rows="10" cols="60"></textarea>
+            </label>
+            <br />
+            <label>HTML
+            <textarea name="html_content" rows="10" cols="60"></textarea>
+            </label>
+            <br />
+            <label>Condition
+            <select name="condition_kind">
+                <option value="">Everyone</option>
+                <option value="attribute">Attribute is</option>
+                <option value="engaged">Opened or clicked an issue</option>
+                <option value="not_engaged">Never opened nor clicked an issue</option>
+            </select>
+            </label>
+            <label>Attribute <input type="text" name="condition_key" placeholder="plan" /></label>
+            <label>Value <input type="text" name="condition_value" /></label>
+            <br />
+            <button type="submit">Add step</button>
+            </form>
+            <p><a href="/admin/sequences">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        htmlescape::encode_minimal(&name),
+        htmlescape::encode_minimal(&trigger.describe()),
+        steps_html,
+        sequence_id,
+        steps.last().map(|step| step.delay_days).unwrap_or(0),
+    )))
+}
+
+
+#[tracing::instrument(name = "Add a sequence step", skip(session, pool, form))]
+pub async fn add_sequence_step(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    sequence_id: web::Path<Uuid>,
+    form: web::Form<StepFormData>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let sequence_id = sequence_id.into_inner();
+    let location = format!("/admin/sequences/{}", sequence_id);
+
+    if get_sequence(&pool, sequence_id).await.map_err(e500)?.is_none() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let steps = get_steps(&pool, sequence_id).await.map_err(e500)?;
+    let definitions = get_attribute_definitions(&pool).await.map_err(e500)?;
+
+    let step = StepCondition::parse(&definitions, &form.condition_kind, &form.condition_key, &form.condition_value)
+        .and_then(|condition| {
+            SequenceStep::parse(
+                steps.last(),
+                &form.delay_days,
+                &form.subject,
+                &form.text_content,
+                &form.html_content,
+                condition,
+            )
+        });
+    let step = match step {
+        Ok(step) => step,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(&location));
+        }
+    };
+
+    if insert_step(&pool, sequence_id, &step).await.map_err(e500)? {
+        FlashMessage::info(format!("Step {} has been added.", step.position + 1)).send();
+    } else {
+        FlashMessage::error("Another step was added in the meantime, review the sequence and try again.").send();
+    }
+    Ok(see_other(&location))
+}
+
+
+#[tracing::instrument(name = "Stop the enrollment of a subscriber", skip(session, pool))]
+pub async fn stop_subscriber_enrollment(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let (subscriber_id, enrollment_id) = path.into_inner();
+
+    if stop_enrollment(&pool, subscriber_id, enrollment_id).await.map_err(e500)? {
+        FlashMessage::info("The subscriber will not receive the rest of the sequence.").send();
+    } else {
+        FlashMessage::error("The enrollment is not active.").send();
+    }
+    Ok(see_other(&format!("/admin/subscribers/{}", subscriber_id)))
+}
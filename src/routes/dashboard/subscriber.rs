@@ -0,0 +1,355 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::domain::automation::SequenceTrigger;
+use crate::helpers::automation::{enroll_subscriber, get_enrollments};
+use crate::helpers::consent::get_consent_events;
+use crate::helpers::referrals::get_referral_stats;
+use crate::helpers::subscriber_attributes::get_attribute_values;
+use crate::helpers::subscriber_data::{
+    erase_subscriber, export_subscriber, get_confirmation_tokens, get_deliveries, Erasure,
+};
+use crate::helpers::subscription_status::get_subscription_events;
+use crate::helpers::tags::{get_subscriber_tags, tag_subscriber, upsert_tag};
+use crate::routes::preferences::data::json_download;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+
+#[derive(serde::Deserialize)]
+pub struct TagFormData {
+    tag: String,
+}
+
+
+struct SubscriberDetails {
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: chrono::DateTime<chrono::Utc>,
+    time_zone: Option<String>,
+}
+
+
+pub async fn subscriber_details(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    subscriber_id: web::Path<Uuid>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let subscriber_id = subscriber_id.into_inner();
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let subscriber = match get_subscriber_details(&pool, subscriber_id).await.map_err(e500)? {
+        Some(subscriber) => subscriber,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let events = get_consent_events(&pool, subscriber_id).await.map_err(e500)?;
+    let status_history = get_subscription_events(&pool, subscriber_id).await.map_err(e500)?;
+    let attributes = get_attribute_values(&pool, subscriber_id).await.map_err(e500)?;
+    let tokens = get_confirmation_tokens(&pool, subscriber_id).await.map_err(e500)?;
+    let deliveries = get_deliveries(&pool, subscriber_id).await.map_err(e500)?;
+    let tags = get_subscriber_tags(&pool, subscriber_id).await.map_err(e500)?;
+    let enrollments = get_enrollments(&pool, subscriber_id).await.map_err(e500)?;
+    let referrals = get_referral_stats(&pool, subscriber_id).await.map_err(e500)?;
+    let referrals_html = match referrals {
+        Some(stats) => format!(
+            "Referral code: {}. Referred {} confirmed subscriber(s), {} pending.",
+            htmlescape::encode_minimal(stats.referral_code.as_deref().unwrap_or("none yet")),
+            stats.credited,
+            stats.pending,
+        ),
+        None => String::new(),
+    };
+
+    let mut attributes_html = String::new();
+    for attribute in &attributes {
+        writeln!(
+            attributes_html,
+            "<li>{}: {}</li>",
+            htmlescape::encode_minimal(&attribute.label),
+            htmlescape::encode_minimal(&attribute.value),
+        ).unwrap();
+    }
+
+    let mut events_html = String::new();
+    for event in &events {
+        writeln!(
+            events_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            event.occurred_at.to_rfc2822(),
+            event.action,
+            htmlescape::encode_minimal(event.ip_address.as_deref().unwrap_or("-")),
+            htmlescape::encode_minimal(event.user_agent.as_deref().unwrap_or("-")),
+            htmlescape::encode_minimal(&event.source),
+            htmlescape::encode_minimal(&event.consent_version),
+        ).unwrap();
+    }
+
+    let mut status_history_html = String::new();
+    for event in &status_history {
+        writeln!(
+            status_history_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            event.occurred_at.to_rfc2822(),
+            event.from_status.as_deref().unwrap_or("-"),
+            event.to_status,
+            htmlescape::encode_minimal(&event.reason),
+        ).unwrap();
+    }
+
+    let mut enrollments_html = String::new();
+    for enrollment in &enrollments {
+        let stop_html = if enrollment.status == "active" {
+            format!(
+                r#"<form action="/admin/subscribers/{}/enrollments/{}/stop" method="post"><button type="submit">Stop</button></form>"#,
+                subscriber_id,
+                enrollment.enrollment_id,
+            )
+        } else {
+            String::new()
+        };
+        writeln!(
+            enrollments_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            htmlescape::encode_minimal(&enrollment.sequence_name),
+            enrollment.status,
+            enrollment.enrolled_at.to_rfc2822(),
+            enrollment.next_position + 1,
+            enrollment.next_step_at.map(|t| t.to_rfc2822()).unwrap_or_else(|| "-".into()),
+            stop_html,
+        ).unwrap();
+    }
+
+    let mut tokens_html = String::new();
+    for token in &tokens {
+        writeln!(
+            tokens_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            token.created_at.to_rfc2822(),
+            token.expires_at.to_rfc2822(),
+            token.consumed_at.map(|t| t.to_rfc2822()).unwrap_or_else(|| "-".into()),
+        ).unwrap();
+    }
+
+    let mut deliveries_html = String::new();
+    for delivery in &deliveries {
+        writeln!(
+            deliveries_html,
+            r#"<tr><td><a href="/admin/newsletters/{}">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+            delivery.newsletter_issue_id,
+            htmlescape::encode_minimal(&delivery.title),
+            delivery.delivered_at.to_rfc2822(),
+            delivery.opened_at.map(|t| t.to_rfc2822()).unwrap_or_else(|| "-".into()),
+            delivery.clicked_at.map(|t| t.to_rfc2822()).unwrap_or_else(|| "-".into()),
+        ).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Subscriber</title>
+        </head>
+        <body>
+            {}
+            <h1>{}</h1>
+            <p>{} - {}, subscribed at {}. Time zone: {}.</p>
+            <ul>
+            {}
+            </ul>
+            <p>Tags: {}</p>
+            <p>{}</p>
+            <form action="/admin/subscribers/{}/tags" method="post">
+                <label>Tag <input type="text" name="tag" /></label>
+                <button type="submit">Add tag</button>
+            </form>
+            <h2>Consent history</h2>
+            <table>
+                <thead>
+                    <tr><th>When</th><th>Action</th><th>IP address</th><th>User agent</th><th>Source</th><th>Consent version</th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>Status history</h2>
+            <table>
+                <thead>
+                    <tr><th>When</th><th>From</th><th>To</th><th>Reason</th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>Sequences</h2>
+            <table>
+                <thead>
+                    <tr><th>Sequence</th><th>Status</th><th>Enrolled</th><th>Next step</th><th>Due</th><th></th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>Confirmation tokens</h2>
+            <table>
+                <thead>
+                    <tr><th>Created</th><th>Expires</th><th>Used</th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>Deliveries</h2>
+            <table>
+                <thead>
+                    <tr><th>Issue</th><th>Delivered</th><th>Opened</th><th>Clicked</th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>Manage</h2>
+            <p><a href="/admin/subscribers/{}/edit">Correct name or email</a></p>
+            <form action="/admin/subscribers/bulk" method="post">
+                <input type="hidden" name="subscriber_id" value="{}" />
+                <button type="submit" name="action" value="unsubscribe">Unsubscribe</button>
+                <button type="submit" name="action" value="delete">Delete</button>
+            </form>
+            <h2>Data requests</h2>
+            <p><a href="/admin/subscribers/{}/export">Export as JSON</a></p>
+            <form action="/admin/subscribers/{}/erase" method="post">
+                <button type="submit">Erase this subscriber</button>
+            </form>
+            <p><a href="/admin/subscribers">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        htmlescape::encode_minimal(&subscriber.name),
+        htmlescape::encode_minimal(&subscriber.email),
+        subscriber.status,
+        subscriber.subscribed_at.to_rfc2822(),
+        htmlescape::encode_minimal(subscriber.time_zone.as_deref().unwrap_or("not set")),
+        attributes_html,
+        if tags.is_empty() { "none".into() } else { htmlescape::encode_minimal(&tags.join(", ")) },
+        referrals_html,
+        subscriber_id,
+        events_html,
+        status_history_html,
+        enrollments_html,
+        tokens_html,
+        deliveries_html,
+        subscriber_id,
+        subscriber_id,
+        subscriber_id,
+        subscriber_id,
+    )))
+}
+
+
+/// Tag the subscriber, enrolling them in the sequences of the tag.
+#[tracing::instrument(name = "Add a tag to a subscriber", skip(session, pool, form))]
+pub async fn add_subscriber_tag(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    subscriber_id: web::Path<Uuid>,
+    form: web::Form<TagFormData>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let subscriber_id = subscriber_id.into_inner();
+    let location = format!("/admin/subscribers/{}", subscriber_id);
+
+    let tag = form.tag.trim();
+    if tag.is_empty() {
+        FlashMessage::error("Name the tag to add.").send();
+        return Ok(see_other(&location));
+    }
+    if get_subscriber_details(&pool, subscriber_id).await.map_err(e500)?.is_none() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let mut transaction = pool.begin().await.map_err(e500)?;
+    let tag_id = upsert_tag(&mut transaction, tag).await.map_err(e500)?;
+    if !tag_subscriber(&mut transaction, subscriber_id, tag_id).await.map_err(e500)? {
+        FlashMessage::error(format!("The subscriber is already tagged {}.", tag)).send();
+        return Ok(see_other(&location));
+    }
+    let enrolled = enroll_subscriber(&mut transaction, subscriber_id, &SequenceTrigger::Tag(tag.to_owned()))
+        .await
+        .map_err(e500)?;
+    transaction.commit().await.map_err(e500)?;
+
+    FlashMessage::info(format!("The subscriber has been tagged {} and enrolled in {} sequence(s).", tag, enrolled))
+        .send();
+    Ok(see_other(&location))
+}
+
+
+pub async fn export_subscriber_data(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    subscriber_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let subscriber_id = subscriber_id.into_inner();
+
+    match export_subscriber(&pool, subscriber_id).await.map_err(e500)? {
+        Some(export) => Ok(json_download(subscriber_id, &export)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+
+pub async fn erase_subscriber_data(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    subscriber_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let mut transaction = pool.begin().await.map_err(e500)?;
+    let erased = erase_subscriber(&mut transaction, subscriber_id.into_inner(), Erasure::Requested)
+        .await
+        .map_err(e500)?;
+    transaction.commit().await.map_err(e500)?;
+    if erased {
+        FlashMessage::info("The subscriber has been erased.").send();
+    } else {
+        FlashMessage::error("The subscriber does not exist.").send();
+    }
+    Ok(see_other("/admin/dashboard"))
+}
+
+
+#[tracing::instrument(name = "Get subscriber details", skip(pool))]
+async fn get_subscriber_details(pool: &PgPool, subscriber_id: Uuid) -> Result<Option<SubscriberDetails>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        SubscriberDetails,
+        r#"SELECT email, name, status::text AS "status!", subscribed_at, time_zone FROM subscriptions WHERE id = $1"#,
+        subscriber_id,
+    )
+        .fetch_optional(pool)
+        .await
+        .context("Failed to retrieve a subscriber.")?;
+
+    Ok(subscriber)
+}
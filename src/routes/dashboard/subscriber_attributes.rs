@@ -0,0 +1,153 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::domain::subscriber_attribute::NewAttribute;
+use crate::helpers::subscriber_attributes::{
+    delete_attribute_definition, get_attribute_definitions, insert_attribute_definition,
+};
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    key: String,
+    label: String,
+    kind: String,
+    #[serde(default)]
+    options: String,
+    /// Checkbox, only submitted when checked.
+    required: Option<String>,
+}
+
+
+pub async fn subscriber_attributes(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let definitions = get_attribute_definitions(&pool).await.map_err(e500)?;
+    let mut rows_html = String::new();
+    for definition in &definitions {
+        writeln!(
+            rows_html,
+            r#"<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>
+            <td><form action="/admin/attributes/{}/delete" method="post"><button type="submit">Delete</button></form></td></tr>"#,
+            htmlescape::encode_minimal(&definition.key),
+            htmlescape::encode_minimal(&definition.label),
+            definition.kind.as_str(),
+            htmlescape::encode_minimal(&definition.options.join(", ")),
+            if definition.required { "yes" } else { "no" },
+            definition.attribute_id,
+        ).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Subscriber attributes</title>
+        </head>
+        <body>
+            {}
+            <p>Attributes are asked on the signup form under their key and can be used in issues as <code>{{{{ key }}}}</code>.</p>
+            <table>
+                <thead>
+                    <tr><th>Key</th><th>Label</th><th>Type</th><th>Options</th><th>Required</th><th></th></tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <h2>New attribute</h2>
+            <form action="/admin/attributes" method="post">
+            <label>Key <input type="text" name="key" placeholder="company" /></label>
+            <br />
+            <label>Label <input type="text" name="label" placeholder="Company" /></label>
+            <br />
+            <label>Type
+            <select name="kind">
+                <option value="text">Text</option>
+                <option value="number">Number</option>
+                <option value="date">Date</option>
+                <option value="enum">One of a list</option>
+                <option value="boolean">Yes or no</option>
+            </select>
+            </label>
+            <br />
+            <label>Options, comma separated <input type="text" name="options" /></label>
+            <br />
+            <label><input type="checkbox" name="required" /> Required</label>
+            <br />
+            <button type="submit">Add attribute</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        rows_html,
+    )))
+}
+
+
+#[tracing::instrument(name = "Add a subscriber attribute", skip(session, pool, form))]
+pub async fn add_subscriber_attribute(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let attribute = match NewAttribute::parse(
+        &form.key,
+        &form.label,
+        &form.kind,
+        &form.options,
+        form.required.is_some(),
+    ) {
+        Ok(attribute) => attribute,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/attributes"));
+        }
+    };
+
+    if insert_attribute_definition(&pool, &attribute).await.map_err(e500)? {
+        FlashMessage::info(format!("The {} attribute has been added.", attribute.key)).send();
+    } else {
+        FlashMessage::error(format!("There is already an attribute with the key {}.", attribute.key)).send();
+    }
+    Ok(see_other("/admin/attributes"))
+}
+
+
+#[tracing::instrument(name = "Delete a subscriber attribute", skip(session, pool))]
+pub async fn delete_subscriber_attribute(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    attribute_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    delete_attribute_definition(&pool, attribute_id.into_inner()).await.map_err(e500)?;
+    FlashMessage::info("The attribute and its values have been deleted.").send();
+    Ok(see_other("/admin/attributes"))
+}
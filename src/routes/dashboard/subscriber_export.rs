@@ -0,0 +1,146 @@
+use actix_web::http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::domain::subscriber_export::{ExportFilter, ExportFormat};
+use crate::domain::subscription_status::SubscriptionStatus;
+use crate::helpers::subscriber_attributes::get_attribute_definitions;
+use crate::helpers::subscriber_export::export_subscribers;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    #[serde(default)]
+    format: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    tag: String,
+    #[serde(default)]
+    from: String,
+    #[serde(default)]
+    to: String,
+    /// Key of a custom attribute, matched against `attribute_value`.
+    #[serde(default)]
+    attribute: String,
+    #[serde(default)]
+    attribute_value: String,
+}
+
+
+pub async fn export_subscribers_form(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let mut statuses_html = String::new();
+    for status in SubscriptionStatus::ALL.iter() {
+        writeln!(statuses_html, r#"<option value="{}">{}</option>"#, status.as_str(), status.label()).unwrap();
+    }
+
+    let mut attributes_html = String::new();
+    for definition in get_attribute_definitions(&pool).await.map_err(e500)? {
+        writeln!(
+            attributes_html,
+            r#"<option value="{}">{}</option>"#,
+            htmlescape::encode_attribute(&definition.key),
+            htmlescape::encode_minimal(&definition.label),
+        ).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Export subscribers</title>
+        </head>
+        <body>
+            {}
+            <form action="/admin/subscribers/export/download" method="get">
+            <label>Status
+            <select name="status">
+                <option value="">Any</option>
+                {}
+            </select>
+            </label>
+            <br />
+            <label>Tag <input type="text" name="tag" /></label>
+            <br />
+            <label>Subscribed from <input type="date" name="from" /></label>
+            <label>to <input type="date" name="to" /></label>
+            <br />
+            <label>Attribute
+            <select name="attribute">
+                <option value="">Any</option>
+                {}
+            </select>
+            </label>
+            <label>is <input type="text" name="attribute_value" /></label>
+            <br />
+            <label>Format
+            <select name="format">
+                <option value="csv">CSV</option>
+                <option value="ndjson">NDJSON</option>
+            </select>
+            </label>
+            <br />
+            <button type="submit">Download</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        statuses_html,
+        attributes_html,
+    )))
+}
+
+
+/// Stream the matching subscribers as a download.
+#[tracing::instrument(name = "Export subscribers", skip(session, pool, query))]
+pub async fn download_subscribers(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    query: web::Query<QueryParams>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let attribute_definitions = get_attribute_definitions(&pool).await.map_err(e500)?;
+    let format = ExportFormat::parse(&query.format);
+    let filter = ExportFilter::parse(&query.status, &query.tag, &query.from, &query.to)
+        .and_then(|filter| filter.with_attribute(&attribute_definitions, &query.attribute, &query.attribute_value));
+    let (format, filter) = match (format, filter) {
+        (Ok(format), Ok(filter)) => (format, filter),
+        (Err(e), _) | (_, Err(e)) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/subscribers/export"));
+        }
+    };
+
+    let attribute_keys = attribute_definitions.into_iter().map(|definition| definition.key).collect();
+    let filename = format!("subscribers-{}.{}", Utc::now().format("%Y%m%d"), format.extension());
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .streaming(export_subscribers(pool.get_ref().clone(), filter, format, attribute_keys)))
+}
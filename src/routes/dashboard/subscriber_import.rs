@@ -0,0 +1,350 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::configuration::subscription_settings::SubscriptionSettings;
+use crate::domain::automation::SequenceTrigger;
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::domain::subscriber_import::{parse_import, ColumnMapping, ImportRow, ImportStatus, ParsedImport};
+use crate::domain::subscription_status::SubscriptionStatus;
+use crate::email::email_client::EmailClient;
+use crate::helpers::automation::enroll_subscriber;
+use crate::helpers::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::helpers::referrals::assign_referral_code;
+use crate::helpers::subscription_status::record_initial_status;
+use crate::helpers::suppression::email_hash;
+use crate::helpers::tags::{tag_subscriber, upsert_tag};
+use crate::routes::subscriptions::helpers::send_confirmation_email;
+use crate::routes::subscriptions::route::issue_token;
+use crate::session_state::TypedSession;
+use crate::startup::run::ApplicationBaseUrl;
+use crate::utils::{e500, see_other};
+
+/// Imports are pasted into a textarea, well beyond the default form size limit.
+pub const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    csv: String,
+    #[serde(default)]
+    email_column: String,
+    #[serde(default)]
+    name_column: String,
+    #[serde(default)]
+    status_column: String,
+    #[serde(default)]
+    tags_column: String,
+    #[serde(default)]
+    subscribed_at_column: String,
+    /// Checkbox: imported rows become pending and get a confirmation email.
+    double_opt_in: Option<String>,
+    /// `preview` for a dry run, `import` to write the valid rows.
+    #[serde(default)]
+    action: String,
+}
+
+impl FormData {
+    fn mapping(&self) -> ColumnMapping {
+        ColumnMapping {
+            email: self.email_column.clone(),
+            name: self.name_column.clone(),
+            status: self.status_column.clone(),
+            tags: self.tags_column.clone(),
+            subscribed_at: self.subscribed_at_column.clone(),
+        }
+    }
+}
+
+
+pub async fn import_subscribers_form(
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    Ok(import_page(&msg_html, "", &ColumnMapping::default(), false))
+}
+
+
+/// Dry run or import of a CSV list of subscribers.
+///
+/// Rows that are invalid, repeated, already subscribed or erased are skipped; the others are written in
+/// a single transaction.
+#[tracing::instrument(
+    name = "Import subscribers",
+    skip(form, request, session, pool, email_client, base_url, settings),
+    fields(action = %form.action)
+)]
+pub async fn import_subscribers(
+    form: web::Form<FormData>,
+    request: HttpRequest,
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let mapping = form.mapping();
+    let double_opt_in = form.double_opt_in.is_some();
+
+    let mut parsed = match parse_import(&form.csv, &mapping) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/subscribers/import"));
+        }
+    };
+
+    let mut transaction = pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    skip_known_addresses(&mut transaction, &mut parsed)
+        .await
+        .context("Failed to look up the imported addresses.")
+        .map_err(e500)?;
+
+    if form.action != "import" {
+        return Ok(import_page(&summary_html(&parsed), &form.csv, &mapping, double_opt_in));
+    }
+
+    let consent = ConsentContext::from_request(&request, "csv-import", &settings.consent_version);
+    let confirmations = insert_imported_rows(&mut transaction, &parsed.valid, double_opt_in, settings.token_ttl(), &consent)
+        .await
+        .map_err(e500)?;
+    transaction.commit()
+        .await
+        .context("Failed to commit SQL transaction to import subscribers.")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "Imported {} subscribers. Skipped {} invalid and {} duplicate rows.",
+        parsed.valid.len(),
+        parsed.invalid.len(),
+        parsed.duplicates.len(),
+    )).send();
+
+    if double_opt_in {
+        let mut failed = 0;
+        for (email, subscription_token) in &confirmations {
+            if let Err(e) = send_confirmation_email(&email_client, email, &base_url.0, subscription_token).await {
+                tracing::error!(error.cause_chain = ?e, "Failed to send a confirmation email to an imported subscriber.");
+                failed += 1;
+            }
+        }
+        FlashMessage::info(format!("Sent {} confirmation emails.", confirmations.len() - failed)).send();
+        if failed > 0 {
+            FlashMessage::error(format!(
+                "{} confirmation emails could not be sent, those subscribers can ask for a new link.",
+                failed,
+            )).send();
+        }
+    }
+
+    Ok(see_other("/admin/subscribers/import"))
+}
+
+
+fn summary_html(parsed: &ParsedImport) -> String {
+    let mut html = format!(
+        "<h2>Dry run</h2><p>{} valid, {} invalid and {} duplicate rows. Nothing has been imported yet.</p>",
+        parsed.valid.len(),
+        parsed.invalid.len(),
+        parsed.duplicates.len(),
+    );
+    for (title, rows) in [("Invalid rows", &parsed.invalid), ("Duplicate rows", &parsed.duplicates)] {
+        if rows.is_empty() {
+            continue;
+        }
+        write!(html, "<h3>{}</h3><table><thead><tr><th>Line</th><th>Email</th><th>Reason</th></tr></thead><tbody>", title).unwrap();
+        for row in rows.iter() {
+            write!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                row.line,
+                htmlescape::encode_minimal(&row.email),
+                htmlescape::encode_minimal(&row.reason),
+            ).unwrap();
+        }
+        html.push_str("</tbody></table>");
+    }
+    html
+}
+
+
+fn import_page(msg_html: &str, csv: &str, mapping: &ColumnMapping, double_opt_in: bool) -> HttpResponse {
+    HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Import subscribers</title>
+        </head>
+        <body>
+            {}
+            <form action="/admin/subscribers/import" method="post">
+            <label>CSV with a header line
+            <textarea name="csv" rows="20" cols="80">{}</textarea>
+            </label>
+            <fieldset>
+                <legend>Header of each column (leave empty when missing)</legend>
+                <label>Email <input type="text" name="email_column" value="{}" /></label>
+                <label>Name <input type="text" name="name_column" value="{}" /></label>
+                <label>Status <input type="text" name="status_column" value="{}" /></label>
+                <label>Tags <input type="text" name="tags_column" value="{}" /></label>
+                <label>Subscribed at <input type="text" name="subscribed_at_column" value="{}" /></label>
+            </fieldset>
+            <label>
+            <input type="checkbox" name="double_opt_in" {} />
+            Ask imported subscribers to confirm by email
+            </label>
+            <br />
+            <button type="submit" name="action" value="preview">Dry run</button>
+            <button type="submit" name="action" value="import">Import</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        htmlescape::encode_minimal(csv),
+        htmlescape::encode_attribute(&mapping.email),
+        htmlescape::encode_attribute(&mapping.name),
+        htmlescape::encode_attribute(&mapping.status),
+        htmlescape::encode_attribute(&mapping.tags),
+        htmlescape::encode_attribute(&mapping.subscribed_at),
+        if double_opt_in { "checked" } else { "" },
+    ))
+}
+
+
+/// Skip the addresses that are already subscribed, or that were erased and must not come back.
+#[tracing::instrument(name = "Skip known addresses of an import", skip_all)]
+async fn skip_known_addresses(
+    transaction: &mut Transaction<'_, Postgres>,
+    parsed: &mut ParsedImport,
+) -> Result<(), sqlx::Error> {
+    let addresses: Vec<String> = parsed.valid.iter().map(|row| row.email.normalized().to_lowercase()).collect();
+    let subscribed: HashSet<String> = sqlx::query!(
+        r#"
+        SELECT lower(normalized_email) AS "email!" FROM subscriptions
+        WHERE lower(normalized_email) = ANY($1)
+        "#,
+        &addresses[..],
+    )
+        .fetch_all(&mut *transaction)
+        .await?
+        .into_iter()
+        .map(|r| r.email)
+        .collect();
+
+    let hashes: Vec<String> = parsed.valid.iter().map(|row| email_hash(row.email.normalized())).collect();
+    let suppressed: HashSet<String> = sqlx::query!(
+        r#"SELECT email_hash FROM suppressions WHERE email_hash = ANY($1)"#,
+        &hashes[..],
+    )
+        .fetch_all(&mut *transaction)
+        .await?
+        .into_iter()
+        .map(|r| r.email_hash)
+        .collect();
+
+    parsed.mark_duplicates("Already a subscriber.", |row| {
+        subscribed.contains(&row.email.normalized().to_lowercase())
+    });
+    parsed.mark_invalid("Erased at the subscriber's request, it cannot be imported.", |row| {
+        suppressed.contains(&email_hash(row.email.normalized()))
+    });
+    Ok(())
+}
+
+
+/// Insert the rows and their tags.
+///
+/// Returns the confirmation tokens issued to the rows that have to confirm their subscription.
+async fn insert_imported_rows<'a>(
+    transaction: &mut Transaction<'_, Postgres>,
+    rows: &'a [ImportRow],
+    double_opt_in: bool,
+    token_ttl: chrono::Duration,
+    consent: &ConsentContext,
+) -> Result<Vec<(&'a SubscriberEmail, String)>, anyhow::Error> {
+    let mut tag_ids: HashMap<&str, Uuid> = HashMap::new();
+    let mut confirmations = Vec::new();
+
+    for row in rows {
+        let status = match row.status {
+            ImportStatus::Unsubscribed => ImportStatus::Unsubscribed,
+            _ if double_opt_in => ImportStatus::Pending,
+            status => status,
+        };
+        let subscriber_id = sqlx::query!(
+            r#"
+            INSERT INTO subscriptions (email, normalized_email, name, subscribed_at, status)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+            row.email.as_ref(),
+            row.email.normalized(),
+            row.name.as_ref(),
+            row.subscribed_at.unwrap_or_else(Utc::now),
+            status.status() as SubscriptionStatus,
+        )
+            .fetch_one(&mut *transaction)
+            .await
+            .with_context(|| format!("Failed to import the subscriber on line {}.", row.line))?
+            .id;
+        record_initial_status(transaction, subscriber_id, status.status(), "import")
+            .await
+            .context("Failed to record the status of an imported subscriber.")?;
+        if status == ImportStatus::Confirmed {
+            assign_referral_code(transaction, subscriber_id)
+                .await
+                .context("Failed to assign a referral code to an imported subscriber.")?;
+        }
+
+        for tag in &row.tags {
+            let tag_id = match tag_ids.get(tag.as_str()) {
+                Some(tag_id) => *tag_id,
+                None => {
+                    let tag_id = upsert_tag(transaction, tag).await.context("Failed to store a tag.")?;
+                    tag_ids.insert(tag, tag_id);
+                    tag_id
+                }
+            };
+            tag_subscriber(transaction, subscriber_id, tag_id)
+                .await
+                .context("Failed to tag an imported subscriber.")?;
+            // Only confirmed rows are enrolled, the others are when they confirm
+            enroll_subscriber(transaction, subscriber_id, &SequenceTrigger::Tag(tag.clone()))
+                .await
+                .context("Failed to enroll an imported subscriber in a sequence.")?;
+        }
+
+        record_consent_event(transaction, subscriber_id, ConsentAction::Import, consent)
+            .await
+            .context("Failed to record the import in the consent trail.")?;
+
+        if status == ImportStatus::Pending {
+            confirmations.push((&row.email, issue_token(transaction, subscriber_id, token_ttl).await?));
+        }
+    }
+
+    Ok(confirmations)
+}
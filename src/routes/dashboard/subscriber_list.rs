@@ -0,0 +1,212 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::domain::subscriber_export::ExportFilter;
+use crate::domain::subscriber_listing::{SortColumn, SortDirection, SubscriberListQuery};
+use crate::domain::subscription_status::SubscriptionStatus;
+use crate::helpers::subscriber_listing::list_subscribers;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+const PAGE_SIZE: i64 = 50;
+
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    /// Start of an email address or name.
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    tag: String,
+    #[serde(default)]
+    from: String,
+    #[serde(default)]
+    to: String,
+    #[serde(default)]
+    sort: String,
+    #[serde(default)]
+    dir: String,
+    /// Cursor of the page, see `ListCursor`.
+    #[serde(default)]
+    after: String,
+}
+
+impl QueryParams {
+    /// Query string of the same search, sorted and paginated as given.
+    fn link(&self, sort: &str, dir: &str, after: &str) -> String {
+        let params = [
+            ("q", self.q.as_str()),
+            ("status", self.status.as_str()),
+            ("tag", self.tag.as_str()),
+            ("from", self.from.as_str()),
+            ("to", self.to.as_str()),
+            ("sort", sort),
+            ("dir", dir),
+            ("after", after),
+        ];
+        let query: Vec<String> = params
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| format!("{}={}", name, urlencoding::encode(value)))
+            .collect();
+        format!("/admin/subscribers?{}", query.join("&"))
+    }
+}
+
+
+#[tracing::instrument(name = "Browse subscribers", skip(session, pool, query, flash_messages))]
+pub async fn browse_subscribers(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    query: web::Query<QueryParams>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let list_query = ExportFilter::parse(&query.status, &query.tag, &query.from, &query.to)
+        .and_then(|filter| SubscriberListQuery::parse(&query.q, filter, &query.sort, &query.dir, &query.after));
+    let list_query = match list_query {
+        Ok(list_query) => list_query,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/subscribers"));
+        }
+    };
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    // One extra row tells whether there is a next page
+    let mut subscribers = list_subscribers(&pool, &list_query, PAGE_SIZE + 1).await.map_err(e500)?;
+    let has_next_page = subscribers.len() as i64 > PAGE_SIZE;
+    subscribers.truncate(PAGE_SIZE as usize);
+
+    let mut rows_html = String::new();
+    for subscriber in &subscribers {
+        writeln!(
+            rows_html,
+            r#"<tr><td><a href="/admin/subscribers/{}">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td><input type="checkbox" name="subscriber_id" value="{}" form="bulk" /></td></tr>"#,
+            subscriber.id,
+            htmlescape::encode_minimal(&subscriber.email),
+            htmlescape::encode_minimal(&subscriber.name),
+            subscriber.status,
+            subscriber.subscribed_at.to_rfc2822(),
+            subscriber.id,
+        ).unwrap();
+    }
+    if subscribers.is_empty() {
+        rows_html.push_str(r#"<tr><td colspan="5">No subscriber matches.</td></tr>"#);
+    }
+
+    let mut headers_html = String::new();
+    for (column, title) in [
+        (SortColumn::Email, "Email"),
+        (SortColumn::Name, "Name"),
+        (SortColumn::Status, "Status"),
+        (SortColumn::SubscribedAt, "Subscribed at"),
+    ].iter() {
+        // Clicking the current sort column flips the direction
+        let (dir, marker) = if *column == list_query.sort {
+            let marker = match list_query.direction {
+                SortDirection::Ascending => " &uarr;",
+                SortDirection::Descending => " &darr;",
+            };
+            (list_query.direction.reversed().as_str(), marker)
+        } else {
+            ("", "")
+        };
+        write!(
+            headers_html,
+            r#"<th><a href="{}">{}</a>{}</th>"#,
+            htmlescape::encode_minimal(&query.link(column.as_str(), dir, "")),
+            title,
+            marker,
+        ).unwrap();
+    }
+    headers_html.push_str("<th></th>");
+
+    let mut pages_html = String::new();
+    if list_query.after.is_some() {
+        write!(
+            pages_html,
+            r#"<a href="{}">First page</a> "#,
+            htmlescape::encode_minimal(&query.link(&query.sort, &query.dir, "")),
+        ).unwrap();
+    }
+    if let (true, Some(last)) = (has_next_page, subscribers.last()) {
+        write!(
+            pages_html,
+            r#"<a href="{}">Next page</a>"#,
+            htmlescape::encode_minimal(&query.link(&query.sort, &query.dir, &last.cursor().encode())),
+        ).unwrap();
+    }
+
+    let mut status_options = String::from(r#"<option value="">Any</option>"#);
+    for status in SubscriptionStatus::ALL.iter() {
+        let selected = if query.status == status.as_str() { " selected" } else { "" };
+        write!(status_options, r#"<option value="{}"{}>{}</option>"#, status.as_str(), selected, status.label()).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Subscribers</title>
+        </head>
+        <body>
+            {}
+            <p><a href="/admin/subscribers/new">Add a subscriber</a></p>
+            <form action="/admin/subscribers" method="get">
+            <label>Email or name starts with <input type="search" name="q" value="{}" /></label>
+            <label>Status <select name="status">{}</select></label>
+            <label>Tag <input type="text" name="tag" value="{}" /></label>
+            <label>Subscribed from <input type="date" name="from" value="{}" /></label>
+            <label>to <input type="date" name="to" value="{}" /></label>
+            <input type="hidden" name="sort" value="{}" />
+            <input type="hidden" name="dir" value="{}" />
+            <button type="submit">Search</button>
+            </form>
+            <table>
+                <thead>
+                    <tr>{}</tr>
+                </thead>
+                <tbody>
+                {}
+                </tbody>
+            </table>
+            <p>{}</p>
+            <form id="bulk" action="/admin/subscribers/bulk" method="post">
+            <label>With the selected subscribers
+            <select name="action">
+                <option value="unsubscribe">Unsubscribe</option>
+                <option value="delete">Delete</option>
+            </select>
+            </label>
+            <button type="submit">Apply</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        htmlescape::encode_attribute(&query.q),
+        status_options,
+        htmlescape::encode_attribute(&query.tag),
+        htmlescape::encode_attribute(&query.from),
+        htmlescape::encode_attribute(&query.to),
+        list_query.sort.as_str(),
+        list_query.direction.as_str(),
+        headers_html,
+        rows_html,
+        pages_html,
+    )))
+}
@@ -0,0 +1,362 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::configuration::subscription_settings::SubscriptionSettings;
+use crate::domain::bulk_action::{BulkAction, BulkSelection};
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::domain::subscriber_name::SubscriberName;
+use crate::domain::subscription_status::SubscriptionStatus;
+use crate::email::email_client::EmailClient;
+use crate::helpers::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::helpers::subscriber_admin::{
+    delete_subscribers, get_subscriber_summaries, insert_subscriber_by_admin, is_email_taken,
+    unsubscribe_subscribers, update_subscriber_details, SubscriberSummary,
+};
+use crate::helpers::suppression::is_suppressed;
+use crate::routes::subscriptions::helpers::send_confirmation_email;
+use crate::routes::subscriptions::route::issue_token;
+use crate::session_state::TypedSession;
+use crate::startup::run::ApplicationBaseUrl;
+use crate::utils::{e500, is_unique_violation, see_other};
+
+
+#[derive(serde::Deserialize)]
+pub struct NewSubscriberForm {
+    email: String,
+    name: String,
+    /// `email` to send a confirmation email, `none` to add the subscriber as confirmed.
+    #[serde(default)]
+    confirmation: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct EditSubscriberForm {
+    email: String,
+    name: String,
+}
+
+
+fn parse_details(email: &str, name: &str) -> Result<(SubscriberEmail, SubscriberName), String> {
+    Ok((SubscriberEmail::parse(email.to_string())?, SubscriberName::parse(name.to_string())?))
+}
+
+
+pub async fn new_subscriber_form(
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Add a subscriber</title>
+        </head>
+        <body>
+            {}
+            <form action="/admin/subscribers/new" method="post">
+            <label>Name <input type="text" name="name" /></label>
+            <br />
+            <label>Email <input type="email" name="email" /></label>
+            <br />
+            <label><input type="radio" name="confirmation" value="email" checked /> Send a confirmation email</label>
+            <label><input type="radio" name="confirmation" value="none" /> Subscribe without confirmation</label>
+            <br />
+            <button type="submit">Add subscriber</button>
+            </form>
+            <p><a href="/admin/subscribers">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+    )))
+}
+
+
+/// Add a subscriber on someone's behalf, pending confirmation or confirmed right away.
+#[tracing::instrument(
+    name = "Add a subscriber on someone's behalf",
+    skip(form, request, session, pool, email_client, base_url, settings),
+    fields(subscriber_email = %form.email)
+)]
+pub async fn add_subscriber(
+    form: web::Form<NewSubscriberForm>,
+    request: HttpRequest,
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let (email, name) = match parse_details(&form.email, &form.name) {
+        Ok(details) => details,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/subscribers/new"));
+        }
+    };
+    let send_confirmation = form.confirmation != "none";
+
+    let mut transaction = pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    if is_email_taken(&mut transaction, &email, None).await.map_err(e500)? {
+        FlashMessage::error("Someone is already subscribed with this address.").send();
+        return Ok(see_other("/admin/subscribers/new"));
+    }
+    if is_suppressed(&mut transaction, email.normalized()).await.map_err(e500)? {
+        FlashMessage::error("This address was erased at the subscriber's request, it cannot be added.").send();
+        return Ok(see_other("/admin/subscribers/new"));
+    }
+
+    let status = if send_confirmation {
+        SubscriptionStatus::PendingConfirmation
+    } else {
+        SubscriptionStatus::Confirmed
+    };
+    let subscriber_id = match insert_subscriber_by_admin(&mut transaction, &email, &name, status).await {
+        Ok(subscriber_id) => subscriber_id,
+        // Someone signed up with the address since the check above
+        Err(e) if is_unique_violation(&e) => {
+            FlashMessage::error("Someone is already subscribed with this address.").send();
+            return Ok(see_other("/admin/subscribers/new"));
+        }
+        Err(e) => Err(e).context("Failed to insert a subscriber added by an admin.").map_err(e500)?,
+    };
+    let consent = ConsentContext::from_request(&request, "admin", &settings.consent_version);
+    record_consent_event(&mut transaction, subscriber_id, ConsentAction::Admin, &consent)
+        .await
+        .context("Failed to record a subscriber added by an admin in the consent trail.")
+        .map_err(e500)?;
+    let subscription_token = if send_confirmation {
+        Some(issue_token(&mut transaction, subscriber_id, settings.token_ttl()).await.map_err(e500)?)
+    } else {
+        None
+    };
+    transaction.commit()
+        .await
+        .context("Failed to commit SQL transaction to add a subscriber.")
+        .map_err(e500)?;
+
+    match subscription_token {
+        Some(subscription_token) => {
+            match send_confirmation_email(&email_client, &email, &base_url.0, &subscription_token).await {
+                Ok(()) => FlashMessage::info("The subscriber has been added and asked to confirm.").send(),
+                Err(e) => {
+                    tracing::error!(error.cause_chain = ?e, "Failed to send a confirmation email.");
+                    FlashMessage::error("The subscriber has been added, but the confirmation email could not be sent.").send();
+                }
+            }
+        }
+        None => FlashMessage::info("The subscriber has been added.").send(),
+    }
+    Ok(see_other(&format!("/admin/subscribers/{}", subscriber_id)))
+}
+
+
+pub async fn edit_subscriber_form(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    subscriber_id: web::Path<Uuid>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let subscriber_id = subscriber_id.into_inner();
+
+    let subscriber = match get_subscriber_summaries(&pool, &[subscriber_id]).await.map_err(e500)?.pop() {
+        Some(subscriber) => subscriber,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Edit subscriber</title>
+        </head>
+        <body>
+            {}
+            <form action="/admin/subscribers/{}/edit" method="post">
+            <label>Name <input type="text" name="name" value="{}" /></label>
+            <br />
+            <label>Email <input type="email" name="email" value="{}" /></label>
+            <br />
+            <button type="submit">Save</button>
+            </form>
+            <p><a href="/admin/subscribers/{}">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        subscriber_id,
+        htmlescape::encode_attribute(&subscriber.name),
+        htmlescape::encode_attribute(&subscriber.email),
+        subscriber_id,
+    )))
+}
+
+
+#[tracing::instrument(name = "Correct the details of a subscriber", skip(form, session, pool))]
+pub async fn edit_subscriber(
+    form: web::Form<EditSubscriberForm>,
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    subscriber_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let subscriber_id = subscriber_id.into_inner();
+    let edit_page = format!("/admin/subscribers/{}/edit", subscriber_id);
+
+    let (email, name) = match parse_details(&form.email, &form.name) {
+        Ok(details) => details,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(&edit_page));
+        }
+    };
+
+    let mut transaction = pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    if is_email_taken(&mut transaction, &email, Some(subscriber_id)).await.map_err(e500)? {
+        FlashMessage::error("Another subscriber has this address.").send();
+        return Ok(see_other(&edit_page));
+    }
+    if is_suppressed(&mut transaction, email.normalized()).await.map_err(e500)? {
+        FlashMessage::error("This address was erased at the subscriber's request, it cannot be used.").send();
+        return Ok(see_other(&edit_page));
+    }
+    match update_subscriber_details(&mut transaction, subscriber_id, &email, &name).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(HttpResponse::NotFound().finish()),
+        Err(e) if is_unique_violation(&e) => {
+            FlashMessage::error("Another subscriber has this address.").send();
+            return Ok(see_other(&edit_page));
+        }
+        Err(e) => return Err(e500(e).into()),
+    }
+    transaction.commit()
+        .await
+        .context("Failed to commit SQL transaction to correct a subscriber.")
+        .map_err(e500)?;
+
+    FlashMessage::info("The subscriber has been updated.").send();
+    Ok(see_other(&format!("/admin/subscribers/{}", subscriber_id)))
+}
+
+
+/// Unsubscribe or delete the selected subscribers, once the admin confirmed the selection.
+#[tracing::instrument(name = "Apply an action to subscribers", skip(form, session, pool))]
+pub async fn bulk_action(
+    form: web::Form<Vec<(String, String)>>,
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let selection = match BulkSelection::parse(&form) {
+        Ok(selection) => selection,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/subscribers"));
+        }
+    };
+
+    if !selection.confirmed {
+        let subscribers = get_subscriber_summaries(&pool, &selection.subscriber_ids).await.map_err(e500)?;
+        return Ok(confirmation_page(selection.action, &subscribers));
+    }
+
+    let (affected, done) = match selection.action {
+        BulkAction::Unsubscribe => (
+            unsubscribe_subscribers(&pool, &selection.subscriber_ids).await.map_err(e500)?,
+            "unsubscribed",
+        ),
+        BulkAction::Delete => (
+            delete_subscribers(&pool, &selection.subscriber_ids).await.map_err(e500)?,
+            "deleted",
+        ),
+    };
+    FlashMessage::info(format!("{} subscribers have been {}.", affected, done)).send();
+    Ok(see_other("/admin/subscribers"))
+}
+
+
+fn confirmation_page(
+    action: BulkAction,
+    subscribers: &[SubscriberSummary],
+) -> HttpResponse {
+    let mut subscribers_html = String::new();
+    for subscriber in subscribers {
+        writeln!(
+            subscribers_html,
+            r#"<li>{} ({}, {})<input type="hidden" name="subscriber_id" value="{}" /></li>"#,
+            htmlescape::encode_minimal(&subscriber.email),
+            htmlescape::encode_minimal(&subscriber.name),
+            subscriber.status,
+            subscriber.id,
+        ).unwrap();
+    }
+    let consequence = match action {
+        BulkAction::Unsubscribe => "They will not receive any further issue.",
+        BulkAction::Delete => "Their records are deleted for good, they can subscribe again later.",
+    };
+
+    HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Confirm</title>
+        </head>
+        <body>
+            <form action="/admin/subscribers/bulk" method="post">
+            <p>{} these {} subscribers? {}</p>
+            <ul>
+            {}
+            </ul>
+            <input type="hidden" name="action" value="{}" />
+            <input type="hidden" name="confirmed" value="yes" />
+            <button type="submit">{}</button>
+            </form>
+            <p><a href="/admin/subscribers">Cancel</a></p>
+        </body>
+        </html>"#,
+        action.verb(),
+        subscribers.len(),
+        consequence,
+        subscribers_html,
+        action.as_str(),
+        action.verb(),
+    ))
+}
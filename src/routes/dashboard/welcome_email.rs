@@ -0,0 +1,107 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::domain::welcome_email::WelcomeEmail;
+use crate::helpers::welcome_email::{get_welcome_email, save_welcome_email};
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    subject: String,
+    text_content: String,
+    html_content: String,
+    /// Checkboxes, only submitted when checked.
+    enabled: Option<String>,
+    include_latest_issue: Option<String>,
+}
+
+
+pub async fn welcome_email_form(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let welcome_email = get_welcome_email(&pool).await.map_err(e500)?;
+    let checked = |on: bool| if on { " checked" } else { "" };
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Welcome email</title>
+        </head>
+        <body>
+            {}
+            <p>Sent once to every subscriber who confirms their subscription.
+            The subject and bodies can use merge fields such as <code>{{{{ name | there }}}}</code>.</p>
+            <form action="/admin/welcome-email" method="post">
+            <label><input type="checkbox" name="enabled" value="on"{} /> Send the welcome email</label>
+            <br />
+            <label>Subject <input type="text" name="subject" value="{}" /></label>
+            <br />
+            <label>Plain text
+            <textarea name="text_content" rows="10" cols="60">{}</textarea>
+            </label>
+            <br />
+            <label>HTML
+            <textarea name="html_content" rows="10" cols="60">{}</textarea>
+            </label>
+            <br />
+            <label><input type="checkbox" name="include_latest_issue" value="on"{} /> Include the latest issue</label>
+            <br />
+            <button type="submit">Save</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        checked(welcome_email.enabled),
+        htmlescape::encode_attribute(&welcome_email.subject),
+        htmlescape::encode_minimal(&welcome_email.text_content),
+        htmlescape::encode_minimal(&welcome_email.html_content),
+        checked(welcome_email.include_latest_issue),
+    )))
+}
+
+
+#[tracing::instrument(name = "Update the welcome email", skip(form, session, pool))]
+pub async fn update_welcome_email(
+    form: web::Form<FormData>,
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    let welcome_email = WelcomeEmail::parse(
+        form.enabled.is_some(),
+        &form.subject,
+        &form.text_content,
+        &form.html_content,
+        form.include_latest_issue.is_some(),
+    );
+    match welcome_email {
+        Ok(welcome_email) => {
+            save_welcome_email(&pool, &welcome_email).await.map_err(e500)?;
+            FlashMessage::info("The welcome email has been saved.").send();
+        }
+        Err(e) => FlashMessage::error(e).send(),
+    }
+    Ok(see_other("/admin/welcome-email"))
+}
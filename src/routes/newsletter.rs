@@ -1,2 +1 @@
 pub mod route;
-mod helper;
\ No newline at end of file
@@ -1,10 +1,16 @@
 use actix_web::{HttpResponse, web};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
+use rand::thread_rng;
 use sqlx::PgPool;
-use crate::domain::subscriber_email::SubscriberEmail;
-use crate::email::email_client::EmailClient;
-use crate::routes::newsletter::helper::{ConfirmedSubscriber};
+use std::str::FromStr;
+use crate::configuration::newsletter_settings::NewsletterSettings;
+use crate::domain::new_issue::NewIssue;
+use crate::domain::subject_test::{SubjectTest, SubjectTestMetric};
+use crate::domain::subscriber_time_zone::SubscriberTimeZone;
+use crate::helpers::newsletter_issue::{
+    enqueue_delivery_tasks, get_confirmed_recipients, insert_newsletter_issue, QueuedDelivery,
+};
 use crate::session_state::TypedSession;
 use crate::utils::{e500, see_other};
 
@@ -14,45 +20,80 @@ pub struct FormData {
     title: String,
     text_content: String,
     html_content: String,
+    /// Alternative subject lines to A/B test against the title, one per line.
+    #[serde(default)]
+    subject_variants: String,
+    #[serde(default)]
+    sample_percentage: String,
+    #[serde(default)]
+    test_metric: String,
+    #[serde(default)]
+    test_window_minutes: String,
+    /// `HH:MM`, to deliver at that time in each subscriber's time zone.
+    #[serde(default)]
+    local_send_time: String,
 }
 
 
-#[tracing::instrument(
-    name = "Get confirmed subscribers", skip(pool)
-)]
-async fn get_confirmed_subscribers(pool: &PgPool) -> Result<Vec<Result<ConfirmedSubscriber, anyhow::Error>>, anyhow::Error> {
-
-    let confirmed_subscribers = sqlx::query!(
-        r#"
-        SELECT email 
-        FROM subscriptions 
-        WHERE status = 'confirmed'
-        "#,
-    ).fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|r| match SubscriberEmail::parse(r.email) {
-            Ok(email) => Ok(ConfirmedSubscriber {email}),
-            Err(error) => {
-                tracing::warn!("A confirmed subscriber is using an invalid email address.\n{}", error);
-                Err(anyhow::anyhow!(error))
-            }
-        }).collect();
-
-    Ok(confirmed_subscribers)
+fn parse_issue(form: FormData, settings: &NewsletterSettings) -> Result<NewIssue, String> {
+    let mut subjects = vec![form.title.clone()];
+    subjects.extend(
+        form.subject_variants.lines()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    );
+
+    let subject_test = if subjects.len() > 1 {
+        let sample_percentage = parse_or(&form.sample_percentage, settings.subject_test_sample_percentage)?;
+        let window_minutes = parse_or(&form.test_window_minutes, settings.subject_test_window_minutes)?;
+        let metric = if form.test_metric.is_empty() {
+            SubjectTestMetric::OpenRate
+        } else {
+            SubjectTestMetric::parse(&form.test_metric)?
+        };
+
+        Some(SubjectTest::parse(
+            subjects,
+            sample_percentage,
+            metric,
+            chrono::Duration::minutes(window_minutes as i64),
+        )?)
+    } else {
+        None
+    };
+
+    let local_send_time = match form.local_send_time.trim() {
+        "" => None,
+        time => Some(
+            chrono::NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|_| format!("{} is not a valid time, use the HH:MM format.", time))?
+        ),
+    };
+
+    NewIssue::parse(form.title, form.text_content, form.html_content, subject_test, local_send_time)
+}
+
+fn parse_or<T: FromStr>(value: &str, default: T) -> Result<T, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        Ok(default)
+    } else {
+        value.parse().map_err(|_| format!("{} is not a valid number.", value))
+    }
 }
 
 
 #[tracing::instrument(
     name = "Publish a neslietter issue",
-    skip(form, session, pool, email_client),
+    skip(form, session, pool, settings),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn publish_newsletter(
     form: web::Form<FormData>,
     session: TypedSession,
     pool: web::Data<PgPool>,
-    email_client: web::Data<EmailClient>,
+    settings: web::Data<NewsletterSettings>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = session.get_user_id().map_err(e500)?;
 
@@ -61,34 +102,71 @@ pub async fn publish_newsletter(
     }
         
     tracing::Span::current().record("user_id", &tracing::field::display(&user_id.unwrap()));
-    let subscribers = get_confirmed_subscribers(&pool).await.map_err(e500)?;
-    
-    for subscriber in subscribers {
-        match subscriber {
-            Ok(subscriber) => {
-                email_client
-                    .send_email(
-                    &subscriber.email,
-                    &form.title,
-                    &form.html_content,
-                    &form.text_content,
-                )
-                .await
-                .with_context(|| {
-                        format!("Failed to sned newsletter issue to {}", subscriber.email)
-                    }).map_err(e500)?;
-
-            }
-            Err(error) => {
-                tracing::warn!(
-                    error.cause_chain = ?error,
-                    error.message = %error,
-                    "Skipping a confirmed subscriber. Their stored contact details are invalid",
-                );
-
-            }
+
+    let new_issue = match parse_issue(form.0, &settings) {
+        Ok(issue) => issue,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/newsletters"));
         }
-    }
-    FlashMessage::info("The newsletter issues has been published!").send();
-    Ok(HttpResponse::Ok().finish())
+    };
+
+    let mut transaction = pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+
+    let newsletter_issue_id = insert_newsletter_issue(&mut transaction, &new_issue)
+        .await
+        .context("Failed to store newsletter issue details")
+        .map_err(e500)?;
+
+    let recipients = get_confirmed_recipients(&mut transaction)
+        .await
+        .context("Failed to retrieve the confirmed subscribers")
+        .map_err(e500)?;
+
+    let now = chrono::Utc::now();
+    let deliveries: Vec<QueuedDelivery> = match (&new_issue.subject_test, new_issue.local_send_time) {
+        // Only the samples are queued for now, the remainder gets the winner once the test window elapses
+        (Some(test), _) => {
+            let ids = recipients.into_iter().map(|r| r.id).collect();
+            test.assign_sample(ids, &mut thread_rng())
+                .into_iter()
+                .map(|(subscriber_id, variant)| QueuedDelivery { subscriber_id, variant, execute_after: now })
+                .collect()
+        }
+        // Each time zone cohort is held in the queue until its local send time
+        (None, Some(local_send_time)) => {
+            let default_time_zone = settings.default_time_zone();
+            recipients.into_iter()
+                .map(|r| {
+                    let time_zone = r.time_zone
+                        .and_then(|tz| SubscriberTimeZone::parse(&tz).ok())
+                        .unwrap_or(default_time_zone);
+                    QueuedDelivery {
+                        subscriber_id: r.id,
+                        variant: 0,
+                        execute_after: time_zone.next_occurrence(local_send_time, now),
+                    }
+                })
+                .collect()
+        }
+        (None, None) => recipients.into_iter()
+            .map(|r| QueuedDelivery { subscriber_id: r.id, variant: 0, execute_after: now })
+            .collect(),
+    };
+
+    enqueue_delivery_tasks(&mut transaction, newsletter_issue_id, &deliveries)
+        .await
+        .context("Failed to enqueue delivery tasks")
+        .map_err(e500)?;
+
+    transaction.commit()
+        .await
+        .context("Failed to commit SQL transaction to publish a newsletter issue")
+        .map_err(e500)?;
+
+    FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.").send();
+    Ok(see_other(&format!("/admin/newsletters/{}", newsletter_issue_id)))
 }
@@ -1,8 +1,65 @@
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
 use actix_web::http::header::ContentType;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::fmt::Write;
 
-pub async fn home() -> HttpResponse {
-    HttpResponse::Ok()
+use crate::domain::bot_protection::BotProtection;
+use crate::domain::referral::clean_referral_code;
+use crate::domain::subscriber_attribute::{AttributeDefinition, AttributeKind};
+use crate::helpers::subscriber_attributes::get_attribute_definitions;
+use crate::utils::e500;
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    /// Referral code of the share link the visitor followed.
+    #[serde(rename = "ref", default)]
+    referral_code: String,
+}
+
+pub async fn home(
+    parameters: web::Query<Parameters>,
+    bot_protection: web::Data<BotProtection>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let attribute_definitions = get_attribute_definitions(&pool).await.map_err(e500)?;
+    let page = include_str!("home.html")
+        .replace("{{attribute_fields}}", &attribute_fields(&attribute_definitions))
+        .replace("{{form_token}}", &bot_protection.issue_form_token(Utc::now()))
+        .replace("{{pow_difficulty}}", &bot_protection.pow_difficulty().to_string())
+        .replace("{{referral_code}}", &clean_referral_code(&parameters.referral_code).unwrap_or_default());
+
+    Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
-        .body(include_str!("home.html"))
+        .body(page))
+}
+
+/// One input per custom attribute, named after its key.
+fn attribute_fields(definitions: &[AttributeDefinition]) -> String {
+    let mut html = String::new();
+    for definition in definitions {
+        let key = htmlescape::encode_attribute(&definition.key);
+        let label = htmlescape::encode_minimal(&definition.label);
+        let required = if definition.required { " required" } else { "" };
+        let input = match definition.kind {
+            AttributeKind::Text => format!(r#"<input type="text" name="{}"{}>"#, key, required),
+            AttributeKind::Number => format!(r#"<input type="number" step="any" name="{}"{}>"#, key, required),
+            AttributeKind::Date => format!(r#"<input type="date" name="{}"{}>"#, key, required),
+            AttributeKind::Boolean => format!(r#"<input type="checkbox" name="{}"{}>"#, key, required),
+            AttributeKind::Enum => {
+                let mut options = String::from(r#"<option value="">-</option>"#);
+                for option in &definition.options {
+                    write!(
+                        options,
+                        r#"<option value="{}">{}</option>"#,
+                        htmlescape::encode_attribute(option),
+                        htmlescape::encode_minimal(option),
+                    ).unwrap();
+                }
+                format!(r#"<select name="{}"{}>{}</select>"#, key, required, options)
+            }
+        };
+        write!(html, "<label>{} {}</label>", label, input).unwrap();
+    }
+    html
 }
@@ -0,0 +1,2 @@
+pub mod route;
+pub mod data;
\ No newline at end of file
@@ -0,0 +1,106 @@
+use actix_web::http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType};
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::configuration::application_settings::HmacSecret;
+use crate::helpers::preferences::PREFERENCES_TOKEN_PURPOSE;
+use crate::helpers::signed_token::verify_subscriber_id;
+use crate::helpers::subscriber_data::{erase_subscriber, export_subscriber, Erasure};
+use crate::utils::e500;
+
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    token: String,
+}
+
+
+/// Hand a subscriber everything we hold about them, as a JSON download.
+#[tracing::instrument(name = "Export my data", skip_all)]
+pub async fn export_my_data(
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = match verify_subscriber_id(&hmac_secret.0, PREFERENCES_TOKEN_PURPOSE, &parameters.token) {
+        Some(id) => id,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+
+    match export_subscriber(&pool, subscriber_id).await.map_err(e500)? {
+        Some(export) => Ok(json_download(subscriber_id, &export)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+
+#[tracing::instrument(name = "Show the erasure confirmation page", skip_all)]
+pub async fn erase_my_data_form(
+    parameters: web::Query<Parameters>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> HttpResponse {
+    if verify_subscriber_id(&hmac_secret.0, PREFERENCES_TOKEN_PURPOSE, &parameters.token).is_none() {
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Erase my data</title>
+        </head>
+        <body>
+            <p>This unsubscribes you and permanently deletes everything we hold about you. It cannot be undone.</p>
+            <form action="/subscriptions/erase" method="post">
+                <input type="hidden" name="token" value="{}">
+                <button type="submit">Erase my data</button>
+            </form>
+        </body>
+        </html>"#,
+        htmlescape::encode_attribute(&parameters.token),
+    ))
+}
+
+
+#[tracing::instrument(name = "Erase my data", skip_all)]
+pub async fn erase_my_data(
+    form: web::Form<Parameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = match verify_subscriber_id(&hmac_secret.0, PREFERENCES_TOKEN_PURPOSE, &form.token) {
+        Some(id) => id,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+
+    // Erasing twice is not an error, the data is gone either way
+    let mut transaction = pool.begin().await.map_err(e500)?;
+    erase_subscriber(&mut transaction, subscriber_id, Erasure::Requested).await.map_err(e500)?;
+    transaction.commit().await.map_err(e500)?;
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Data erased</title>
+        </head>
+        <body>
+            <p>Your data has been erased and you will not hear from us again.</p>
+        </body>
+        </html>"#
+    ))
+}
+
+
+pub fn json_download<T: serde::Serialize>(subscriber_id: Uuid, body: &T) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("subscriber-{}.json", subscriber_id))],
+        })
+        .json(body)
+}
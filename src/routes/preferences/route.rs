@@ -0,0 +1,167 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::configuration::application_settings::HmacSecret;
+use crate::domain::referral::share_url;
+use crate::domain::subscriber_time_zone::SubscriberTimeZone;
+use crate::helpers::preferences::PREFERENCES_TOKEN_PURPOSE;
+use crate::helpers::referrals::get_referral_stats;
+use crate::helpers::signed_token::verify_subscriber_id;
+use crate::startup::run::ApplicationBaseUrl;
+use crate::utils::{e500, see_other};
+
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    token: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    token: String,
+    time_zone: String,
+}
+
+struct Preferences {
+    name: String,
+    time_zone: Option<String>,
+}
+
+
+#[tracing::instrument(name = "Show subscriber preferences", skip_all)]
+pub async fn preferences_form(
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = match verify_subscriber_id(&hmac_secret.0, PREFERENCES_TOKEN_PURPOSE, &parameters.token) {
+        Some(id) => id,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let preferences = match get_preferences(&pool, subscriber_id).await.map_err(e500)? {
+        Some(preferences) => preferences,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let referrals_html = match get_referral_stats(&pool, subscriber_id).await.map_err(e500)? {
+        Some(stats) => match &stats.referral_code {
+            Some(referral_code) => format!(
+                r#"<p>Share this link with friends who would enjoy the newsletter: <a href="{0}">{0}</a></p>
+                <p>{1} friend(s) joined through your link, {2} still have to confirm their subscription.</p>"#,
+                htmlescape::encode_minimal(&share_url(&base_url.0, referral_code)),
+                stats.credited,
+                stats.pending,
+            ),
+            None => "<p>Confirm your subscription to get a link to share with friends.</p>".into(),
+        },
+        None => String::new(),
+    };
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Your preferences</title>
+        </head>
+        <body>
+            {}
+            <p>Hi {}, newsletter issues sent at a local time are delivered according to this time zone.</p>
+            <form action="/subscriptions/preferences" method="post">
+                <input type="hidden" name="token" value="{}">
+                <label>Time zone
+                    <input type="text" placeholder="e.g. Europe/Berlin" name="time_zone" value="{}">
+                </label>
+                <br>
+                <button type="submit">Save</button>
+            </form>
+            <h2>Refer a friend</h2>
+            {}
+            <h2>Your data</h2>
+            <p><a href="/subscriptions/data?token={2}">Download everything we hold about you</a></p>
+            <p><a href="/subscriptions/erase?token={2}">Unsubscribe and erase your data</a></p>
+        </body>
+        </html>"#,
+        msg_html,
+        htmlescape::encode_minimal(&preferences.name),
+        htmlescape::encode_attribute(&parameters.token),
+        htmlescape::encode_attribute(preferences.time_zone.as_deref().unwrap_or_default()),
+        referrals_html,
+    )))
+}
+
+
+#[tracing::instrument(name = "Update subscriber preferences", skip_all)]
+pub async fn update_preferences(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = match verify_subscriber_id(&hmac_secret.0, PREFERENCES_TOKEN_PURPOSE, &form.token) {
+        Some(id) => id,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let location = format!("/subscriptions/preferences?token={}", form.token);
+
+    let time_zone = match form.time_zone.trim() {
+        "" => None,
+        time_zone => match SubscriberTimeZone::parse(time_zone) {
+            Ok(time_zone) => Some(time_zone),
+            Err(e) => {
+                FlashMessage::error(e).send();
+                return Ok(see_other(&location));
+            }
+        },
+    };
+
+    store_time_zone(&pool, subscriber_id, time_zone).await.map_err(e500)?;
+
+    FlashMessage::info("Your preferences have been updated.").send();
+    Ok(see_other(&location))
+}
+
+
+#[tracing::instrument(name = "Get subscriber preferences", skip(pool))]
+async fn get_preferences(pool: &PgPool, subscriber_id: Uuid) -> Result<Option<Preferences>, anyhow::Error> {
+    let preferences = sqlx::query_as!(
+        Preferences,
+        r#"SELECT name, time_zone FROM subscriptions WHERE id = $1"#,
+        subscriber_id,
+    )
+        .fetch_optional(pool)
+        .await
+        .context("Failed to retrieve the preferences of a subscriber.")?;
+
+    Ok(preferences)
+}
+
+
+#[tracing::instrument(name = "Store subscriber time zone", skip(pool))]
+async fn store_time_zone(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    time_zone: Option<SubscriberTimeZone>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET time_zone = $2 WHERE id = $1"#,
+        subscriber_id,
+        time_zone.map(|tz| tz.name()),
+    )
+        .execute(pool)
+        .await
+        .context("Failed to update the time zone of a subscriber.")?;
+
+    Ok(())
+}
@@ -1,2 +1,3 @@
-mod helpers;
-pub mod route;
\ No newline at end of file
+pub mod helpers;
+pub mod route;
+pub mod resend;
\ No newline at end of file
@@ -1,26 +1,31 @@
 use tracing;
 use crate::email::email_client::EmailClient;
-use crate::domain::new_subscriber::NewSubscriber;
+use crate::email::message_options::MessageOptions;
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::errors::send_email_error::SendEmailError;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(email_client, new_subscriber, subscription_token)
+    skip(email_client, recipient, subscription_token)
 )]
 pub async fn send_confirmation_email(
     email_client: &EmailClient, 
-    new_subscriber: NewSubscriber,
+    recipient: &SubscriberEmail,
     base_url: &str,
     subscription_token: &str
-) -> Result<(), reqwest::Error> {
+) -> Result<(), SendEmailError> {
     let confirmation_link = format!("{}/subscriptions/confirm?subscription_token={}", base_url, subscription_token);
     let html_body =     &format!("Welcome to our newsletter!<br />`
         Click <a href=\"{}\">here</a> to confirm your subscription.", confirmation_link);
     let plain_body = &format!("Welcome to our newsletter!\nVisit {} to confirm your subscription.", confirmation_link);
 
+    let mut options = MessageOptions::transactional();
+    options.tag = Some("subscription-confirmation".into());
+
     email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body).await
+        .send_email(recipient, "Welcome!", html_body, plain_body, &[], &options).await
 }
 
 
@@ -0,0 +1,126 @@
+use actix_web::HttpRequest;
+use actix_web::http::header::ContentType;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::configuration::subscription_settings::SubscriptionSettings;
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::email::email_client::EmailClient;
+use crate::errors::subscribe_error::SubscribeError;
+use crate::rate_limiting::limiter::RateLimiter;
+use crate::routes::prelude::*;
+use crate::routes::subscriptions::helpers;
+use crate::routes::subscriptions::route::issue_token;
+use crate::startup::run::ApplicationBaseUrl;
+
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+}
+
+
+/// Send a new confirmation link to a pending subscriber.
+///
+/// The response is the same whether or not the address is pending, so that it does not reveal who is on the list.
+#[tracing::instrument(
+    name = "Resend a confirmation email",
+    skip(form, request, pool, email_client, base_url, settings, rate_limiter),
+    fields(subscriber_email = %form.email)
+)]
+pub async fn resend_confirmation(
+    form: web::Form<FormData>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<SubscriptionSettings>,
+    rate_limiter: web::Data<RateLimiter>,
+) -> Result<HttpResponse, SubscribeError> {
+    let email = SubscriberEmail::parse(form.0.email).map_err(SubscribeError::ValidationError)?;
+    rate_limiter.check_target(request.path(), email.normalized()).await?;
+
+    let mut transaction = pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let subscriber_id = get_pending_subscriber_id(&mut transaction, &email)
+        .await.context("Failed to look up the subscriber in the database.")?;
+
+    if let Some(subscriber_id) = subscriber_id {
+        let cooling_down = sent_recently(&mut transaction, subscriber_id, settings.resend_cooldown())
+            .await.context("Failed to look up the latest confirmation token.")?;
+
+        if cooling_down {
+            tracing::info!("A confirmation email was sent recently, not sending another one.");
+        } else {
+            let subscription_token = issue_token(&mut transaction, subscriber_id, settings.token_ttl()).await?;
+            transaction.commit()
+                .await.context("Failed to commit SQL transaction to store a new confirmation token")?;
+
+            // An error page would tell that the address is pending, the subscriber can ask again instead
+            if let Err(e) = helpers::send_confirmation_email(&email_client, &email, base_url.0.as_str(), &subscription_token).await {
+                tracing::error!(error.cause_chain = ?e, "Failed to resend a confirmation email.");
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+        <html lang="en">
+        <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Confirmation email</title>
+        </head>
+        <body>
+            <p>If this address is waiting for confirmation, a new link is on its way. Check your spam folder too.</p>
+            <p><a href="/">&lt;- Back</a></p>
+        </body>
+        </html>"#
+    ))
+}
+
+
+#[tracing::instrument(name = "Get pending subscriber by email", skip(transaction, email))]
+async fn get_pending_subscriber_id(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &SubscriberEmail,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let subscriber = sqlx::query!(
+        r#"
+        SELECT id FROM subscriptions
+        WHERE lower(normalized_email) = lower($1) AND status = 'pending_confirmation'
+        FOR UPDATE
+        "#,
+        email.normalized(),
+    )
+        .fetch_optional(transaction)
+        .await?;
+
+    Ok(subscriber.map(|s| s.id))
+}
+
+
+/// Whether a confirmation token was issued to the subscriber within the cooldown.
+#[tracing::instrument(name = "Check the resend cooldown", skip(transaction))]
+async fn sent_recently(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    cooldown: chrono::Duration,
+) -> Result<bool, sqlx::Error> {
+    let recent = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM subscription_tokens
+            WHERE subscriber_id = $1 AND created_at > $2
+        ) AS "recent!"
+        "#,
+        subscriber_id,
+        chrono::Utc::now() - cooldown,
+    )
+        .fetch_one(transaction)
+        .await?;
+
+    Ok(recent.recent)
+}
@@ -1,5 +1,7 @@
 use crate::{routes::{prelude::*}, domain::subscriber_email::SubscriberEmail, startup::run::ApplicationBaseUrl};
+use actix_web::HttpRequest;
 use chrono::Utc;
+use std::collections::HashMap;
 use std::convert::{TryInto, TryFrom};
 use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
@@ -8,12 +10,26 @@ use anyhow::{Context, Result};
 
 use crate::domain::{
     subscriber_name::SubscriberName,
+    subscriber_time_zone::SubscriberTimeZone,
     new_subscriber::NewSubscriber,
 };
+use crate::configuration::subscription_settings::SubscriptionSettings;
+use crate::domain::bot_protection::{BotProtection, BotRejection, SignupAttempt};
+use crate::domain::email_policy::EmailPolicy;
+use crate::domain::referral::clean_referral_code;
+use crate::domain::subscriber_attribute::parse_attribute_values;
+use crate::domain::subscription_status::SubscriptionStatus;
 use crate::email::email_client::EmailClient;
+use crate::rate_limiting::limiter::RateLimiter;
+use crate::helpers::client_ip::client_ip;
+use crate::helpers::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::helpers::referrals::record_referrer;
+use crate::helpers::subscriber_attributes::{get_attribute_definitions, save_attribute_values};
+use crate::helpers::subscription_status::{change_status, record_initial_status};
 use crate::routes::subscriptions::helpers;
 use crate::errors::store_token_error::StoreTokenError;
 use crate::errors::subscribe_error::SubscribeError;
+use crate::utils::is_unique_violation;
 
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -22,7 +38,11 @@ impl TryFrom<FormData> for NewSubscriber {
     fn try_from(value: FormData) -> Result<Self, Self::Error> {
         let name = SubscriberName::parse(value.name)?;
         let email = SubscriberEmail::parse(value.email)?;
-        Ok(Self {email, name})
+        let time_zone = match value.time_zone.trim() {
+            "" => None,
+            time_zone => Some(SubscriberTimeZone::parse(time_zone)?),
+        };
+        Ok(Self {email, name, time_zone, attributes: Vec::new()})
     }
 }
 
@@ -32,43 +52,137 @@ impl TryFrom<FormData> for NewSubscriber {
 pub struct FormData {
     pub email: String,
     pub name: String,
+    /// IANA time zone, filled in by the browser on the signup form.
+    #[serde(default)]
+    pub time_zone: String,
+    /// Identifier of the form the subscription came from, kept in the consent trail.
+    #[serde(default)]
+    pub source: String,
+    /// Honeypot, hidden from people.
+    #[serde(default)]
+    pub website: String,
+    /// Signed when the form was served, see `BotProtection::issue_form_token`.
+    #[serde(default)]
+    pub form_token: String,
+    #[serde(default)]
+    pub pow_nonce: String,
+    #[serde(default)]
+    pub captcha_response: String,
+    /// From the share link of the subscriber who referred this one.
+    #[serde(default)]
+    pub referral_code: String,
+    /// Custom attributes, submitted under their key.
+    #[serde(flatten)]
+    pub attributes: HashMap<String, String>,
 }
 
 
 #[tracing::instrument (
     name = "Adding a new subscriber"
-    skip(form, pool, email_client, base_url),
+    skip(form, request, pool, email_client, base_url, settings, email_policy, bot_protection, rate_limiter),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
     )
 )]
+#[allow(clippy::too_many_arguments)]
 pub async fn subscribe(
     form: web::Form<FormData>, 
+    request: HttpRequest,
     pool: web::Data<PgPool>, 
     email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<SubscriptionSettings>,
+    email_policy: web::Data<EmailPolicy>,
+    bot_protection: web::Data<BotProtection>,
+    rate_limiter: web::Data<RateLimiter>,
 ) -> Result<HttpResponse, SubscribeError> {
+    let remote_ip = client_ip(&request).map(|ip| ip.to_string());
+    let attempt = SignupAttempt {
+        honeypot: &form.website,
+        email: &form.email,
+        form_token: &form.form_token,
+        pow_nonce: &form.pow_nonce,
+        captcha_response: &form.captcha_response,
+        remote_ip: remote_ip.as_deref(),
+    };
+    match bot_protection.check(&attempt, Utc::now()).await {
+        Ok(()) => {}
+        // Bots are not told they were caught, no email is sent either way
+        Err(BotRejection::Trapped) => {
+            tracing::warn!("A signup filled in the honeypot field and was dropped.");
+            return Ok(HttpResponse::Ok().finish());
+        }
+        Err(BotRejection::Rejected(e)) => return Err(SubscribeError::ValidationError(e)),
+    }
+
+    let token_ttl = settings.token_ttl();
+    let consent = ConsentContext::from_request(&request, &form.source, &settings.consent_version);
+
+    let mut form = form.into_inner();
+    let submitted_attributes = std::mem::take(&mut form.attributes);
+    let referral_code = clean_referral_code(&form.referral_code);
+    let mut new_subscriber: NewSubscriber = form.try_into().map_err( SubscribeError::ValidationError)?;
+    let attribute_definitions = get_attribute_definitions(&pool)
+        .await.context("Failed to load the subscriber attribute definitions.")?;
+    new_subscriber.attributes = parse_attribute_values(&attribute_definitions, &submitted_attributes)
+        .map_err(SubscribeError::ValidationError)?;
+    rate_limiter.check_target(request.path(), new_subscriber.email.normalized()).await?;
+    email_policy.check(&new_subscriber.email).await.map_err(SubscribeError::ValidationError)?;
 
     let mut transaction = pool.begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
+    let subscriber = check_subscriber(&mut transaction, &new_subscriber)
+        .await.context("Failed to look up the subscriber in the database.")?;
 
-    let new_subscriber = form.0.try_into().map_err( |e| SubscribeError::ValidationError(e))?;
+    let subscriber_id = match subscriber {
+        None => {
+            let subscriber_id = match insert_subscriber(&mut transaction, &new_subscriber).await {
+                Ok(subscriber_id) => subscriber_id,
+                // A concurrent signup with the same address got in first and sends the confirmation email
+                Err(e) if is_unique_violation(&e) => return Ok(HttpResponse::Ok().finish()),
+                Err(e) => Err(e).context("Failed to insert new subscriber in the database.")?,
+            };
+            // Only new subscribers count as referrals, the credit comes once they confirm
+            if let Some(referral_code) = &referral_code {
+                record_referrer(&mut transaction, subscriber_id, referral_code)
+                    .await.context("Failed to record the referrer of a new subscriber.")?;
+            }
+            subscriber_id
+        }
+        Some((subscriber_id, SubscriptionStatus::PendingConfirmation)) => subscriber_id,
+        Some((subscriber_id, SubscriptionStatus::Unsubscribed)) => {
+            resubscribe(&mut transaction, subscriber_id, &new_subscriber)
+                .await.context("Failed to move an unsubscribed subscriber back to pending confirmation.")?;
+            subscriber_id
+        }
+        // Same response as a new subscription, so that the endpoint does not reveal who is on the list.
+        // Bounced, complained about and cleaned addresses stay off the list.
+        Some(_) => return Ok(HttpResponse::Ok().finish()),
+    };
 
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
-        .await.context("Failed to insert new subscriber in the database.")?;
+    // Pending subscribers signing up again may correct their answers
+    save_attribute_values(&mut transaction, subscriber_id, &new_subscriber.attributes)
+        .await.context("Failed to store the attributes of a new subscriber.")?;
 
-    let subscription_token = helpers::generate_subscription_token();
-    store_token(&mut transaction, subscriber_id, &subscription_token)
-        .await.context("Failed to store the confirmation token for a new subscriber.")?;
+    // Pending subscribers keep their current link
+    let pending_token = get_token(&mut transaction, subscriber_id)
+        .await.context("Failed to retrieve the confirmation token of a pending subscriber.")?;
+    let subscription_token = match pending_token {
+        Some(token) => token,
+        None => issue_token(&mut transaction, subscriber_id, token_ttl).await?,
+    };
+
+    record_consent_event(&mut transaction, subscriber_id, ConsentAction::Subscribe, &consent)
+        .await.context("Failed to record the consent of a new subscriber.")?;
 
     transaction.commit()
         .await.context("Failed to commit SQL transaction to store a new subscriber")?;
 
     helpers::send_confirmation_email(
         &email_client, 
-        new_subscriber, 
+        &new_subscriber.email, 
         base_url.0.as_str(), 
         &subscription_token)
     .await.context("Failed to send a confirmation email")?;
@@ -87,25 +201,94 @@ insert_subscriber(
     new_subscriber: &NewSubscriber
 ) -> Result<Uuid, sqlx::Error> {
     let user = sqlx::query!(r#"
-        INSERT INTO subscriptions (email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, 'pending_confirmation') RETURNING id
+        INSERT INTO subscriptions (email, normalized_email, name, subscribed_at, status, time_zone)
+        VALUES ($1, $2, $3, $4, $5, $6) RETURNING id
         "#,
         // Uuid::new_v4(), 
         new_subscriber.email.as_ref(),
+        new_subscriber.email.normalized(),
         new_subscriber.name.as_ref(),
-        Utc::now()
+        Utc::now(),
+        SubscriptionStatus::PendingConfirmation as SubscriptionStatus,
+        new_subscriber.time_zone.map(|tz| tz.name()),
     )
-        .fetch_one(transaction)
+        .fetch_one(&mut *transaction)
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e); 
             e
         })?;
+    record_initial_status(transaction, user.id, SubscriptionStatus::PendingConfirmation, "signup").await?;
 
     Ok(user.id)
 }
 
 
+pub async fn issue_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    ttl: chrono::Duration,
+) -> Result<String, anyhow::Error> {
+    let subscription_token = helpers::generate_subscription_token();
+    store_token(transaction, subscriber_id, &subscription_token, Utc::now() + ttl)
+        .await.context("Failed to store the confirmation token for a new subscriber.")?;
+
+    Ok(subscription_token)
+}
+
+
+#[tracing::instrument(
+    name = "Get the pending subscription token of a subscriber",
+    skip(transaction)
+)]
+pub async fn get_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let token = sqlx::query!(
+        r#"
+        SELECT subscription_token FROM subscription_tokens
+        WHERE subscriber_id = $1 AND expires_at > now() AND consumed_at IS NULL
+        ORDER BY expires_at DESC
+        LIMIT 1
+        "#,
+        subscriber_id,
+    )
+        .fetch_optional(transaction)
+        .await?;
+
+    Ok(token.map(|t| t.subscription_token))
+}
+
+
+#[tracing::instrument(
+    name = "Move an unsubscribed subscriber back to pending confirmation",
+    skip(transaction, new_subscriber)
+)]
+pub async fn resubscribe(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    new_subscriber: &NewSubscriber,
+) -> Result<(), anyhow::Error> {
+    change_status(transaction, subscriber_id, SubscriptionStatus::PendingConfirmation, "signup").await?;
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET name = $2, time_zone = $3, subscribed_at = $4
+        WHERE id = $1
+        "#,
+        subscriber_id,
+        new_subscriber.name.as_ref(),
+        new_subscriber.time_zone.map(|tz| tz.name()),
+        Utc::now(),
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
+
+
 #[tracing::instrument(
     name = "Store subscription token in the database"
     skip(subscription_token, transaction)
@@ -113,15 +296,17 @@ insert_subscriber(
 pub async fn store_token(
     transaction: &mut Transaction<'_, Postgres>, 
     subscriber_id: Uuid, 
-    subscription_token: &str
+    subscription_token: &str,
+    expires_at: chrono::DateTime<Utc>,
 ) -> Result<(), StoreTokenError> {
     sqlx::query!(
         r#"
-    INSERT INTO subscription_tokens (subscription_token, subscriber_id)
-    VALUES ($1, $2)
+    INSERT INTO subscription_tokens (subscription_token, subscriber_id, created_at, expires_at)
+    VALUES ($1, $2, now(), $3)
         "#,
         subscription_token,
         subscriber_id,
+        expires_at,
     )
         .execute(transaction)
         .await
@@ -133,44 +318,20 @@ pub async fn store_token(
         Ok(())
 }
 
-enum SubscriberStatus {
-    Pending,
-    Confirmed,
-    NotExist
-}
-
-impl SubscriberStatus {
-    pub fn new(state: &str) -> Self {
-        match state {
-            "pending_confirmation" => SubscriberStatus::Pending,
-            "confirmed" => SubscriberStatus::Confirmed,
-            _ => SubscriberStatus::NotExist
-        }
-    }
-}
-
-pub struct SubscriberState {
-    status: SubscriberStatus,
-    id: Option<Uuid>,
-}
-
 #[tracing::instrument(
     name = "Check if subscriber email already exists in the database"
     skip(transaction, new_subscriber)
 )]
 pub async fn check_subscriber(
     transaction: &mut Transaction<'_, Postgres>, 
-    new_subscriber: &NewSubscriber) -> Result<SubscriberState, sqlx::Error> {
+    new_subscriber: &NewSubscriber) -> Result<Option<(Uuid, SubscriptionStatus)>, sqlx::Error> {
     let user = sqlx::query!(
-        r#"SELECT email, id, status FROM subscriptions WHERE email = $1"#, 
-        new_subscriber.email.as_ref())
-        .fetch_one(transaction).await.map_err(|e| {
+        r#"SELECT id, status AS "status: SubscriptionStatus" FROM subscriptions WHERE lower(normalized_email) = lower($1)"#, 
+        new_subscriber.email.normalized())
+        .fetch_optional(transaction).await.map_err(|e| {
             tracing::error!("Failed to execute query {}", e); 
             e
         })?;
 
-    Ok(SubscriberState {
-        status: SubscriberStatus::new(user.status.as_str()),
-        id: Some(user.id)
-    })
+    Ok(user.map(|user| (user.id, user.status)))
 }
\ No newline at end of file
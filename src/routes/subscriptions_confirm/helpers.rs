@@ -1,18 +1,60 @@
-use sqlx::{PgPool};
+use anyhow::Context;
+use secrecy::Secret;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::domain::referral::{share_url, ReferralReward};
+use crate::domain::subscriber_email::SubscriberEmail;
+use crate::domain::subscription_status::SubscriptionStatus;
+use crate::domain::welcome_email::WelcomeEmail;
+use crate::email::email_client::EmailClient;
+use crate::email::footer::with_preferences_footer;
+use crate::email::message_options::MessageOptions;
+use crate::errors::status_change_error::StatusChangeError;
+use crate::helpers::preferences::preferences_url;
+use crate::helpers::referrals::get_referral_stats;
+use crate::helpers::subscriber_attributes::get_merge_values;
+use crate::helpers::subscription_status::change_status;
+use crate::helpers::welcome_email::get_latest_issue;
+
+pub enum TokenStatus {
+    Valid(Uuid),
+    /// The subscriber behind the token confirmed already, possibly with another token.
+    AlreadyConfirmed,
+    Expired,
+    NotFound,
+}
+
 #[tracing::instrument(
     name = "Mark subscriber as confirmed",
-    skip(subscriber_id, pool)
+    skip(subscriber_id, transaction)
 )]
 pub async fn confirm_subscriber(
-    pool: &PgPool, subscriber_id: Uuid
+    transaction: &mut Transaction<'_, Postgres>, subscriber_id: Uuid
+) -> Result<(), StatusChangeError> {
+    change_status(transaction, subscriber_id, SubscriptionStatus::Confirmed, "confirmation")
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to confirm a subscriber: {:#?}", e);
+            e
+        })?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(
+    name = "Mark subscription token as consumed",
+    skip(subscription_token, transaction)
+)]
+pub async fn consume_token(
+    transaction: &mut Transaction<'_, Postgres>, subscription_token: &str
 ) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
-        subscriber_id,
+        r#"UPDATE subscription_tokens SET consumed_at = now() WHERE subscription_token = $1"#,
+        subscription_token,
     )
-        .execute(pool)
+        .execute(transaction)
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:#?}", e);
@@ -30,9 +72,19 @@ pub async fn confirm_subscriber(
 pub async fn get_subscriber_id_from_token(
     pool: &PgPool, 
     subscription_token: &str
-) -> Result<Option<Uuid>, sqlx::Error> {
+) -> Result<TokenStatus, sqlx::Error> {
     let result = sqlx::query!(
-        r#"SELECT subscriber_id FROM subscription_tokens WHERE subscription_token = $1"#,
+        r#"
+        SELECT
+            t.subscriber_id,
+            t.expires_at <= now() AS "expired!",
+            t.consumed_at IS NOT NULL OR s.status = 'confirmed' AS "confirmed!"
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE t.subscription_token = $1
+            -- Links sent before the subscriber left the list do not bring them back
+            AND s.status IN ('pending_confirmation', 'confirmed')
+        "#,
         subscription_token
     )
         .fetch_optional(pool)
@@ -41,5 +93,93 @@ pub async fn get_subscriber_id_from_token(
             tracing::error!("Failed to execute query: {:?}", e); e
         })?;
 
-    Ok(result.map(|r| r.subscriber_id))
-}
\ No newline at end of file
+    Ok(match result {
+        None => TokenStatus::NotFound,
+        Some(r) if r.confirmed => TokenStatus::AlreadyConfirmed,
+        Some(r) if r.expired => TokenStatus::Expired,
+        Some(r) => TokenStatus::Valid(r.subscriber_id),
+    })
+}
+
+
+#[tracing::instrument(
+    name = "Send the welcome email to a new subscriber",
+    skip(pool, email_client, welcome_email, base_url, hmac_secret)
+)]
+pub async fn send_welcome_email(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    subscriber_id: Uuid,
+    welcome_email: &WelcomeEmail,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<(), anyhow::Error> {
+    let merge_values = get_merge_values(pool, subscriber_id)
+        .await.context("Failed to get the merge values of a new subscriber.")?;
+    let recipient = SubscriberEmail::parse(merge_values.get("email").cloned().unwrap_or_default())
+        .map_err(anyhow::Error::msg)?;
+    let latest_issue = if welcome_email.include_latest_issue {
+        get_latest_issue(pool).await.context("Failed to get the latest issue.")?
+    } else {
+        None
+    };
+
+    let email = welcome_email.render(&merge_values, latest_issue.as_ref());
+    let (html_content, text_content) = with_preferences_footer(
+        &email.html_content,
+        &email.text_content,
+        &preferences_url(base_url, hmac_secret, subscriber_id),
+    );
+    let mut options = MessageOptions::transactional();
+    options.tag = Some("welcome".into());
+
+    email_client
+        .send_email(&recipient, &email.subject, &html_content, &text_content, &[], &options)
+        .await
+        .context("Failed to send the welcome email.")?;
+
+    Ok(())
+}
+
+
+#[tracing::instrument(
+    name = "Send a referral reward",
+    skip(pool, email_client, reward, base_url, hmac_secret),
+    fields(milestone = reward.milestone)
+)]
+pub async fn send_referral_reward(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    referrer_id: Uuid,
+    reward: &ReferralReward,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<(), anyhow::Error> {
+    let mut merge_values = get_merge_values(pool, referrer_id)
+        .await.context("Failed to get the merge values of a referrer.")?;
+    let stats = get_referral_stats(pool, referrer_id)
+        .await.context("Failed to get the referral statistics of a referrer.")?
+        .ok_or_else(|| anyhow::anyhow!("The referrer does not exist."))?;
+    merge_values.insert("referral_count".into(), stats.credited.to_string());
+    if let Some(referral_code) = &stats.referral_code {
+        merge_values.insert("share_url".into(), share_url(base_url, referral_code));
+    }
+    let recipient = SubscriberEmail::parse(merge_values.get("email").cloned().unwrap_or_default())
+        .map_err(anyhow::Error::msg)?;
+
+    let email = reward.render(&merge_values);
+    let (html_content, text_content) = with_preferences_footer(
+        &email.html_content,
+        &email.text_content,
+        &preferences_url(base_url, hmac_secret, referrer_id),
+    );
+    let mut options = MessageOptions::transactional();
+    options.tag = Some("referral-reward".into());
+
+    email_client
+        .send_email(&recipient, &email.subject, &html_content, &text_content, &[], &options)
+        .await
+        .context("Failed to send a referral reward.")?;
+
+    Ok(())
+}
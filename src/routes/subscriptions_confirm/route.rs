@@ -1,33 +1,218 @@
-use actix_web::{HttpResponse, web};
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, web};
 use sqlx::PgPool;
 
-use crate::routes::subscriptions_confirm::helpers;
+use crate::configuration::application_settings::HmacSecret;
+use crate::configuration::subscription_settings::SubscriptionSettings;
+use crate::domain::automation::SequenceTrigger;
+use crate::email::email_client::EmailClient;
+use crate::errors::status_change_error::StatusChangeError;
+use crate::helpers::automation::{enroll_in_tag_sequences, enroll_subscriber};
+use crate::helpers::consent::{record_consent_event, ConsentAction, ConsentContext};
+use crate::helpers::referrals::{
+    assign_referral_code, claim_referral_rewards, credit_referral, release_referral_reward,
+};
+use crate::helpers::welcome_email::{claim_welcome_email, get_welcome_email, release_welcome_email};
+use crate::routes::subscriptions_confirm::helpers::{self, TokenStatus};
+use crate::startup::run::ApplicationBaseUrl;
 
 #[derive(serde::Deserialize)]
 pub struct Parameters {
     subscription_token: String
 }
 
+
+/// Show a button that confirms the subscription.
+///
+/// Mail scanners prefetch every link in an email, so following the link alone must not confirm anything.
 #[tracing::instrument(
-    name = "Confirm a pending subscriber",
+    name = "Show the subscription confirmation page",
     skip(parameters, pool)
 )]
-pub async fn confirm(parameters: web::Query<Parameters>, pool: web::Data<PgPool>) -> HttpResponse {
-    let id = match helpers::get_subscriber_id_from_token(&pool, &parameters.subscription_token).await {
-        Ok(id) => id,
-        Err(_) => {
-            return HttpResponse::InternalServerError().finish()
-        }
+pub async fn confirm_form(parameters: web::Query<Parameters>, pool: web::Data<PgPool>) -> HttpResponse {
+    let status = match helpers::get_subscriber_id_from_token(&pool, &parameters.subscription_token).await {
+        Ok(status) => status,
+        Err(e) => return internal_error(&e, "Failed to look up the confirmation token."),
     };
 
-    match id {
-        None => HttpResponse::Unauthorized().finish(),
-        Some(subscriber_id) => {
-            if helpers::confirm_subscriber(&pool, subscriber_id).await.is_err() {
-                return HttpResponse::InternalServerError().finish();
+    match status {
+        TokenStatus::Valid(_) => page(
+            StatusCode::OK,
+            "Confirm your subscription",
+            &format!(
+                r#"<p>One last step: confirm that you want to receive our newsletter.</p>
+                <form action="/subscriptions/confirm" method="post">
+                    <input type="hidden" name="subscription_token" value="{}">
+                    <button type="submit">Confirm my subscription</button>
+                </form>"#,
+                htmlescape::encode_attribute(&parameters.subscription_token),
+            ),
+        ),
+        status => outcome_page(status),
+    }
+}
+
+
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(form, request, pool, settings, email_client, base_url, hmac_secret)
+)]
+pub async fn confirm(
+    form: web::Form<Parameters>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> HttpResponse {
+    let status = match helpers::get_subscriber_id_from_token(&pool, &form.subscription_token).await {
+        Ok(status) => status,
+        Err(e) => return internal_error(&e, "Failed to look up the confirmation token."),
+    };
+
+    if let TokenStatus::Valid(subscriber_id) = status {
+        let consent = ConsentContext::from_request(&request, "confirmation-page", &settings.consent_version);
+        let welcome_email = match get_welcome_email(&pool).await {
+            Ok(welcome_email) => welcome_email,
+            Err(e) => return internal_error(&e, "Failed to get the welcome email."),
+        };
+        let mut transaction = match pool.begin().await {
+            Ok(transaction) => transaction,
+            Err(e) => return internal_error(&e, "Failed to acquire a Postgres connection from the pool."),
+        };
+        match helpers::confirm_subscriber(&mut transaction, subscriber_id).await {
+            Ok(()) => {}
+            // Another request with the same token got there first
+            Err(StatusChangeError::NotAllowed(_)) => return outcome_page(TokenStatus::AlreadyConfirmed),
+            Err(e) => return internal_error(&e, "Failed to confirm the subscriber."),
+        }
+        if let Err(e) = helpers::consume_token(&mut transaction, &form.subscription_token).await {
+            return internal_error(&e, "Failed to mark the confirmation token as consumed.");
+        }
+        if let Err(e) = record_consent_event(&mut transaction, subscriber_id, ConsentAction::Confirm, &consent).await {
+            return internal_error(&e, "Failed to record the consent of the subscriber.");
+        }
+        // Enrolled and credited in the same transaction, a failure leaves the link valid for another try
+        if let Err(e) = enroll_subscriber(&mut transaction, subscriber_id, &SequenceTrigger::Confirmation).await {
+            return internal_error(&e, "Failed to enroll the subscriber in the confirmation sequences.");
+        }
+        if let Err(e) = enroll_in_tag_sequences(&mut transaction, subscriber_id).await {
+            return internal_error(&e, "Failed to enroll the subscriber in the sequences of their tags.");
+        }
+        if let Err(e) = assign_referral_code(&mut transaction, subscriber_id).await {
+            return internal_error(&e, "Failed to assign a referral code to the subscriber.");
+        }
+        // The rewards are claimed with the credit, so that each milestone is rewarded once
+        let referral_rewards = match credit_referral(&mut transaction, subscriber_id).await {
+            Ok(Some(referrer_id)) => match claim_referral_rewards(&mut transaction, referrer_id).await {
+                Ok(rewards) => Some((referrer_id, rewards)),
+                Err(e) => return internal_error(&e, "Failed to claim the referral rewards of the referrer."),
+            },
+            Ok(None) => None,
+            Err(e) => return internal_error(&e, "Failed to credit the referrer of the subscriber."),
+        };
+        // Claimed in the same transaction, so that confirming repeatedly never sends it twice
+        let send_welcome = if welcome_email.enabled {
+            match claim_welcome_email(&mut transaction, subscriber_id).await {
+                Ok(claimed) => claimed,
+                Err(e) => return internal_error(&e, "Failed to claim the welcome email."),
             }
-            
-            return HttpResponse::Ok().finish()
+        } else {
+            false
+        };
+        if let Err(e) = transaction.commit().await {
+            return internal_error(&e, "Failed to commit the confirmation.");
         }
+
+        if send_welcome {
+            if let Err(e) = helpers::send_welcome_email(
+                &pool, &email_client, subscriber_id, &welcome_email, &base_url.0, &hmac_secret.0,
+            ).await {
+                // The subscription is confirmed all the same
+                tracing::error!(error.cause_chain = ?e, "Failed to send the welcome email.");
+                if let Err(e) = release_welcome_email(&pool, subscriber_id).await {
+                    tracing::error!(error.cause_chain = ?e, "Failed to release the welcome email.");
+                }
+            }
+        }
+
+        if let Some((referrer_id, rewards)) = referral_rewards {
+            for reward in &rewards {
+                if let Err(e) = helpers::send_referral_reward(
+                    &pool, &email_client, referrer_id, reward, &base_url.0, &hmac_secret.0,
+                ).await {
+                    tracing::error!(error.cause_chain = ?e, "Failed to send a referral reward.");
+                    if let Err(e) = release_referral_reward(&pool, referrer_id, reward.milestone).await {
+                        tracing::error!(error.cause_chain = ?e, "Failed to release a referral reward.");
+                    }
+                }
+            }
+        }
+
+        return page(
+            StatusCode::OK,
+            "Subscription confirmed",
+            "<p>Thanks, your subscription is confirmed! The next issue will land in your inbox.</p>",
+        );
+    }
+
+    outcome_page(status)
+}
+
+
+fn outcome_page(status: TokenStatus) -> HttpResponse {
+    match status {
+        // Confirming twice is harmless, the second attempt gets the same good news
+        TokenStatus::AlreadyConfirmed | TokenStatus::Valid(_) => page(
+            StatusCode::OK,
+            "Subscription confirmed",
+            "<p>Your subscription is already confirmed, there is nothing else to do.</p>",
+        ),
+        // Expired links get a way to ask for a new one instead of a bare error
+        TokenStatus::Expired => page(
+            StatusCode::GONE,
+            "Confirmation link expired",
+            r#"<p>This confirmation link has expired. Fill in your email address to receive a new one.</p>
+            <form action="/subscriptions/resend" method="post">
+                <label>Email
+                    <input type="email" placeholder="you@example.com" name="email">
+                </label>
+                <button type="submit">Send me a new link</button>
+            </form>"#,
+        ),
+        TokenStatus::NotFound => page(
+            StatusCode::UNAUTHORIZED,
+            "Invalid confirmation link",
+            r#"<p>This confirmation link is not valid. Make sure you copied the whole link from the email.</p>
+            <p><a href="/">Subscribe again</a></p>"#,
+        ),
     }
-}
\ No newline at end of file
+}
+
+
+fn internal_error(e: &dyn std::error::Error, message: &str) -> HttpResponse {
+    tracing::error!(error.cause_chain = ?e, "{}", message);
+    HttpResponse::InternalServerError().finish()
+}
+
+
+fn page(status: StatusCode, title: &str, body: &str) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+            <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>{}</title>
+            </head>
+            <body>
+                {}
+            </body>
+            </html>"#,
+            title,
+            body,
+        ))
+}
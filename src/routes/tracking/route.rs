@@ -0,0 +1,81 @@
+use actix_web::http::header::{CacheControl, CacheDirective, LOCATION};
+use actix_web::{HttpResponse, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::email::tracking::links_to;
+use crate::utils::e500;
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+#[derive(serde::Deserialize)]
+pub struct ClickParameters {
+    url: String,
+}
+
+
+#[tracing::instrument(name = "Track an issue open", skip(pool))]
+pub async fn track_open(
+    delivery_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    sqlx::query!(
+        r#"UPDATE issue_deliveries SET opened_at = now() WHERE delivery_id = $1 AND opened_at IS NULL"#,
+        delivery_id.into_inner(),
+    )
+        .execute(pool.get_ref())
+        .await
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("image/gif")
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(PIXEL))
+}
+
+
+#[tracing::instrument(name = "Track an issue click", skip(pool, parameters))]
+pub async fn track_click(
+    delivery_id: web::Path<Uuid>,
+    parameters: web::Query<ClickParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let delivery_id = delivery_id.into_inner();
+    let issue = sqlx::query!(
+        r#"
+        SELECT i.html_content
+        FROM issue_deliveries d
+        JOIN newsletter_issues i ON i.newsletter_issue_id = d.newsletter_issue_id
+        WHERE d.delivery_id = $1
+        "#,
+        delivery_id,
+    )
+        .fetch_optional(pool.get_ref())
+        .await
+        .map_err(e500)?;
+
+    match issue {
+        Some(issue) if links_to(&issue.html_content, &parameters.url) => {
+            // A click implies the issue was opened, even if images were blocked
+            sqlx::query!(
+                r#"
+                UPDATE issue_deliveries
+                SET clicked_at = COALESCE(clicked_at, now()), opened_at = COALESCE(opened_at, now())
+                WHERE delivery_id = $1
+                "#,
+                delivery_id,
+            )
+                .execute(pool.get_ref())
+                .await
+                .map_err(e500)?;
+
+            Ok(HttpResponse::Found().insert_header((LOCATION, parameters.url.as_str())).finish())
+        }
+        _ => Ok(HttpResponse::NotFound().finish()),
+    }
+}
@@ -1,13 +1,15 @@
 use actix_web::dev::Server;
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
-use crate::email::email_client::EmailClient;
 use crate::{startup::run::run};
 use std::net::TcpListener;
 use crate::configuration::{
     settings::Settings,
     database_settings::DatabaseSettings,
 };
+use crate::domain::bot_protection::BotProtection;
+use crate::domain::email_policy::EmailPolicy;
+use crate::helpers::client_ip::TrustedProxies;
 
 pub struct Application {
     port: u16,
@@ -16,18 +18,25 @@ pub struct Application {
 
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
+        let email_policy = configuration.email_policy.policy();
+        let bot_protection = configuration.application.bot_protection
+            .protection(&configuration.application.hmac_secret, &configuration.redis_uri)
+            .await?;
+        Self::build_with(configuration, email_policy, bot_protection).await
+    }
+
+    /// Like `build`, with custom signup checks, e.g. with an `MxResolver` or a `CaptchaVerifier` plugged in.
+    pub async fn build_with(
+        configuration: Settings,
+        email_policy: EmailPolicy,
+        bot_protection: BotProtection,
+    ) -> Result<Self, anyhow::Error> {
     
         let connection_pool = get_connection_pool(&configuration.database);   
     
-        let sender_email = configuration
-            .email_client.sender().expect("Invalid sender email address.");
-    
-        let timeout = configuration.email_client.timeout();
-    
-        let email_client = EmailClient::new(
-            configuration.email_client.base_url, sender_email, 
-            configuration.email_client.authorization_token,
-            timeout);
+        let email_client = configuration.email_client.client();
+
+        let rate_limiter = configuration.rate_limits.limiter(&configuration.redis_uri).await?;
     
         let address = format!("{}:{}", 
             configuration.application.host, configuration.application.port);
@@ -41,6 +50,12 @@ impl Application {
             configuration.application.base_url,
             configuration.application.hmac_secret,
             configuration.redis_uri,
+            configuration.newsletter,
+            configuration.subscriptions,
+            email_policy,
+            bot_protection,
+            rate_limiter,
+            TrustedProxies::new(configuration.application.trusted_proxies),
         ).await?;
         Ok(Self {port, server})
     }
@@ -13,12 +13,32 @@ use std::net::TcpListener;
 use sqlx::{PgPool};
 use tracing_actix_web::TracingLogger;
 
+use crate::configuration::application_settings::HmacSecret;
+use crate::configuration::newsletter_settings::NewsletterSettings;
+use crate::configuration::subscription_settings::SubscriptionSettings;
+use crate::domain::bot_protection::BotProtection;
+use crate::domain::email_policy::EmailPolicy;
 use crate::email::email_client::EmailClient;
-use crate::routes::{health_check, subscribe, confirm, publish_newsletter, 
-    home, login_form, login, admin_dashboard, change_password, change_password_form, log_out};
+use crate::helpers::client_ip::TrustedProxies;
+use crate::rate_limiting::limiter::RateLimiter;
+use crate::rate_limiting::middleware::RateLimit;
+use crate::routes::{health_check, subscribe, resend_confirmation, confirm, confirm_form, publish_newsletter, 
+    home, login_form, login, admin_dashboard, change_password, change_password_form, log_out,
+    publish_newsletter_form, newsletter_issue, track_open, track_click,
+    preferences_form, update_preferences, change_issue_delivery, send_correction,
+    subscriber_details, export_subscriber_data, erase_subscriber_data,
+    export_my_data, erase_my_data_form, erase_my_data,
+    import_subscribers_form, import_subscribers, MAX_IMPORT_BYTES,
+    export_subscribers_form, download_subscribers,
+    subscriber_attributes, add_subscriber_attribute, delete_subscriber_attribute, browse_subscribers,
+    new_subscriber_form, add_subscriber, edit_subscriber_form, edit_subscriber, bulk_action,
+    welcome_email_form, update_welcome_email, add_subscriber_tag,
+    sequences, add_sequence, sequence_details, add_sequence_step, stop_subscriber_enrollment,
+    referrals, add_referral_reward, remove_referral_reward};
 
 pub struct ApplicationBaseUrl(pub String);
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     listner: TcpListener, 
     db_pool: PgPool, 
@@ -26,12 +46,25 @@ pub async fn run(
     base_url: String,
     hmac_secret: Secret<String>,
     redis_uri: Secret<String>,
+    newsletter_settings: NewsletterSettings,
+    subscription_settings: SubscriptionSettings,
+    email_policy: EmailPolicy,
+    bot_protection: BotProtection,
+    rate_limiter: RateLimiter,
+    trusted_proxies: TrustedProxies,
 ) -> Result<Server, anyhow::Error> {
 
     let db_pool = web::Data::new(db_pool);
     let email_client = web::Data::new(email_client);
     let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let newsletter_settings = web::Data::new(newsletter_settings);
+    let subscription_settings = web::Data::new(subscription_settings);
+    let email_policy = web::Data::new(email_policy);
+    let bot_protection = web::Data::new(bot_protection);
+    let rate_limiter = web::Data::new(rate_limiter);
+    let trusted_proxies = web::Data::new(trusted_proxies);
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
+    let hmac_secret = web::Data::new(HmacSecret(hmac_secret));
 
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
@@ -40,13 +73,21 @@ pub async fn run(
 
     let server = HttpServer::new( move || {
         App::new()
+        .wrap(RateLimit::new(rate_limiter.clone(), trusted_proxies.clone()))
         .wrap(message_framework.clone())
         .wrap(SessionMiddleware::new(redis_store.clone(), secret_key.clone()))
         .wrap(TracingLogger::default())
             .wrap(message_framework.clone())
             .route("/health_check", web::get().to(health_check))
             .route("/subscriptions", web::post().to(subscribe))
-            .route("/subscriptions/confirm", web::get().to(confirm))
+            .route("/subscriptions/resend", web::post().to(resend_confirmation))
+            .route("/subscriptions/confirm", web::get().to(confirm_form))
+            .route("/subscriptions/confirm", web::post().to(confirm))
+            .route("/subscriptions/preferences", web::get().to(preferences_form))
+            .route("/subscriptions/preferences", web::post().to(update_preferences))
+            .route("/subscriptions/data", web::get().to(export_my_data))
+            .route("/subscriptions/erase", web::get().to(erase_my_data_form))
+            .route("/subscriptions/erase", web::post().to(erase_my_data))
             .route("/newsletters", web::post().to(publish_newsletter))
             .route("/", web::get().to(home))
             .route("/login", web::get().to(login_form))
@@ -55,9 +96,57 @@ pub async fn run(
             .route("/admin/password", web::get().to(change_password_form))
             .route("/admin/password", web::post().to(change_password))
             .route("/admin/logout", web::post().to(log_out))
+            .route("/admin/newsletters", web::get().to(publish_newsletter_form))
+            .route("/admin/newsletters/{issue_id}", web::get().to(newsletter_issue))
+            .route("/admin/newsletters/{issue_id}/delivery", web::post().to(change_issue_delivery))
+            .route("/admin/newsletters/{issue_id}/correction", web::post().to(send_correction))
+            .route("/admin/subscribers", web::get().to(browse_subscribers))
+            // Registered before `{subscriber_id}`, which would match `new`, `bulk`, `import` and `export` as well
+            .route("/admin/subscribers/new", web::get().to(new_subscriber_form))
+            .route("/admin/subscribers/new", web::post().to(add_subscriber))
+            .route("/admin/subscribers/bulk", web::post().to(bulk_action))
+            .service(
+                web::resource("/admin/subscribers/import")
+                    .app_data(web::FormConfig::default().limit(MAX_IMPORT_BYTES))
+                    .route(web::get().to(import_subscribers_form))
+                    .route(web::post().to(import_subscribers))
+            )
+            .route("/admin/subscribers/export", web::get().to(export_subscribers_form))
+            .route("/admin/subscribers/export/download", web::get().to(download_subscribers))
+            .route("/admin/subscribers/{subscriber_id}", web::get().to(subscriber_details))
+            .route("/admin/subscribers/{subscriber_id}/edit", web::get().to(edit_subscriber_form))
+            .route("/admin/subscribers/{subscriber_id}/edit", web::post().to(edit_subscriber))
+            .route("/admin/subscribers/{subscriber_id}/tags", web::post().to(add_subscriber_tag))
+            .route(
+                "/admin/subscribers/{subscriber_id}/enrollments/{enrollment_id}/stop",
+                web::post().to(stop_subscriber_enrollment),
+            )
+            .route("/admin/subscribers/{subscriber_id}/export", web::get().to(export_subscriber_data))
+            .route("/admin/subscribers/{subscriber_id}/erase", web::post().to(erase_subscriber_data))
+            .route("/admin/attributes", web::get().to(subscriber_attributes))
+            .route("/admin/attributes", web::post().to(add_subscriber_attribute))
+            .route("/admin/attributes/{attribute_id}/delete", web::post().to(delete_subscriber_attribute))
+            .route("/admin/welcome-email", web::get().to(welcome_email_form))
+            .route("/admin/welcome-email", web::post().to(update_welcome_email))
+            .route("/admin/sequences", web::get().to(sequences))
+            .route("/admin/sequences", web::post().to(add_sequence))
+            .route("/admin/sequences/{sequence_id}", web::get().to(sequence_details))
+            .route("/admin/sequences/{sequence_id}/steps", web::post().to(add_sequence_step))
+            .route("/admin/referrals", web::get().to(referrals))
+            .route("/admin/referrals/rewards", web::post().to(add_referral_reward))
+            .route("/admin/referrals/rewards/{milestone}/delete", web::post().to(remove_referral_reward))
+            .route("/issues/{delivery_id}/open", web::get().to(track_open))
+            .route("/issues/{delivery_id}/click", web::get().to(track_click))
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(newsletter_settings.clone())
+            .app_data(subscription_settings.clone())
+            .app_data(email_policy.clone())
+            .app_data(bot_protection.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(trusted_proxies.clone())
+            .app_data(hmac_secret.clone())
     }).listen(listner)?
     .run();
     Ok(server)
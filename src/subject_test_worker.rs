@@ -0,0 +1,92 @@
+use std::time::Duration;
+use sqlx::PgPool;
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+use crate::configuration::settings::Settings;
+use crate::domain::subject_test::{pick_winner, SubjectTestMetric};
+use crate::helpers::newsletter_issue::{enqueue_remainder, get_variant_results};
+use crate::startup::application::get_connection_pool;
+
+pub enum DecisionOutcome {
+    WinnerPicked,
+    NothingToDecide,
+}
+
+
+/// Wait for the test window of A/B tested issues to elapse, then send the winning subject line to everyone else.
+pub async fn run_subject_test_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let pool = get_connection_pool(&configuration.database);
+
+    loop {
+        match try_decide_subject_test(&pool).await {
+            Ok(DecisionOutcome::NothingToDecide) => {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(DecisionOutcome::WinnerPicked) => {}
+        }
+    }
+}
+
+
+#[tracing::instrument(
+    skip_all,
+    fields(newsletter_issue_id=tracing::field::Empty, winning_variant=tracing::field::Empty),
+    err
+)]
+pub async fn try_decide_subject_test(pool: &PgPool) -> Result<DecisionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let issue = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id, subject_test_metric AS "metric!"
+        FROM newsletter_issues
+        WHERE subject_test_metric IS NOT NULL
+            AND winning_variant IS NULL
+            AND delivery_status <> 'cancelled'
+            AND subject_test_decide_at <= now()
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+        .fetch_optional(&mut transaction)
+        .await?;
+
+    let issue = match issue {
+        Some(issue) => issue,
+        None => return Ok(DecisionOutcome::NothingToDecide),
+    };
+    Span::current().record("newsletter_issue_id", &display(issue.newsletter_issue_id));
+
+    let metric = SubjectTestMetric::parse(&issue.metric).map_err(anyhow::Error::msg)?;
+    let results = get_variant_results(pool, issue.newsletter_issue_id).await?;
+    let winner = pick_winner(&results, metric);
+    Span::current().record("winning_variant", &display(winner));
+
+    set_winning_variant(&mut transaction, issue.newsletter_issue_id, winner).await?;
+    enqueue_remainder(&mut transaction, issue.newsletter_issue_id, winner).await?;
+    transaction.commit().await?;
+
+    tracing::info!("Subject test decided, the winning subject line is being sent to the remainder.");
+    Ok(DecisionOutcome::WinnerPicked)
+}
+
+
+async fn set_winning_variant(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    newsletter_issue_id: Uuid,
+    winning_variant: i16,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE newsletter_issues SET winning_variant = $2 WHERE newsletter_issue_id = $1"#,
+        newsletter_issue_id,
+        winning_variant,
+    )
+        .execute(transaction)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use sqlx::PgPool;
+
+use crate::configuration::settings::Settings;
+use crate::helpers::subscriber_data::{erase_subscriber, Erasure};
+use crate::startup::application::get_connection_pool;
+
+pub struct CleanupOutcome {
+    pub expired_tokens: u64,
+    pub unconfirmed_subscribers: u64,
+}
+
+
+/// Periodically purge expired confirmation tokens and subscribers who never confirmed.
+pub async fn run_cleanup_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let pool = get_connection_pool(&configuration.database);
+    let settings = configuration.subscriptions;
+
+    loop {
+        // Failures are logged by `purge_stale_subscriptions`, the next run tries again
+        let _ = purge_stale_subscriptions(&pool, settings.unconfirmed_retention()).await;
+        tokio::time::sleep(settings.cleanup_interval()).await;
+    }
+}
+
+
+#[tracing::instrument(
+    skip(pool),
+    fields(expired_tokens=tracing::field::Empty, unconfirmed_subscribers=tracing::field::Empty),
+    err
+)]
+pub async fn purge_stale_subscriptions(
+    pool: &PgPool,
+    unconfirmed_retention: chrono::Duration,
+) -> Result<CleanupOutcome, anyhow::Error> {
+    let cutoff = chrono::Utc::now() - unconfirmed_retention;
+    let mut transaction = pool.begin().await?;
+
+    let expired_tokens = sqlx::query!(r#"DELETE FROM subscription_tokens WHERE expires_at <= now()"#)
+        .execute(&mut transaction)
+        .await?
+        .rows_affected();
+
+    let stale = sqlx::query!(
+        r#"SELECT id FROM subscriptions WHERE status = 'pending_confirmation' AND subscribed_at < $1"#,
+        cutoff,
+    )
+        .fetch_all(&mut transaction)
+        .await?;
+    // Erased rather than deleted, their consent trail can only go through an erasure
+    let mut unconfirmed_subscribers = 0;
+    for subscriber in stale {
+        if erase_subscriber(&mut transaction, subscriber.id, Erasure::Expired).await? {
+            unconfirmed_subscribers += 1;
+        }
+    }
+
+    transaction.commit().await?;
+
+    tracing::Span::current()
+        .record("expired_tokens", &expired_tokens)
+        .record("unconfirmed_subscribers", &unconfirmed_subscribers);
+
+    Ok(CleanupOutcome { expired_tokens, unconfirmed_subscribers })
+}
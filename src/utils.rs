@@ -9,3 +9,8 @@ pub fn e500<T>(e: T) -> actix_web::error::InternalError<T> {
 pub fn see_other(location: &str) -> HttpResponse {
     HttpResponse::SeeOther().insert_header((LOCATION, location)).finish()
 }
+
+// Postgres reports a clash with a unique constraint or index as SQLSTATE 23505.
+pub fn is_unique_violation(e: &sqlx::Error) -> bool {
+    matches!(e, sqlx::Error::Database(e) if e.code().as_deref() == Some("23505"))
+}
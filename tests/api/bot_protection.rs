@@ -0,0 +1,187 @@
+use secrecy::Secret;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::domain::bot_protection::{is_valid_proof_of_work, proof_of_work_challenge};
+
+use crate::helpers::app::{spawn_app, spawn_app_with, TestApp};
+
+const BODY: &str = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+
+async fn mount_email_server(app: &TestApp, expected_emails: u64) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_emails)
+        .mount(&app.email_server)
+        .await;
+}
+
+fn with_form_token(form_token: &str) -> String {
+    format!("{}&form_token={}", BODY, urlencoding::encode(form_token))
+}
+
+fn solve(form_token: &str, email: &str, difficulty: u8) -> String {
+    let challenge = proof_of_work_challenge(form_token, email);
+    (0u64..)
+        .map(|nonce| nonce.to_string())
+        .find(|nonce| is_valid_proof_of_work(&challenge, nonce, difficulty))
+        .unwrap()
+}
+
+
+#[actix_rt::test]
+async fn the_signup_form_embeds_a_form_token_and_a_honeypot() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let page = app.get_home().await.text().await.unwrap();
+
+    // Assert
+    assert!(page.contains(r#"name="website""#));
+    assert!(!app.get_signup_form_token().await.is_empty());
+}
+
+
+#[actix_rt::test]
+async fn a_filled_in_honeypot_is_silently_dropped() {
+    // Arrange
+    let app = spawn_app().await;
+    mount_email_server(&app, 0).await;
+
+    // Act
+    let response = app.post_subscription(format!("{}&website=https%3A%2F%2Fspam.example", BODY)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(saved.is_none());
+}
+
+
+#[actix_rt::test]
+async fn a_signup_without_a_form_token_is_rejected_when_a_minimum_fill_time_is_set() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.bot_protection.min_form_fill_seconds = 60).await;
+    mount_email_server(&app, 0).await;
+
+    // Act
+    let response = app.post_subscription(BODY.into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+
+#[actix_rt::test]
+async fn a_form_submitted_faster_than_the_minimum_fill_time_is_rejected() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.bot_protection.min_form_fill_seconds = 60).await;
+    mount_email_server(&app, 0).await;
+    let form_token = app.get_signup_form_token().await;
+
+    // Act
+    let response = app.post_subscription(with_form_token(&form_token)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+
+#[actix_rt::test]
+async fn a_form_submitted_after_the_minimum_fill_time_is_accepted() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.bot_protection.min_form_fill_seconds = 1).await;
+    mount_email_server(&app, 1).await;
+    let form_token = app.get_signup_form_token().await;
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    // Act
+    let response = app.post_subscription(with_form_token(&form_token)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+
+#[actix_rt::test]
+async fn the_proof_of_work_is_required_when_enabled() {
+    // Arrange
+    let difficulty = 16;
+    let app = spawn_app_with(|c| c.application.bot_protection.proof_of_work_difficulty = difficulty).await;
+    mount_email_server(&app, 1).await;
+    let form_token = app.get_signup_form_token().await;
+    let nonce = solve(&form_token, EMAIL, difficulty);
+
+    // Act - Part 1 - No proof of work
+    let response = app.post_subscription(with_form_token(&form_token)).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Act - Part 2 - Solved challenge
+    let body = format!("{}&pow_nonce={}", with_form_token(&form_token), nonce);
+    let response = app.post_subscription(body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+
+#[actix_rt::test]
+async fn a_solved_form_cannot_be_replayed() {
+    // Arrange
+    let difficulty = 8;
+    let app = spawn_app_with(|c| c.application.bot_protection.proof_of_work_difficulty = difficulty).await;
+    mount_email_server(&app, 1).await;
+    let form_token = app.get_signup_form_token().await;
+    let body = format!("{}&pow_nonce={}", with_form_token(&form_token), solve(&form_token, EMAIL, difficulty));
+
+    // Act
+    let first = app.post_subscription(body.clone()).await;
+    let second = app.post_subscription(body).await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 400);
+}
+
+
+#[actix_rt::test]
+async fn a_solved_form_cannot_sign_up_another_address() {
+    // Arrange
+    let difficulty = 8;
+    let app = spawn_app_with(|c| c.application.bot_protection.proof_of_work_difficulty = difficulty).await;
+    mount_email_server(&app, 0).await;
+    let form_token = app.get_signup_form_token().await;
+    let nonce = solve(&form_token, EMAIL, difficulty);
+
+    // Act
+    let response = app.post_subscription(format!(
+        "name=bot&email=someone_else%40gmail.com&form_token={}&pow_nonce={}",
+        urlencoding::encode(&form_token),
+        nonce,
+    )).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+
+#[actix_rt::test]
+async fn the_captcha_response_is_checked_when_a_verifier_is_configured() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.bot_protection.captcha_stub_response = Some(Secret::new("solved".to_string()));
+    }).await;
+    mount_email_server(&app, 1).await;
+
+    // Act - Part 1 - Wrong response
+    let response = app.post_subscription(format!("{}&captcha_response=guessed", BODY)).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Act - Part 2 - Expected response
+    let response = app.post_subscription(format!("{}&captcha_response=solved", BODY)).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
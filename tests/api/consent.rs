@@ -0,0 +1,121 @@
+use crate::helpers::app::{spawn_app, spawn_app_with};
+use crate::helpers::email::create_confirmed_subscriber;
+
+
+#[actix_rt::test]
+async fn subscribing_and_confirming_are_recorded_in_the_consent_trail() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    create_confirmed_subscriber(&app).await;
+
+    // Assert
+    let events = sqlx::query!(
+        "SELECT action, ip_address, source, consent_version FROM consent_events ORDER BY occurred_at"
+    )
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].action, "subscribe");
+    assert_eq!(events[0].source, "api");
+    assert_eq!(events[1].action, "confirm");
+    assert_eq!(events[1].source, "confirmation-page");
+    assert!(events.iter().all(|e| e.ip_address.is_some() && !e.consent_version.is_empty()));
+}
+
+
+#[actix_rt::test]
+async fn the_client_address_is_taken_from_a_trusted_proxy() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.trusted_proxies = vec!["127.0.0.1/32".parse().unwrap()]).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    app.api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header("X-Forwarded-For", "198.51.100.1")
+        .form(&[("name", "le guin"), ("email", "ursula_le_guin@gmail.com")])
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let event = sqlx::query!("SELECT ip_address FROM consent_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(event.ip_address.as_deref(), Some("198.51.100.1"));
+}
+
+
+#[actix_rt::test]
+async fn consent_events_cannot_be_rewritten() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    // Act
+    let outcome = sqlx::query!("UPDATE consent_events SET source = 'forged'")
+        .execute(&app.db_pool)
+        .await;
+
+    // Assert
+    assert!(outcome.is_err());
+}
+
+
+#[actix_rt::test]
+async fn consent_events_are_only_removed_by_an_erasure() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    // Act
+    let deleted_events = sqlx::query!("DELETE FROM consent_events")
+        .execute(&app.db_pool)
+        .await;
+    let deleted_subscriber = sqlx::query!("DELETE FROM subscriptions")
+        .execute(&app.db_pool)
+        .await;
+
+    // Assert
+    assert!(deleted_events.is_err());
+    assert!(deleted_subscriber.is_err());
+    let events = sqlx::query!(r#"SELECT count(*) AS "count!" FROM consent_events"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(events, 2);
+}
+
+
+#[actix_rt::test]
+async fn the_consent_trail_is_shown_on_the_admin_subscriber_page() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let html_page = app.api_client
+        .get(format!("{}/admin/subscribers/{}", &app.address, subscriber.id))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains("<h2>Consent history</h2>"));
+    assert!(html_page.contains("confirmation-page"));
+}
@@ -7,7 +7,7 @@ async fn health_check_works() {
     let app = spawn_app().await;
     let client = reqwest::Client::new();
 
-    let response = client.get(&format!("{}/health_check", &app.address)).send().await.expect("Failed to execuet request");
+    let response = client.get(format!("{}/health_check", &app.address)).send().await.expect("Failed to execuet request");
 
     // Act
     assert!(response.status().is_success());
@@ -1,10 +1,16 @@
-use zero2prod::configuration::{settings::get_configuration};
+use zero2prod::configuration::rate_limit_settings::RateLimitBackend;
+use zero2prod::configuration::settings::{get_configuration, Settings};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
 use zero2prod::startup::application::{Application, get_connection_pool};
+use zero2prod::email::email_client::EmailClient;
+use zero2prod::automation_worker::try_execute_step;
+use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
 use uuid::Uuid;
 use once_cell::sync::Lazy;
+use secrecy::Secret;
 use sqlx::{PgPool};
-use wiremock::MockServer;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use crate::helpers::db::configure_database;
 use crate::helpers::email::ConfirmationLinks;
@@ -31,19 +37,64 @@ pub struct TestApp {
     pub email_server: MockServer,
     pub port: u16,
     pub test_user: TestUser,
-    pub api_client: reqwest::Client
+    pub api_client: reqwest::Client,
+    pub email_client: EmailClient,
+    pub base_url: String,
+    pub hmac_secret: Secret<String>,
 }
 
 impl TestApp {
     pub async fn post_subscription(&self, body: String) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send().await
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_home(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// The form token embedded in the signup form of the home page.
+    pub async fn get_signup_form_token(&self) -> String {
+        let page = self.get_home().await.text().await.unwrap();
+        let marker = r#"name="form_token" value=""#;
+        let start = page.find(marker).expect("The signup form has no form token.") + marker.len();
+        let end = start + page[start..].find('"').unwrap();
+        page[start..end].to_string()
+    }
+
+    pub async fn post_resend_confirmation(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions/resend", &self.address))
+            .form(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Press the button on the page the confirmation link leads to.
+    pub async fn post_confirmation(&self, confirmation_link: &reqwest::Url) -> reqwest::Response {
+        let subscription_token = confirmation_link
+            .query_pairs()
+            .find(|(key, _)| key == "subscription_token")
+            .map(|(_, value)| value.into_owned())
+            .unwrap();
+
+        self.api_client
+            .post(format!("{}/subscriptions/confirm", &self.address))
+            .form(&serde_json::json!({ "subscription_token": subscription_token }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
 
@@ -63,8 +114,8 @@ impl TestApp {
             confirmation_link
         };
 
-        let html = get_link(&body["HtmlBody"].as_str().unwrap());
-        let plain_text = get_link(&body["TextBody"].as_str().unwrap());
+        let html = get_link(body["HtmlBody"].as_str().unwrap());
+        let plain_text = get_link(body["TextBody"].as_str().unwrap());
 
         ConfirmationLinks { html, plain_text}
     }
@@ -74,7 +125,7 @@ impl TestApp {
         let TestUser {username, password, ..} = &self.test_user;
 
         self.api_client
-            .post(&format!("{}/newsletters", &self.address))
+            .post(format!("{}/newsletters", &self.address))
             .basic_auth(username, Some(password))
             .json(&body)
             .send()
@@ -86,15 +137,23 @@ impl TestApp {
         where Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .form(body)
             .send()
             .await.expect("Failed to execute request.")
     }
 
+    pub async fn login_as_admin(&self) {
+        let response = self.post_login(&serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password
+        })).await;
+        assert_eq!(response.headers().get("Location").unwrap(), "/admin/dashboard");
+    }
+
     pub async fn get_login(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -102,7 +161,7 @@ impl TestApp {
 
     pub async fn get_admin_dashboard(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/dashboard", &self.address))
+            .get(format!("{}/admin/dashboard", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -110,7 +169,7 @@ impl TestApp {
 
     pub async fn get_change_password(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/password", &self.address))
+            .get(format!("{}/admin/password", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -121,16 +180,290 @@ impl TestApp {
             Body: serde::Serialize
     {
         self.api_client
-            .post(&format!("{}/admin/password", &self.address))
+            .post(format!("{}/admin/password", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_publish_newsletter<Body>(&self, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/newsletters", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_issue(&self, location: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}{}", &self.address, location))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_issue_delivery_action(&self, issue_page: &str, action: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}{}/delivery", &self.address, issue_page))
+            .form(&serde_json::json!({ "action": action }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_correction<Body>(&self, issue_page: &str, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}{}/correction", &self.address, issue_page))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// `path_and_query` is relative to `/admin/subscribers`, e.g. `?q=ada`.
+    pub async fn get_subscribers(&self, path_and_query: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers{}", &self.address, path_and_query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_import_subscribers(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/import", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_import_subscribers<Body>(&self, body: &Body) -> reqwest::Response
+        where Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/import", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Import `csv`, whose columns are named `email`, `name`, `status`, `tags` and `subscribed_at`.
+    pub async fn import_subscribers(&self, csv: &str) {
+        let response = self.post_import_subscribers(&serde_json::json!({
+            "csv": csv,
+            "email_column": "email",
+            "name_column": "name",
+            "status_column": "status",
+            "tags_column": "tags",
+            "subscribed_at_column": "subscribed_at",
+            "action": "import",
+        })).await;
+        assert_eq!(response.status().as_u16(), 303);
+        assert_eq!(response.headers().get("Location").unwrap(), "/admin/subscribers/import");
+    }
+
+    pub async fn get_export_subscribers(&self, query: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/export/download?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_attributes(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/attributes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriber_attribute<Body>(&self, body: &Body) -> reqwest::Response
+        where Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/attributes", &self.address))
             .form(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_new_subscriber<Body>(&self, body: &Body) -> reqwest::Response
+        where Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/new", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_edit_subscriber<Body>(&self, subscriber_id: Uuid, body: &Body) -> reqwest::Response
+        where Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/{}/edit", &self.address, subscriber_id))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// `body` lists the form fields in order, since `subscriber_id` may be repeated.
+    pub async fn post_bulk_action(&self, body: &[(&str, &str)]) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/subscribers/bulk", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_welcome_email(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/welcome-email", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_welcome_email<Body>(&self, body: &Body) -> reqwest::Response
+        where Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/welcome-email", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Accept every email sent to the email server.
+    pub async fn mount_ok_email_server(&self) {
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&self.email_server)
+            .await;
+    }
+
+    /// Drain the delivery queue, as the background worker would.
+    pub async fn dispatch_all_pending_emails(&self) {
+        loop {
+            if let ExecutionOutcome::TaskCompleted = try_execute_task(&self.db_pool, &self.email_client, &self.base_url, &self.hmac_secret)
+                .await
+                .unwrap()
+            {
+                continue;
+            }
+            break;
+        }
+    }
+
+    pub async fn get_sequences(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/sequences", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_sequence<Body>(&self, body: &Body) -> reqwest::Response
+        where Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/sequences", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sequence(&self, sequence_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/sequences/{}", &self.address, sequence_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_sequence_step<Body>(&self, sequence_id: Uuid, body: &Body) -> reqwest::Response
+        where Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/sequences/{}/steps", &self.address, sequence_id))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriber_tag(&self, subscriber_id: Uuid, tag: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/subscribers/{}/tags", &self.address, subscriber_id))
+            .form(&serde_json::json!({ "tag": tag }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_stop_enrollment(&self, subscriber_id: Uuid, enrollment_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/subscribers/{}/enrollments/{}/stop",
+                &self.address, subscriber_id, enrollment_id,
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_referrals(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/referrals", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_referral_reward<Body>(&self, body: &Body) -> reqwest::Response
+        where Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/referrals/rewards", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Run every sequence step that is due, as the automation worker would.
+    pub async fn dispatch_all_automation_steps(&self) {
+        loop {
+            if let ExecutionOutcome::TaskCompleted = try_execute_step(&self.db_pool, &self.email_client, &self.base_url, &self.hmac_secret)
+                .await
+                .unwrap()
+            {
+                continue;
+            }
+            break;
+        }
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/admin/lopgout", &self.address))
+            .post(format!("{}/admin/lopgout", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -140,6 +473,11 @@ impl TestApp {
 
 /// Spin up an instance of our application
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Like `spawn_app`, with the configuration adjusted by `customise` first.
+pub async fn spawn_app_with(customise: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     // Launch a mock server to stand in for Postmark's API
@@ -152,6 +490,13 @@ pub async fn spawn_app() -> TestApp {
         // Use a random OS port 
         c.application.port = 0;
         c.email_client.base_url = email_server.uri();
+        // Tests submit the signup form straight away, without a browser to solve the proof of work
+        c.application.bot_protection.min_form_fill_seconds = 0;
+        c.application.bot_protection.proof_of_work_difficulty = 0;
+        // Every test app gets its own counters, all requests come from 127.0.0.1
+        c.rate_limits.backend = RateLimitBackend::Memory;
+        c.application.bot_protection.used_form_tokens = RateLimitBackend::Memory;
+        customise(&mut c);
         c
     };
 
@@ -164,11 +509,11 @@ pub async fn spawn_app() -> TestApp {
     let address = format!("http://127.0.0.1:{}", application_port);
     
     // build(configuration).await.expect("Failed to build expectation");
-    let _ = tokio::spawn(application.run_until_stopped());
+    tokio::spawn(application.run_until_stopped());
 
     let db_pool = get_connection_pool(&configuration.database);
     let mut test_user = TestUser::generate();
-    test_user.store(&db_pool);
+    test_user.store(&db_pool).await;
 
     let client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none())
@@ -176,15 +521,18 @@ pub async fn spawn_app() -> TestApp {
         .build()
         .unwrap();
 
-    let test_app = TestApp {
+    
+
+    TestApp {
         address,
         db_pool,
         email_server,
         port: application_port,
         test_user,
         api_client: client,
-    };
-
-    test_app
+        email_client: configuration.email_client.client(),
+        base_url: configuration.application.base_url,
+        hmac_secret: configuration.application.hmac_secret,
+    }
 }
 
@@ -1,4 +1,3 @@
-use uuid::Uuid;
 use zero2prod::configuration::{database_settings::DatabaseSettings};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 
@@ -1,4 +1,4 @@
-use wiremock::matchers::{any, method, path};
+use wiremock::matchers::{method, path};
 use wiremock::{Mock, ResponseTemplate};
 
 use super::app::TestApp;
@@ -32,15 +32,14 @@ pub async fn create_unconfirmed_subcriber(app: &TestApp) -> ConfirmationLinks {
         .pop()
         .unwrap();
 
-    app.get_confirmation_links(&email_request)
+    app.get_confirmation_links(email_request)
 }
 
 
 pub async fn create_confirmed_subscriber(app: &TestApp) {
     let confirmation_link = create_unconfirmed_subcriber(app).await;
-    reqwest::get(confirmation_link.html)
+    app.post_confirmation(&confirmation_link.html)
         .await
-        .unwrap()
         .error_for_status()
         .unwrap();
 }
\ No newline at end of file
@@ -1,4 +1,3 @@
-use std::collections::HashSet;
 
 use crate::helpers::app::spawn_app;
 
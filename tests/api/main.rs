@@ -5,4 +5,20 @@ mod subscriptions_confirm;
 mod newsletters;
 mod login;
 mod dashboard;
-mod change_password;
\ No newline at end of file
+mod change_password;
+mod preferences;
+mod subscription_cleanup;
+mod subscriptions_resend;
+mod consent;
+mod subscriber_data;
+mod bot_protection;
+mod rate_limiting;
+mod subscriber_import;
+mod subscriber_export;
+mod subscriber_attributes;
+mod subscriber_list;
+mod subscriber_management;
+mod subscription_status;
+mod welcome_email;
+mod sequences;
+mod referrals;
\ No newline at end of file
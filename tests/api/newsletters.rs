@@ -1,14 +1,16 @@
-use crate::helpers::app::{spawn_app, TestApp};
+use crate::helpers::app::spawn_app;
 use uuid::Uuid;
 use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
 use crate::helpers::email::{create_confirmed_subscriber, create_unconfirmed_subcriber};
+use zero2prod::subject_test_worker::{try_decide_subject_test, DecisionOutcome};
 
 
 #[actix_rt::test]
 async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     let app = spawn_app().await;
     create_unconfirmed_subcriber(&app).await;
+    app.login_as_admin().await;
 
     Mock::given(any())
         .respond_with(ResponseTemplate::new(200))
@@ -16,25 +18,123 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
         .mount(&app.email_server)
         .await;
 
-    
-    let newsletter_request_body = serde_json::json!({
+    let response = app.post_publish_newsletter(&newsletter_form()).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    app.dispatch_all_pending_emails().await;
+}
+
+
+#[actix_rt::test]
+async fn newsletters_are_delivered_to_confirmed_subscribers() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_publish_newsletter(&newsletter_form()).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    app.dispatch_all_pending_emails().await;
+}
+
+
+#[actix_rt::test]
+async fn the_winning_subject_line_is_recorded_once_the_test_window_elapses() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_publish_newsletter(&serde_json::json!({
         "title": "Newsletter title",
-        "content": {
-            "text": "Newsletter body as plain text",
-            "html": "<p>Newsletter body as HTML</P>",
-        }
-    });
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "subject_variants": "Another newsletter title",
+        "sample_percentage": "10",
+        "test_metric": "open",
+    })).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let issue_page = response.headers().get("Location").unwrap().to_str().unwrap().to_owned();
 
-    let response = app.post_newsletters(newsletter_request_body).await;
+    // The only subscriber is part of the first sample
+    app.dispatch_all_pending_emails().await;
+
+    sqlx::query!("UPDATE newsletter_issues SET subject_test_decide_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let outcome = try_decide_subject_test(&app.db_pool).await.unwrap();
+    assert!(matches!(outcome, DecisionOutcome::WinnerPicked));
 
-    assert_eq!(response.status().as_u16(), 200);
+    // Nobody is left to receive the winner
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_newsletter_issue(&issue_page).await.text().await.unwrap();
+    assert!(html_page.contains("(winner)"));
 }
 
 
 #[actix_rt::test]
-async fn newsletters_are_delivered_to_confirmed_subscribers() {
+async fn sample_members_whose_send_failed_are_not_sent_the_winning_subject() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "subject_variants": "Another newsletter title",
+        "sample_percentage": "10",
+        "test_metric": "open",
+    })).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    // The only subscriber is part of the first sample, and the send fails
+    app.dispatch_all_pending_emails().await;
+
+    sqlx::query!("UPDATE newsletter_issues SET subject_test_decide_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let outcome = try_decide_subject_test(&app.db_pool).await.unwrap();
+    assert!(matches!(outcome, DecisionOutcome::WinnerPicked));
+
+    // The remainder leaves them out
+    app.dispatch_all_pending_emails().await;
+    let failures = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM issue_delivery_failures"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(failures.count, 1);
+}
+
+
+#[actix_rt::test]
+async fn issues_sent_in_local_time_wait_for_the_subscribers_send_time() {
     let app = spawn_app().await;
     create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
 
     Mock::given(path("/email"))
         .and(method("POST"))
@@ -43,17 +143,142 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
         .mount(&app.email_server)
         .await;
 
-    let newsletter_request_body = serde_json::json!({
+    // The subscriber has no time zone, so the default (UTC) applies
+    let send_time = (chrono::Utc::now() + chrono::Duration::hours(2)).format("%H:%M").to_string();
+    let response = app.post_publish_newsletter(&serde_json::json!({
         "title": "Newsletter title",
-        "content": {
-            "text": "Newsletter body as plain text",
-            "html": "<p>Newsletter body as HTML</p>"
-        }
-    });
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "local_send_time": send_time,
+    })).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    app.dispatch_all_pending_emails().await;
+    let queued = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(queued.count, 1);
+
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+}
+
+
+#[actix_rt::test]
+async fn a_paused_issue_is_not_delivered_until_it_is_resumed() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_publish_newsletter(&newsletter_form()).await;
+    let issue_page = response.headers().get("Location").unwrap().to_str().unwrap().to_owned();
 
-    let response = app.post_newsletters(newsletter_request_body).await;
+    let response = app.post_issue_delivery_action(&issue_page, "pause").await;
+    assert_eq!(response.status().as_u16(), 303);
+    app.dispatch_all_pending_emails().await;
 
-    assert_eq!(response.status().as_u16(), 200)
+    let html_page = app.get_newsletter_issue(&issue_page).await.text().await.unwrap();
+    assert!(html_page.contains("Delivery is paused: 0 emails sent, 1 waiting"));
+
+    app.post_issue_delivery_action(&issue_page, "resume").await;
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_newsletter_issue(&issue_page).await.text().await.unwrap();
+    assert!(html_page.contains("Delivery is sending: 1 emails sent, 0 waiting"));
+}
+
+
+#[actix_rt::test]
+async fn a_cancelled_issue_drops_its_pending_deliveries() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_publish_newsletter(&newsletter_form()).await;
+    let issue_page = response.headers().get("Location").unwrap().to_str().unwrap().to_owned();
+
+    app.post_issue_delivery_action(&issue_page, "cancel").await;
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_newsletter_issue(&issue_page).await.text().await.unwrap();
+    assert!(html_page.contains("Delivery cancelled, 1 pending emails will not be sent."));
+
+    // Cancelling is final
+    app.post_issue_delivery_action(&issue_page, "resume").await;
+    let html_page = app.get_newsletter_issue(&issue_page).await.text().await.unwrap();
+    assert!(html_page.contains("An issue that is cancelled cannot be resumed."));
+}
+
+
+#[actix_rt::test]
+async fn a_correction_only_goes_to_subscribers_who_received_the_issue() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_publish_newsletter(&newsletter_form()).await;
+    let issue_page = response.headers().get("Location").unwrap().to_str().unwrap().to_owned();
+    app.dispatch_all_pending_emails().await;
+
+    // Subscribed after the issue went out
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, normalized_email, name, subscribed_at, status)
+        VALUES ($1, 'late@example.com', 'late@example.com', 'late comer', now(), 'confirmed')
+        "#,
+        Uuid::new_v4(),
+    )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app.post_correction(&issue_page, &serde_json::json!({
+        "title": "Correction: Newsletter title",
+        "text_content": "Corrected body as plain text",
+        "html_content": "<p>Corrected body as HTML</p>",
+    })).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let correction_page = response.headers().get("Location").unwrap().to_str().unwrap().to_owned();
+    assert_ne!(correction_page, issue_page);
+
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_newsletter_issue(&correction_page).await.text().await.unwrap();
+    assert!(html_page.contains("will go out to 1 recipients"));
+    assert!(html_page.contains("1 emails sent"));
+}
+
+
+fn newsletter_form() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })
 }
 
 
@@ -85,13 +310,12 @@ async fn newsletters_returns_400_for_invalid_data() {
 }
 
 
-
 #[tokio::test]
 async fn requests_missing_authorization_are_rejected() {
     let app = spawn_app().await;
 
     let response = reqwest::Client::new()
-        .post(&format!("{}/newsletters", &app.address))
+        .post(format!("{}/newsletters", &app.address))
         .json(&serde_json::json!({
             "title": "Newsletter title",
             "content": {
@@ -114,7 +338,7 @@ async fn non_existing_user_is_rejected() {
     let password = app.test_user.password;
 
     let response = reqwest::Client::new()
-        .post(&format!("{}/newsletters", &app.address))
+        .post(format!("{}/newsletters", &app.address))
         .basic_auth(username, Some(password))
         .json(&serde_json::json!({
             "title": "Newsletter title",
@@ -143,7 +367,7 @@ async fn invalid_password_is_rejected() {
     assert_ne!(app.test_user.password, password);
 
     let response = reqwest::Client::new()
-        .post(&format!("{}/newsletters", &app.address))
+        .post(format!("{}/newsletters", &app.address))
         .basic_auth(username, Some(password))
         .json(&serde_json::json!({
             "title": "Newsletter title",
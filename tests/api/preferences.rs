@@ -0,0 +1,75 @@
+use crate::helpers::app::spawn_app;
+use crate::helpers::email::create_confirmed_subscriber;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+
+#[actix_rt::test]
+async fn issues_link_to_a_preferences_page_that_updates_the_time_zone() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app.email_server.received_requests().await.unwrap().pop().unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let text_body = body["TextBody"].as_str().unwrap();
+    let link = text_body.lines().last().unwrap().rsplit(' ').next().unwrap();
+    let mut preferences_link = reqwest::Url::parse(link).unwrap();
+    preferences_link.set_port(Some(app.port)).unwrap();
+    let token = preferences_link.query_pairs().find(|(k, _)| k == "token").unwrap().1.into_owned();
+
+    // Act - Part 1 - Open the preferences page
+    let response = app.api_client.get(preferences_link).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Act - Part 2 - Pick a time zone
+    let response = app.api_client
+        .post(format!("{}/subscriptions/preferences", &app.address))
+        .form(&serde_json::json!({ "token": token, "time_zone": "Asia/Tokyo" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 303);
+
+    // Assert
+    let saved = sqlx::query!("SELECT time_zone FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.time_zone.as_deref(), Some("Asia/Tokyo"));
+}
+
+
+#[actix_rt::test]
+async fn preferences_are_rejected_with_a_forged_token() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.api_client
+        .get(format!(
+            "{}/subscriptions/preferences?token={}.{}",
+            &app.address, uuid::Uuid::new_v4(), "00".repeat(32),
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
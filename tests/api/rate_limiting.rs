@@ -0,0 +1,162 @@
+use zero2prod::configuration::rate_limit_settings::{LimitSettings, RouteRateLimitSettings};
+
+use crate::helpers::app::spawn_app_with;
+
+
+fn route(path: &str) -> RouteRateLimitSettings {
+    RouteRateLimitSettings { path: path.into(), per_ip: None, per_target: None, per_route: None }
+}
+
+fn limit(requests: u32) -> Option<LimitSettings> {
+    Some(LimitSettings { requests, window_seconds: 3600 })
+}
+
+
+#[actix_rt::test]
+async fn signups_beyond_the_per_ip_limit_get_a_429_with_retry_after() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.rate_limits.routes = vec![RouteRateLimitSettings { per_ip: limit(2), ..route("/subscriptions") }];
+    }).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    for i in 0..2 {
+        let response = app.post_subscription(format!("name=le%20guin&email=ursula{}%40gmail.com", i)).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let response = app.post_subscription("name=le%20guin&email=ursula2%40gmail.com".into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 429);
+    let retry_after: u64 = response.headers()["Retry-After"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 0 && retry_after <= 3600);
+}
+
+
+#[actix_rt::test]
+async fn repeated_signups_for_the_same_address_are_limited() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.rate_limits.routes = vec![RouteRateLimitSettings { per_target: limit(1), ..route("/subscriptions") }];
+    }).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    let first = app.post_subscription("name=le%20guin&email=ursula%40gmail.com".into()).await;
+    let same_address = app.post_subscription("name=le%20guin&email=Ursula%40Gmail.com".into()).await;
+    let other_address = app.post_subscription("name=le%20guin&email=le.guin%40gmail.com".into()).await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(same_address.status().as_u16(), 429);
+    assert!(same_address.headers().contains_key("Retry-After"));
+    assert_eq!(other_address.status().as_u16(), 200);
+}
+
+
+#[actix_rt::test]
+async fn the_route_limit_is_shared_by_every_client() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.rate_limits.routes = vec![RouteRateLimitSettings { per_route: limit(1), ..route("/subscriptions/resend") }];
+    }).await;
+
+    // Act
+    let first = app.post_resend_confirmation("ursula@gmail.com").await;
+    let second = app.post_resend_confirmation("le.guin@gmail.com").await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 429);
+}
+
+
+#[actix_rt::test]
+async fn login_attempts_for_the_same_username_are_limited() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.rate_limits.routes = vec![RouteRateLimitSettings { per_target: limit(2), ..route("/login") }];
+    }).await;
+    let login_body = serde_json::json!({ "username": "random-username", "password": "random-password" });
+
+    // Act
+    for _ in 0..2 {
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 303);
+    }
+    let response = app.post_login(&login_body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+
+#[actix_rt::test]
+async fn pages_are_not_rate_limited() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.rate_limits.routes = vec![RouteRateLimitSettings { per_ip: limit(0), ..route("/login") }];
+    }).await;
+
+    // Act
+    let page = app.get_login().await;
+    let submission = app.post_login(&serde_json::json!({ "username": "u", "password": "p" })).await;
+
+    // Assert
+    assert_eq!(page.status().as_u16(), 200);
+    assert_eq!(submission.status().as_u16(), 429);
+}
+
+
+#[actix_rt::test]
+async fn clients_behind_a_trusted_proxy_are_limited_separately() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.trusted_proxies = vec!["127.0.0.1/32".parse().unwrap()];
+        c.rate_limits.routes = vec![RouteRateLimitSettings { per_ip: limit(1), ..route("/subscriptions") }];
+    }).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    let mut statuses = Vec::new();
+    for (i, client) in ["198.51.100.1", "198.51.100.2", "198.51.100.1"].iter().enumerate() {
+        let response = app.api_client
+            .post(format!("{}/subscriptions", &app.address))
+            .header("X-Forwarded-For", *client)
+            .form(&[("name", "le guin"), ("email", &format!("ursula{}@gmail.com", i))])
+            .send()
+            .await
+            .unwrap();
+        statuses.push(response.status().as_u16());
+    }
+
+    // Assert
+    assert_eq!(statuses, vec![200, 200, 429]);
+}
+
+
+#[actix_rt::test]
+async fn forwarding_headers_of_untrusted_peers_are_ignored() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.rate_limits.routes = vec![RouteRateLimitSettings { per_ip: limit(1), ..route("/subscriptions") }];
+    }).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    let mut statuses = Vec::new();
+    for (i, client) in ["198.51.100.1", "198.51.100.2"].iter().enumerate() {
+        let response = app.api_client
+            .post(format!("{}/subscriptions", &app.address))
+            .header("X-Forwarded-For", *client)
+            .form(&[("name", "le guin"), ("email", &format!("ursula{}@gmail.com", i))])
+            .send()
+            .await
+            .unwrap();
+        statuses.push(response.status().as_u16());
+    }
+
+    // Assert
+    assert_eq!(statuses, vec![200, 429]);
+}
@@ -0,0 +1,228 @@
+use uuid::Uuid;
+use zero2prod::helpers::preferences::preferences_url;
+
+use crate::helpers::app::{spawn_app, TestApp};
+use crate::helpers::email::{create_confirmed_subscriber, ConfirmationLinks};
+
+const REFERRER_EMAIL: &str = "ursulua_le_guin@gmail.com";
+
+
+/// The id and referral code of the confirmed subscriber created by `create_confirmed_subscriber`.
+async fn referrer(app: &TestApp) -> (Uuid, String) {
+    let referrer = sqlx::query!("SELECT id, referral_code FROM subscriptions WHERE email = $1", REFERRER_EMAIL)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    (referrer.id, referrer.referral_code.expect("Confirmed subscribers have a referral code."))
+}
+
+/// Sign a friend up through the share link, returning their confirmation links.
+async fn refer_a_friend(app: &TestApp, referral_code: &str) -> ConfirmationLinks {
+    app.post_subscription(format!("name=Octavia&email=octavia%40example.com&referral_code={}", referral_code))
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = app.email_server.received_requests().await.unwrap().pop().unwrap();
+    app.get_confirmation_links(&email_request)
+}
+
+/// The subjects of the emails sent so far to `recipient`.
+async fn subjects_sent_to(app: &TestApp, recipient: &str) -> Vec<String> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| serde_json::from_slice::<serde_json::Value>(&request.body).unwrap())
+        .filter(|body| body["To"] == recipient)
+        .map(|body| body["Subject"].as_str().unwrap().to_owned())
+        .collect()
+}
+
+
+#[actix_rt::test]
+async fn you_must_be_logged_in_to_manage_referral_rewards() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let page = app.get_referrals().await;
+    let response = app.post_referral_reward(&serde_json::json!({
+        "milestone": "3",
+        "subject": "Thank you",
+        "text_content": "Hi",
+        "html_content": "<p>Hi</p>",
+    })).await;
+
+    // Assert
+    assert_eq!(page.headers().get("Location").unwrap(), "/login");
+    assert_eq!(response.headers().get("Location").unwrap(), "/login");
+    let rewards = sqlx::query!("SELECT count(*) AS \"count!\" FROM referral_rewards")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(rewards, 0);
+}
+
+#[actix_rt::test]
+async fn confirmed_subscribers_find_their_share_link_in_the_preference_center() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let (subscriber_id, referral_code) = referrer(&app).await;
+
+    // Act
+    let html_page = app.api_client
+        .get(preferences_url(&app.address, &app.hmac_secret, subscriber_id))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains(&format!("{}/?ref={}", app.base_url, referral_code)));
+    assert!(html_page.contains("0 friend(s) joined through your link"));
+}
+
+#[actix_rt::test]
+async fn the_share_link_carries_the_referral_code_into_the_signup_form() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let html_page = app.api_client
+        .get(format!("{}/?ref=aB3d9%22", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains(r#"<input type="hidden" name="referral_code" value="aB3d9">"#));
+}
+
+#[actix_rt::test]
+async fn referrals_are_credited_once_the_friend_confirms() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let (referrer_id, referral_code) = referrer(&app).await;
+    app.mount_ok_email_server().await;
+
+    // Act - Part 1 - The friend signs up
+    let confirmation_links = refer_a_friend(&app, &referral_code).await;
+    let friend = sqlx::query!("SELECT referred_by, referral_credited_at FROM subscriptions WHERE name = 'Octavia'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(friend.referred_by, Some(referrer_id));
+    assert!(friend.referral_credited_at.is_none());
+
+    // Act - Part 2 - The friend confirms
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+
+    // Assert
+    let friend = sqlx::query!("SELECT referral_credited_at FROM subscriptions WHERE name = 'Octavia'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(friend.referral_credited_at.is_some());
+    app.login_as_admin().await;
+    let html_page = app.get_referrals().await.text().await.unwrap();
+    assert!(html_page.contains(&format!(
+        "<td>{}</td><td>1</td><td>0</td>",
+        referral_code,
+    )));
+}
+
+#[actix_rt::test]
+async fn a_failed_credit_leaves_the_confirmation_link_valid() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let (_, referral_code) = referrer(&app).await;
+    app.mount_ok_email_server().await;
+    let confirmation_links = refer_a_friend(&app, &referral_code).await;
+    // Sabotage the credit
+    sqlx::query!("ALTER TABLE subscriptions ADD CONSTRAINT no_credits CHECK (referral_credited_at IS NULL) NOT VALID")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act - Part 1 - The credit fails
+    let response = app.post_confirmation(&confirmation_links.html).await;
+    assert_eq!(response.status().as_u16(), 500);
+    let friend = sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions WHERE name = 'Octavia'"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(friend.status, "pending_confirmation");
+
+    // Act - Part 2 - The same link works once crediting does
+    sqlx::query!("ALTER TABLE subscriptions DROP CONSTRAINT no_credits")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+
+    // Assert
+    let friend = sqlx::query!("SELECT referral_credited_at FROM subscriptions WHERE name = 'Octavia'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(friend.referral_credited_at.is_some());
+}
+
+#[actix_rt::test]
+async fn unknown_referral_codes_do_not_prevent_signing_up() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    refer_a_friend(&app, "nosuchcode").await;
+
+    // Assert
+    let friend = sqlx::query!("SELECT referred_by FROM subscriptions WHERE name = 'Octavia'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(friend.referred_by.is_none());
+}
+
+#[actix_rt::test]
+async fn reaching_a_milestone_sends_the_reward_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    let response = app.post_referral_reward(&serde_json::json!({
+        "milestone": "1",
+        "subject": "{{ referral_count }} friend joined, thank you {{ name }}!",
+        "text_content": "Keep sharing {{ share_url }}",
+        "html_content": "<p>Keep sharing {{ share_url }}</p>",
+    })).await;
+    assert_eq!(response.status().as_u16(), 303);
+    create_confirmed_subscriber(&app).await;
+    let (_, referral_code) = referrer(&app).await;
+    app.mount_ok_email_server().await;
+    let confirmation_links = refer_a_friend(&app, &referral_code).await;
+
+    // Act
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+
+    // Assert
+    let subjects = subjects_sent_to(&app, REFERRER_EMAIL).await;
+    assert_eq!(subjects.iter().filter(|subject| subject.as_str() == "1 friend joined, thank you le guin!").count(), 1);
+    let rewarded = sqlx::query!("SELECT milestone FROM referral_rewards_sent")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .milestone;
+    assert_eq!(rewarded, 1);
+}
@@ -0,0 +1,298 @@
+use uuid::Uuid;
+
+use crate::helpers::app::{spawn_app, TestApp};
+use crate::helpers::email::{create_confirmed_subscriber, create_unconfirmed_subcriber};
+
+
+/// A sequence with a first step on the day of enrollment and a second one three days later.
+async fn create_sequence(app: &TestApp, trigger: &str, trigger_tag: &str) -> Uuid {
+    let response = app.post_sequence(&serde_json::json!({
+        "name": "Onboarding",
+        "trigger": trigger,
+        "trigger_tag": trigger_tag,
+    })).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let sequence_id = sqlx::query!("SELECT sequence_id FROM sequences WHERE name = 'Onboarding'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .sequence_id;
+
+    add_step(app, sequence_id, "0", "Day one, {{ name }}", "").await;
+    add_step(app, sequence_id, "3", "Day three", "").await;
+    sequence_id
+}
+
+async fn add_step(app: &TestApp, sequence_id: Uuid, delay_days: &str, subject: &str, condition_kind: &str) {
+    let response = app.post_sequence_step(sequence_id, &serde_json::json!({
+        "delay_days": delay_days,
+        "subject": subject,
+        "text_content": "Hi there",
+        "html_content": "<p>Hi there</p>",
+        "condition_kind": condition_kind,
+    })).await;
+    assert_eq!(response.status().as_u16(), 303);
+}
+
+async fn subscriber_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id
+}
+
+async fn enrollment(app: &TestApp) -> (Uuid, String, i16) {
+    let enrollment = sqlx::query!("SELECT enrollment_id, status, next_position FROM sequence_enrollments")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    (enrollment.enrollment_id, enrollment.status, enrollment.next_position)
+}
+
+/// Pretend the enrollment is due, instead of waiting for days.
+async fn make_next_step_due(app: &TestApp) {
+    sqlx::query!("UPDATE sequence_enrollments SET next_step_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+/// The subjects of the emails sent so far, in order.
+async fn sent_subjects(app: &TestApp) -> Vec<String> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            body["Subject"].as_str().unwrap().to_owned()
+        })
+        .collect()
+}
+
+
+#[actix_rt::test]
+async fn you_must_be_logged_in_to_define_sequences() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let page = app.get_sequences().await;
+    let response = app.post_sequence(&serde_json::json!({
+        "name": "Onboarding",
+        "trigger": "confirmation",
+    })).await;
+
+    // Assert
+    assert_eq!(page.headers().get("Location").unwrap(), "/login");
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), "/login");
+    let sequences = sqlx::query!("SELECT count(*) AS \"count!\" FROM sequences")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(sequences, 0);
+}
+
+#[actix_rt::test]
+async fn a_step_cannot_be_scheduled_before_the_previous_one() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    let sequence_id = create_sequence(&app, "confirmation", "").await;
+
+    // Act
+    add_step(&app, sequence_id, "1", "Too early", "").await;
+
+    // Assert
+    let html_page = app.get_sequence(sequence_id).await.text().await.unwrap();
+    assert!(html_page.contains("<p><i>The delay must be at least 3 days, the delay of the previous step.</i></p>"));
+    assert!(!html_page.contains("Too early"));
+}
+
+#[actix_rt::test]
+async fn confirming_enrolls_the_subscriber_and_sends_the_first_step() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    create_sequence(&app, "confirmation", "").await;
+    let confirmation_links = create_unconfirmed_subcriber(&app).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+    app.dispatch_all_automation_steps().await;
+    app.dispatch_all_automation_steps().await;
+
+    // Assert - the second step is not due for three days
+    let subjects = sent_subjects(&app).await;
+    assert_eq!(subjects.len(), 2);
+    assert_eq!(subjects[1], "Day one, le guin");
+    let (_, status, next_position) = enrollment(&app).await;
+    assert_eq!((status.as_str(), next_position), ("active", 1));
+}
+
+#[actix_rt::test]
+async fn a_failed_enrollment_leaves_the_confirmation_link_valid() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    create_sequence(&app, "confirmation", "").await;
+    let confirmation_links = create_unconfirmed_subcriber(&app).await;
+    app.mount_ok_email_server().await;
+    // Sabotage the enrollments
+    sqlx::query!("ALTER TABLE sequence_enrollments ADD CONSTRAINT no_enrollments CHECK (false) NOT VALID")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act - Part 1 - The enrollment fails
+    let response = app.post_confirmation(&confirmation_links.html).await;
+    assert_eq!(response.status().as_u16(), 500);
+    let saved = sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+
+    // Act - Part 2 - The same link works once enrolling does
+    sqlx::query!("ALTER TABLE sequence_enrollments DROP CONSTRAINT no_enrollments")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+
+    // Assert
+    let (_, status, _) = enrollment(&app).await;
+    assert_eq!(status, "active");
+}
+
+#[actix_rt::test]
+async fn the_enrollment_completes_once_the_last_step_is_due() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    let sequence_id = create_sequence(&app, "confirmation", "").await;
+    create_confirmed_subscriber(&app).await;
+    app.mount_ok_email_server().await;
+    app.dispatch_all_automation_steps().await;
+
+    // Act
+    make_next_step_due(&app).await;
+    app.dispatch_all_automation_steps().await;
+
+    // Assert
+    let subjects = sent_subjects(&app).await;
+    assert_eq!(subjects.last().unwrap(), "Day three");
+    let (_, status, _) = enrollment(&app).await;
+    assert_eq!(status, "completed");
+    let html_page = app.get_sequence(sequence_id).await.text().await.unwrap();
+    assert!(html_page.contains("<td>2</td><td>3</td><td>Day three</td><td>-</td><td>1</td><td>0</td><td>0</td><td>0</td>"));
+}
+
+#[actix_rt::test]
+async fn steps_whose_condition_is_not_met_are_skipped() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    let sequence_id = create_sequence(&app, "confirmation", "").await;
+    add_step(&app, sequence_id, "5", "Thanks for reading", "engaged").await;
+    create_confirmed_subscriber(&app).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    app.dispatch_all_automation_steps().await;
+    make_next_step_due(&app).await;
+    app.dispatch_all_automation_steps().await;
+    make_next_step_due(&app).await;
+    app.dispatch_all_automation_steps().await;
+
+    // Assert - the subscriber never opened an issue
+    let subjects = sent_subjects(&app).await;
+    assert!(!subjects.contains(&"Thanks for reading".to_string()));
+    let outcome = sqlx::query!("SELECT outcome FROM sequence_step_runs WHERE position = 2")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .outcome;
+    assert_eq!(outcome, "skipped");
+    let (_, status, _) = enrollment(&app).await;
+    assert_eq!(status, "completed");
+}
+
+#[actix_rt::test]
+async fn a_stopped_enrollment_gets_no_more_steps() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    create_sequence(&app, "confirmation", "").await;
+    create_confirmed_subscriber(&app).await;
+    app.mount_ok_email_server().await;
+    app.dispatch_all_automation_steps().await;
+    let (enrollment_id, _, _) = enrollment(&app).await;
+    let sent_before = sent_subjects(&app).await.len();
+
+    // Act
+    let response = app.post_stop_enrollment(subscriber_id(&app).await, enrollment_id).await;
+    make_next_step_due(&app).await;
+    app.dispatch_all_automation_steps().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(sent_subjects(&app).await.len(), sent_before);
+    let (_, status, _) = enrollment(&app).await;
+    assert_eq!(status, "stopped");
+}
+
+#[actix_rt::test]
+async fn subscribers_who_unsubscribe_leave_their_sequences() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    create_sequence(&app, "confirmation", "").await;
+    create_confirmed_subscriber(&app).await;
+    app.mount_ok_email_server().await;
+    let sent_before = sent_subjects(&app).await.len();
+
+    // Act
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_automation_steps().await;
+
+    // Assert
+    assert_eq!(sent_subjects(&app).await.len(), sent_before);
+    let (_, status, _) = enrollment(&app).await;
+    assert_eq!(status, "stopped");
+}
+
+#[actix_rt::test]
+async fn tagging_a_subscriber_enrolls_them_in_the_sequences_of_the_tag() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    create_sequence(&app, "tag", "trial").await;
+    create_confirmed_subscriber(&app).await;
+    app.mount_ok_email_server().await;
+    let subscriber_id = subscriber_id(&app).await;
+    let enrolled_on_confirmation = sqlx::query!("SELECT count(*) AS \"count!\" FROM sequence_enrollments")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+
+    // Act
+    let response = app.post_subscriber_tag(subscriber_id, "trial").await;
+    app.dispatch_all_automation_steps().await;
+
+    // Assert
+    assert_eq!(enrolled_on_confirmation, 0);
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(sent_subjects(&app).await.last().unwrap(), "Day one, le guin");
+    let (_, status, next_position) = enrollment(&app).await;
+    assert_eq!((status.as_str(), next_position), ("active", 1));
+}
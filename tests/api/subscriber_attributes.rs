@@ -0,0 +1,189 @@
+
+use crate::helpers::app::{spawn_app, TestApp};
+
+
+async fn define_attributes(app: &TestApp) {
+    let company = app.post_subscriber_attribute(&serde_json::json!({
+        "key": "company",
+        "label": "Company",
+        "kind": "text",
+        "required": "on",
+    })).await;
+    assert_eq!(company.status().as_u16(), 303);
+    let seats = app.post_subscriber_attribute(&serde_json::json!({
+        "key": "seats",
+        "label": "Seats",
+        "kind": "number",
+    })).await;
+    assert_eq!(seats.status().as_u16(), 303);
+}
+
+
+#[actix_rt::test]
+async fn you_must_be_logged_in_to_define_attributes() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_subscriber_attribute(&serde_json::json!({
+        "key": "company",
+        "label": "Company",
+        "kind": "text",
+    })).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), "/login");
+}
+
+
+#[actix_rt::test]
+async fn invalid_attribute_definitions_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+
+    // Act
+    app.post_subscriber_attribute(&serde_json::json!({
+        "key": "role",
+        "label": "Role",
+        "kind": "enum",
+        "options": "",
+    })).await;
+
+    // Assert
+    let html_page = app.get_subscriber_attributes().await.text().await.unwrap();
+    assert!(html_page.contains("<p><i>An enum attribute needs at least one option.</i></p>"));
+}
+
+
+#[actix_rt::test]
+async fn defined_attributes_are_asked_on_the_signup_form() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    define_attributes(&app).await;
+
+    // Act
+    let html_page = app.get_home().await.text().await.unwrap();
+
+    // Assert
+    assert!(html_page.contains(r#"<input type="text" name="company" required>"#));
+    assert!(html_page.contains(r#"<input type="number" step="any" name="seats">"#));
+}
+
+
+#[actix_rt::test]
+async fn submitted_attributes_are_validated_and_stored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    define_attributes(&app).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    let response = app
+        .post_subscription("name=le%20guin&email=ursula_le_guin%40gmail.com&company=Acme&seats=12.0".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!(
+        r#"
+        SELECT a.key, v.value FROM subscriber_attribute_values v
+        JOIN subscriber_attributes a ON a.attribute_id = v.attribute_id
+        ORDER BY a.key
+        "#,
+    )
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    let saved: Vec<_> = saved.into_iter().map(|r| (r.key, r.value)).collect();
+    assert_eq!(saved, vec![("company".to_string(), "Acme".to_string()), ("seats".to_string(), "12".to_string())]);
+}
+
+
+#[actix_rt::test]
+async fn subscribe_returns_a_400_for_invalid_or_missing_attributes() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    define_attributes(&app).await;
+    let test_cases = vec![
+        ("name=le%20guin&email=ursula_le_guin%40gmail.com", "missing required attribute"),
+        ("name=le%20guin&email=ursula_le_guin%40gmail.com&company=Acme&seats=many", "not a number"),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app.post_subscription(body.into()).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            description
+        );
+    }
+}
+
+
+#[actix_rt::test]
+async fn attributes_fill_in_the_merge_fields_of_an_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    define_attributes(&app).await;
+    app.mount_ok_email_server().await;
+    app.post_subscription("name=le%20guin&email=ursula_le_guin%40gmail.com&company=Smith%20%26%20Sons".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = app.email_server.received_requests().await.unwrap().pop().unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+
+    // Act
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "News for {{ company }}",
+        "text_content": "Hi {{name}}, you have {{ seats | no }} seats.",
+        "html_content": "<p>Hi {{name}} from {{company}}</p>",
+    })).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app.email_server.received_requests().await.unwrap().pop().unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["Subject"], "News for Smith & Sons");
+    assert!(body["TextBody"].as_str().unwrap().starts_with("Hi le guin, you have no seats."));
+    assert!(body["HtmlBody"].as_str().unwrap().contains("<p>Hi le guin from Smith &amp; Sons</p>"));
+}
+
+
+#[actix_rt::test]
+async fn exports_have_attribute_columns_and_can_be_segmented_by_attribute() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    define_attributes(&app).await;
+    app.mount_ok_email_server().await;
+    app.post_subscription("name=ada&email=ada%40example.com&company=Acme&seats=3".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    app.post_subscription("name=grace&email=grace%40example.com&company=Navy".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let all = app.get_export_subscribers("format=csv").await.text().await.unwrap();
+    let segment = app.get_export_subscribers("attribute=seats&attribute_value=3.0").await.text().await.unwrap();
+
+    // Assert
+    assert!(all.lines().next().unwrap().ends_with(",company,seats"));
+    assert!(all.lines().any(|line| line.contains("ada@example.com") && line.ends_with(",Acme,3")));
+    assert_eq!(segment.lines().count(), 2);
+    assert!(segment.contains("ada@example.com"));
+}
@@ -0,0 +1,160 @@
+use secrecy::Secret;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::helpers::preferences::PREFERENCES_TOKEN_PURPOSE;
+use zero2prod::helpers::signed_token::sign_subscriber_id;
+use zero2prod::helpers::suppression::email_hash;
+
+use crate::helpers::app::{spawn_app, TestApp};
+use crate::helpers::email::create_confirmed_subscriber;
+
+
+async fn subscriber_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id
+}
+
+fn token(secret: &Secret<String>, subscriber_id: Uuid) -> String {
+    sign_subscriber_id(secret, PREFERENCES_TOKEN_PURPOSE, subscriber_id)
+}
+
+
+#[actix_rt::test]
+async fn a_subscriber_can_download_their_data() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })).await;
+    app.dispatch_all_pending_emails().await;
+    let id = subscriber_id(&app).await;
+
+    // Act
+    let response = app.api_client
+        .get(format!("{}/subscriptions/data?token={}", &app.address, token(&app.hmac_secret, id)))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let export: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(export["subscription"]["email"], "ursulua_le_guin@gmail.com");
+    assert_eq!(export["confirmation_tokens"].as_array().unwrap().len(), 1);
+    assert_eq!(export["consent_events"].as_array().unwrap().len(), 2);
+    assert_eq!(export["deliveries"][0]["title"], "Newsletter title");
+}
+
+
+#[actix_rt::test]
+async fn erasing_a_subscriber_keeps_anonymous_statistics_and_a_suppression() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })).await;
+    app.dispatch_all_pending_emails().await;
+    let id = subscriber_id(&app).await;
+
+    // Act
+    let response = app.api_client
+        .post(format!("{}/subscriptions/erase", &app.address))
+        .form(&serde_json::json!({ "token": token(&app.hmac_secret, id) }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let subscribers = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(subscribers.count, 0);
+    let deliveries = sqlx::query!("SELECT subscriber_id FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(deliveries.subscriber_id.is_none());
+    let suppression = sqlx::query!("SELECT email_hash, reason FROM suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(suppression.email_hash, email_hash("ursulua_le_guin@gmail.com"));
+    assert_eq!(suppression.reason, "erasure");
+}
+
+
+#[actix_rt::test]
+async fn admins_can_export_and_erase_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+    let id = subscriber_id(&app).await;
+
+    // Act - Part 1 - Export
+    let response = app.api_client
+        .get(format!("{}/admin/subscribers/{}/export", &app.address, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let export: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(export["subscription"]["id"], id.to_string());
+
+    // Act - Part 2 - Erase
+    let response = app.api_client
+        .post(format!("{}/admin/subscribers/{}/erase", &app.address, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 303);
+
+    // Assert
+    let html_page = app.get_admin_dashboard().await.text().await.unwrap();
+    assert!(html_page.contains("The subscriber has been erased."));
+}
+
+
+#[actix_rt::test]
+async fn data_requests_with_a_forged_token_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let id = subscriber_id(&app).await;
+    let forged = token(&Secret::new("not-our-secret".to_string()), id);
+
+    // Act
+    let response = app.api_client
+        .post(format!("{}/subscriptions/erase", &app.address))
+        .form(&serde_json::json!({ "token": forged }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,128 @@
+use crate::helpers::app::spawn_app;
+
+const CSV: &str = "email,name,status,tags,subscribed_at\n\
+                   ada@example.com,Ada,confirmed,vip,2021-06-01\n\
+                   grace@example.com,Grace,unsubscribed,,2021-07-01\n\
+                   linus@example.com,Linus,confirmed,,2022-01-15\n";
+
+
+#[actix_rt::test]
+async fn you_must_be_logged_in_to_export_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_export_subscribers("format=csv").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), "/login");
+}
+
+
+#[actix_rt::test]
+async fn every_subscriber_is_exported_as_csv_by_default() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    app.import_subscribers(CSV).await;
+
+    // Act
+    let response = app.get_export_subscribers("").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("Content-Type").unwrap(), "text/csv; charset=utf-8");
+    let disposition = response.headers().get("Content-Disposition").unwrap().to_str().unwrap().to_owned();
+    assert!(disposition.starts_with("attachment"));
+    assert!(disposition.contains(".csv"));
+    let body = response.text().await.unwrap();
+    let mut lines = body.lines();
+    assert!(lines.next().unwrap().starts_with("id,email,name,status,subscribed_at"));
+    assert_eq!(lines.count(), 3);
+}
+
+
+#[actix_rt::test]
+async fn an_export_without_matches_still_has_a_header() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+
+    // Act
+    let body = app.get_export_subscribers("format=csv").await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(body.lines().count(), 1);
+}
+
+
+#[actix_rt::test]
+async fn exports_are_filtered_by_status_tag_and_date_range() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    app.import_subscribers(CSV).await;
+
+    // Act
+    let by_status = app.get_export_subscribers("status=confirmed").await.text().await.unwrap();
+    let by_tag = app.get_export_subscribers("tag=vip").await.text().await.unwrap();
+    let by_date = app.get_export_subscribers("from=2021-07-01&to=2021-12-31").await.text().await.unwrap();
+
+    // Assert
+    assert!(by_status.contains("ada@example.com") && by_status.contains("linus@example.com"));
+    assert!(!by_status.contains("grace@example.com"));
+    assert!(by_tag.contains("ada@example.com"));
+    assert_eq!(by_tag.lines().count(), 2);
+    assert!(by_date.contains("grace@example.com"));
+    assert_eq!(by_date.lines().count(), 2);
+}
+
+
+#[actix_rt::test]
+async fn ndjson_exports_have_one_object_per_line() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    app.import_subscribers(CSV).await;
+
+    // Act
+    let response = app.get_export_subscribers("format=ndjson&tag=vip").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("Content-Type").unwrap(), "application/x-ndjson");
+    let body = response.text().await.unwrap();
+    let subscribers: Vec<serde_json::Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(subscribers.len(), 1);
+    assert_eq!(subscribers[0]["email"], "ada@example.com");
+    assert_eq!(subscribers[0]["tags"], serde_json::json!(["vip"]));
+    assert_eq!(subscribers[0]["consent_source"], "csv-import");
+}
+
+
+#[actix_rt::test]
+async fn invalid_criteria_are_reported_on_the_export_form() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+
+    // Act - Part 1 - Ask for an unknown format
+    let response = app.get_export_subscribers("format=xlsx").await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), "/admin/subscribers/export");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.api_client
+        .get(format!("{}/admin/subscribers/export", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<p><i>xlsx is not a supported export format.</i></p>"));
+}
@@ -0,0 +1,191 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::helpers::suppression::email_hash;
+
+use crate::helpers::app::spawn_app;
+use crate::helpers::email::create_confirmed_subscriber;
+
+const CSV: &str = "Email,Name,Status,Tags,Joined\n\
+                   ada@example.com,Ada,subscribed,\"vip, beta\",2021-06-01\n\
+                   grace@example.com,Grace,unsubscribed,,\n\
+                   not-an-email,Nobody,,,\n\
+                   ADA@example.com,Ada again,,,\n";
+
+
+fn import_form(action: &str, double_opt_in: bool) -> serde_json::Value {
+    let mut form = serde_json::json!({
+        "csv": CSV,
+        "email_column": "Email",
+        "name_column": "Name",
+        "status_column": "Status",
+        "tags_column": "Tags",
+        "subscribed_at_column": "Joined",
+        "action": action,
+    });
+    if double_opt_in {
+        form["double_opt_in"] = "on".into();
+    }
+    form
+}
+
+
+#[actix_rt::test]
+async fn you_must_be_logged_in_to_import_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_import_subscribers(&import_form("import", false)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), "/login");
+}
+
+
+#[actix_rt::test]
+async fn a_dry_run_summarises_the_rows_without_importing_them() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+
+    // Act
+    let response = app.post_import_subscribers(&import_form("preview", false)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("2 valid, 1 invalid and 1 duplicate rows."));
+    assert!(html_page.contains("Same address as line 2."));
+    let saved = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(saved.is_empty());
+}
+
+
+#[actix_rt::test]
+async fn valid_rows_are_imported_with_their_status_and_tags() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Import
+    let response = app.post_import_subscribers(&import_form("import", false)).await;
+    assert_eq!(response.headers().get("Location").unwrap(), "/admin/subscribers/import");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_import_subscribers().await.text().await.unwrap();
+    assert!(html_page.contains("Imported 2 subscribers. Skipped 1 invalid and 1 duplicate rows."));
+
+    // Assert
+    let saved = sqlx::query!(
+        r#"
+        SELECT s.email, s.status::text AS "status!", s.subscribed_at, array_remove(array_agg(t.name ORDER BY t.name), NULL) AS "tags!"
+        FROM subscriptions s
+        LEFT JOIN subscription_tags st ON st.subscriber_id = s.id
+        LEFT JOIN tags t ON t.tag_id = st.tag_id
+        GROUP BY s.id
+        ORDER BY s.email
+        "#
+    )
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 2);
+    assert_eq!(saved[0].email, "ada@example.com");
+    assert_eq!(saved[0].status, "confirmed");
+    assert_eq!(saved[0].tags, vec!["beta".to_string(), "vip".to_string()]);
+    assert_eq!(saved[0].subscribed_at.to_rfc3339(), "2021-06-01T00:00:00+00:00");
+    assert_eq!(saved[1].email, "grace@example.com");
+    assert_eq!(saved[1].status, "unsubscribed");
+
+    let consent = sqlx::query!("SELECT action, source FROM consent_events")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(consent.len(), 2);
+    assert!(consent.iter().all(|event| event.action == "import" && event.source == "csv-import"));
+}
+
+
+#[actix_rt::test]
+async fn double_opt_in_sends_a_confirmation_email_to_imported_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        // Unsubscribed rows stay unsubscribed and are not emailed
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_import_subscribers(&import_form("import", true)).await;
+
+    // Assert
+    let saved = sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions WHERE email = 'ada@example.com'"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+
+#[actix_rt::test]
+async fn existing_and_erased_addresses_are_skipped() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_as_admin().await;
+    sqlx::query!(
+        "INSERT INTO suppressions (email_hash, reason, suppressed_at) VALUES ($1, 'erasure', now())",
+        email_hash("grace@example.com"),
+    )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let csv = "email,name\nursulua_le_guin@gmail.com,Ursula\ngrace@example.com,Grace\nada@example.com,Ada\n";
+
+    // Act
+    let response = app.post_import_subscribers(&serde_json::json!({
+        "csv": csv,
+        "email_column": "email",
+        "name_column": "name",
+        "action": "preview",
+    })).await;
+
+    // Assert
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("1 valid, 1 invalid and 1 duplicate rows."));
+    assert!(html_page.contains("Already a subscriber."));
+}
+
+
+#[actix_rt::test]
+async fn an_unknown_column_is_reported() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+
+    // Act - Part 1 - Submit
+    let response = app.post_import_subscribers(&serde_json::json!({
+        "csv": "address\nada@example.com\n",
+        "email_column": "email",
+        "action": "preview",
+    })).await;
+    assert_eq!(response.headers().get("Location").unwrap(), "/admin/subscribers/import");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_import_subscribers().await.text().await.unwrap();
+    assert!(html_page.contains("The CSV has no email column."));
+}
@@ -0,0 +1,241 @@
+use crate::helpers::app::{spawn_app, TestApp};
+use crate::helpers::email::create_unconfirmed_subcriber;
+
+
+async fn import_sample(app: &TestApp) {
+    app.import_subscribers(
+        "email,name,status,tags,subscribed_at\n\
+         ada@example.com,Ada,confirmed,,2021-06-01\n\
+         grace@example.com,Grace,unsubscribed,,2021-07-01\n\
+         linus@example.com,Linus,confirmed,,2022-01-15\n",
+    ).await;
+}
+
+/// The email addresses listed on a page, in order.
+fn listed_emails(html_page: &str) -> Vec<String> {
+    html_page
+        .split(r#"<tr><td><a href="/admin/subscribers/"#)
+        .skip(1)
+        .map(|row| {
+            let start = row.find('>').unwrap() + 1;
+            let end = row.find("</a>").unwrap();
+            row[start..end].to_string()
+        })
+        .collect()
+}
+
+/// The `href` of the link with the given text.
+fn link(html_page: &str, text: &str) -> Option<String> {
+    let end = html_page.find(&format!(">{}</a>", text))?;
+    let start = html_page[..end].rfind("href=\"")? + "href=\"".len();
+    Some(html_page[start..end - 1].replace("&amp;", "&"))
+}
+
+
+#[actix_rt::test]
+async fn you_must_be_logged_in_to_browse_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_subscribers("").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), "/login");
+}
+
+
+#[actix_rt::test]
+async fn the_newest_subscribers_are_listed_first() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    import_sample(&app).await;
+
+    // Act
+    let html_page = app.get_subscribers("").await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(listed_emails(&html_page), vec!["linus@example.com", "grace@example.com", "ada@example.com"]);
+}
+
+
+#[actix_rt::test]
+async fn subscribers_can_be_searched_by_prefix_and_filtered() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    import_sample(&app).await;
+
+    // Act
+    let by_name = app.get_subscribers("?q=GRA").await.text().await.unwrap();
+    let by_email = app.get_subscribers("?q=ada%40").await.text().await.unwrap();
+    let infix = app.get_subscribers("?q=example").await.text().await.unwrap();
+    let confirmed = app.get_subscribers("?status=confirmed&from=2022-01-01").await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(listed_emails(&by_name), vec!["grace@example.com"]);
+    assert_eq!(listed_emails(&by_email), vec!["ada@example.com"]);
+    assert!(listed_emails(&infix).is_empty());
+    assert_eq!(listed_emails(&confirmed), vec!["linus@example.com"]);
+}
+
+
+#[actix_rt::test]
+async fn subscribers_can_be_sorted_by_column() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    import_sample(&app).await;
+
+    // Act
+    let ascending = app.get_subscribers("?sort=name").await.text().await.unwrap();
+    let descending = app.get_subscribers("?sort=name&dir=desc").await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(listed_emails(&ascending), vec!["ada@example.com", "grace@example.com", "linus@example.com"]);
+    assert_eq!(listed_emails(&descending), vec!["linus@example.com", "grace@example.com", "ada@example.com"]);
+    // The column currently sorted on links to the reverse order
+    assert_eq!(link(&ascending, "Name").unwrap(), "/admin/subscribers?sort=name&dir=desc");
+}
+
+
+#[actix_rt::test]
+async fn statuses_are_sorted_in_the_order_of_the_lifecycle() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    import_sample(&app).await;
+    create_unconfirmed_subcriber(&app).await;
+
+    // Act
+    let html_page = app.get_subscribers("?sort=status").await.text().await.unwrap();
+
+    // Assert
+    let emails = listed_emails(&html_page);
+    assert_eq!(emails[0], "ursulua_le_guin@gmail.com");
+    assert_eq!(emails[3], "grace@example.com");
+}
+
+
+#[actix_rt::test]
+async fn pages_follow_each_other_without_gaps_or_repeats() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    let mut csv = String::from("email,name,status,tags,subscribed_at\n");
+    for i in 0..60 {
+        // Pairs of subscribers share a name, so that ties are broken by id
+        csv.push_str(&format!("reader{:02}@example.com,Reader {:02},confirmed,,2021-01-01\n", i, i / 2));
+    }
+    app.import_subscribers(&csv).await;
+
+    // Act - Part 1 - First page
+    let first_page = app.get_subscribers("?sort=name").await.text().await.unwrap();
+    let next_page = link(&first_page, "Next page").expect("The first page has no link to the next one.");
+
+    // Act - Part 2 - Second page
+    let html_page = app.api_client
+        .get(format!("{}{}", &app.address, next_page))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    let mut listed = listed_emails(&first_page);
+    assert_eq!(listed.len(), 50);
+    listed.extend(listed_emails(&html_page));
+    listed.sort();
+    listed.dedup();
+    assert_eq!(listed.len(), 60);
+    assert!(link(&html_page, "Next page").is_none());
+    assert!(link(&html_page, "First page").is_some());
+}
+
+
+#[actix_rt::test]
+async fn every_sort_order_pages_through_all_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    let mut csv = String::from("email,name,status,tags,subscribed_at\n");
+    for i in 0..60 {
+        let status = if i % 2 == 0 { "confirmed" } else { "unsubscribed" };
+        csv.push_str(&format!(
+            "reader{:02}@example.com,Reader {:02},{},,2021-01-0{}\n", i, i / 2, status, i % 3 + 1,
+        ));
+    }
+    app.import_subscribers(&csv).await;
+
+    for sort in &["subscribed_at", "email", "name", "status"] {
+        for dir in &["asc", "desc"] {
+            // Act
+            let first_page = app.get_subscribers(&format!("?sort={}&dir={}", sort, dir))
+                .await.text().await.unwrap();
+            let next_page = link(&first_page, "Next page").expect("The first page has no link to the next one.");
+            let second_page = app.api_client
+                .get(format!("{}{}", &app.address, next_page))
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+
+            // Assert
+            let mut listed = listed_emails(&first_page);
+            listed.extend(listed_emails(&second_page));
+            listed.sort();
+            listed.dedup();
+            assert_eq!(listed.len(), 60, "Sorted by {} {}", sort, dir);
+        }
+    }
+}
+
+
+#[actix_rt::test]
+async fn invalid_criteria_are_reported() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+
+    // Act - Part 1 - Sort on an unknown column
+    let response = app.get_subscribers("?sort=password").await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), "/admin/subscribers");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_subscribers("").await.text().await.unwrap();
+    assert!(html_page.contains("<p><i>Subscribers cannot be sorted by password.</i></p>"));
+}
+
+
+#[actix_rt::test]
+async fn the_detail_page_shows_the_tokens_and_deliveries_of_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    import_sample(&app).await;
+    let listing = app.get_subscribers("?q=ada").await.text().await.unwrap();
+    let detail_page = link(&listing, "ada@example.com").unwrap();
+
+    // Act
+    let html_page = app.api_client
+        .get(format!("{}{}", &app.address, detail_page))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains("<h1>Ada</h1>"));
+    assert!(html_page.contains("<h2>Consent history</h2>"));
+    assert!(html_page.contains("<h2>Confirmation tokens</h2>"));
+    assert!(html_page.contains("<h2>Deliveries</h2>"));
+}
@@ -0,0 +1,225 @@
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::app::{spawn_app, TestApp};
+
+
+async fn add_subscriber(app: &TestApp, email: &str, confirmation: &str) -> reqwest::Response {
+    app.post_new_subscriber(&serde_json::json!({
+        "name": "Ada Lovelace",
+        "email": email,
+        "confirmation": confirmation,
+    })).await
+}
+
+async fn subscriber_id(app: &TestApp, email: &str) -> Uuid {
+    sqlx::query!("SELECT id FROM subscriptions WHERE email = $1", email)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch the subscriber.")
+        .id
+}
+
+async fn status(app: &TestApp, subscriber_id: Uuid) -> Option<String> {
+    sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions WHERE id = $1"#, subscriber_id)
+        .fetch_optional(&app.db_pool)
+        .await
+        .expect("Failed to fetch the subscriber.")
+        .map(|row| row.status)
+}
+
+async fn new_subscriber_page(app: &TestApp) -> String {
+    app.api_client
+        .get(format!("{}/admin/subscribers/new", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+
+#[actix_rt::test]
+async fn you_must_be_logged_in_to_manage_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let added = add_subscriber(&app, "ada@example.com", "none").await;
+    let bulk = app.post_bulk_action(&[
+        ("action", "delete"),
+        ("subscriber_id", &Uuid::new_v4().to_string()),
+        ("confirmed", "yes"),
+    ]).await;
+
+    // Assert
+    assert_eq!(added.status().as_u16(), 303);
+    assert_eq!(added.headers().get("Location").unwrap(), "/login");
+    assert_eq!(bulk.headers().get("Location").unwrap(), "/login");
+    let count = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(count, 0);
+}
+
+#[actix_rt::test]
+async fn admins_can_add_a_confirmed_subscriber_without_an_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = add_subscriber(&app, "ada@example.com", "none").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    let id = subscriber_id(&app, "ada@example.com").await;
+    assert_eq!(status(&app, id).await.unwrap(), "confirmed");
+    let consent = sqlx::query!("SELECT action, source FROM consent_events WHERE subscriber_id = $1", id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(consent.action, "admin");
+    assert_eq!(consent.source, "admin");
+}
+
+#[actix_rt::test]
+async fn admins_can_add_a_subscriber_who_must_confirm() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    add_subscriber(&app, "ada@example.com", "email").await;
+
+    // Assert
+    let id = subscriber_id(&app, "ada@example.com").await;
+    assert_eq!(status(&app, id).await.unwrap(), "pending_confirmation");
+}
+
+#[actix_rt::test]
+async fn an_address_already_subscribed_or_invalid_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    add_subscriber(&app, "ada@example.com", "none").await;
+
+    // Act - Part 1 - Duplicate
+    let duplicate = add_subscriber(&app, "ADA@example.com", "none").await;
+    assert_eq!(duplicate.headers().get("Location").unwrap(), "/admin/subscribers/new");
+    let html_page = new_subscriber_page(&app).await;
+    assert!(html_page.contains("Someone is already subscribed with this address."));
+
+    // Act - Part 2 - Invalid
+    let invalid = add_subscriber(&app, "not an address", "none").await;
+    assert_eq!(invalid.headers().get("Location").unwrap(), "/admin/subscribers/new");
+    let html_page = new_subscriber_page(&app).await;
+    assert!(html_page.contains("not an address is not a valid subscriber email."));
+}
+
+#[actix_rt::test]
+async fn admins_can_correct_the_name_and_email_of_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    add_subscriber(&app, "ada@example.com", "none").await;
+    add_subscriber(&app, "grace@example.com", "none").await;
+    let id = subscriber_id(&app, "ada@example.com").await;
+
+    // Act - Part 1 - Clash with another subscriber
+    let response = app.post_edit_subscriber(id, &serde_json::json!({
+        "name": "Ada",
+        "email": "grace@example.com",
+    })).await;
+    assert_eq!(response.headers().get("Location").unwrap(), format!("/admin/subscribers/{}/edit", id).as_str());
+
+    // Act - Part 2 - Correction
+    let response = app.post_edit_subscriber(id, &serde_json::json!({
+        "name": "Ada King",
+        "email": "ada.king@example.com",
+    })).await;
+
+    // Assert
+    assert_eq!(response.headers().get("Location").unwrap(), format!("/admin/subscribers/{}", id).as_str());
+    let saved = sqlx::query!("SELECT email, name FROM subscriptions WHERE id = $1", id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.email, "ada.king@example.com");
+    assert_eq!(saved.name, "Ada King");
+}
+
+#[actix_rt::test]
+async fn bulk_unsubscribing_asks_for_a_confirmation_first() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    add_subscriber(&app, "ada@example.com", "none").await;
+    add_subscriber(&app, "grace@example.com", "none").await;
+    let ada = subscriber_id(&app, "ada@example.com").await.to_string();
+    let grace = subscriber_id(&app, "grace@example.com").await;
+
+    // Act - Part 1 - Prompt
+    let response = app.post_bulk_action(&[("action", "unsubscribe"), ("subscriber_id", &ada)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("ada@example.com"));
+    assert!(html_page.contains(r#"name="confirmed" value="yes""#));
+    assert_eq!(status(&app, ada.parse().unwrap()).await.unwrap(), "confirmed");
+
+    // Act - Part 2 - Confirmation
+    let response = app.post_bulk_action(&[
+        ("action", "unsubscribe"),
+        ("subscriber_id", &ada),
+        ("confirmed", "yes"),
+    ]).await;
+
+    // Assert
+    assert_eq!(response.headers().get("Location").unwrap(), "/admin/subscribers");
+    assert_eq!(status(&app, ada.parse().unwrap()).await.unwrap(), "unsubscribed");
+    assert_eq!(status(&app, grace).await.unwrap(), "confirmed");
+    let html_page = app.get_subscribers("").await.text().await.unwrap();
+    assert!(html_page.contains("1 subscribers have been unsubscribed."));
+}
+
+#[actix_rt::test]
+async fn admins_can_delete_several_subscribers_at_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    add_subscriber(&app, "ada@example.com", "none").await;
+    add_subscriber(&app, "grace@example.com", "none").await;
+    add_subscriber(&app, "linus@example.com", "none").await;
+    let ada = subscriber_id(&app, "ada@example.com").await;
+    let grace = subscriber_id(&app, "grace@example.com").await;
+    let linus = subscriber_id(&app, "linus@example.com").await;
+
+    // Act
+    app.post_bulk_action(&[
+        ("action", "delete"),
+        ("subscriber_id", &ada.to_string()),
+        ("subscriber_id", &grace.to_string()),
+        ("confirmed", "yes"),
+    ]).await;
+
+    // Assert
+    assert!(status(&app, ada).await.is_none());
+    assert!(status(&app, grace).await.is_none());
+    assert_eq!(status(&app, linus).await.unwrap(), "confirmed");
+}
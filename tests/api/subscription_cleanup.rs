@@ -0,0 +1,54 @@
+use crate::helpers::app::spawn_app;
+use crate::helpers::email::{create_confirmed_subscriber, create_unconfirmed_subcriber};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::subscription_cleanup_worker::purge_stale_subscriptions;
+
+
+#[actix_rt::test]
+async fn expired_tokens_are_purged() {
+    // Arrange
+    let app = spawn_app().await;
+    create_unconfirmed_subcriber(&app).await;
+    sqlx::query!("UPDATE subscription_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let outcome = purge_stale_subscriptions(&app.db_pool, chrono::Duration::days(30)).await.unwrap();
+
+    // Assert
+    assert_eq!(outcome.expired_tokens, 1);
+    assert_eq!(outcome.unconfirmed_subscribers, 0);
+}
+
+
+#[actix_rt::test]
+async fn only_unconfirmed_subscribers_past_the_retention_are_purged() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscription("name=never%20confirmed&email=stale%40example.com".into()).await;
+    sqlx::query!("UPDATE subscriptions SET subscribed_at = now() - interval '31 days'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let outcome = purge_stale_subscriptions(&app.db_pool, chrono::Duration::days(30)).await.unwrap();
+
+    // Assert
+    assert_eq!(outcome.unconfirmed_subscribers, 1);
+    let remaining = sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions"#)
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].status, "confirmed");
+}
@@ -0,0 +1,100 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::app::{spawn_app, TestApp};
+use crate::helpers::email::{create_confirmed_subscriber, create_unconfirmed_subcriber};
+
+
+async fn status(app: &TestApp) -> String {
+    sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch the subscriber.")
+        .status
+}
+
+
+#[actix_rt::test]
+async fn signing_up_and_confirming_is_recorded_in_the_status_history() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    create_confirmed_subscriber(&app).await;
+
+    // Assert
+    let history = sqlx::query!(
+        r#"
+        SELECT from_status::text, to_status::text AS "to_status!", reason
+        FROM subscription_events
+        ORDER BY occurred_at
+        "#
+    )
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].from_status, None);
+    assert_eq!(history[0].to_status, "pending_confirmation");
+    assert_eq!(history[0].reason, "signup");
+    assert_eq!(history[1].from_status.as_deref(), Some("pending_confirmation"));
+    assert_eq!(history[1].to_status, "confirmed");
+    assert_eq!(history[1].reason, "confirmation");
+}
+
+#[actix_rt::test]
+async fn a_bounced_subscriber_signing_up_again_stays_off_the_list() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET status = 'bounced'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscription("name=le%20guin&email=ursulua_le_guin%40gmail.com".into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(status(&app).await, "bounced");
+}
+
+#[actix_rt::test]
+async fn a_confirmation_link_does_not_bring_back_someone_who_was_unsubscribed() {
+    // Arrange
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subcriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app.post_confirmation(&confirmation_links.html).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(status(&app).await, "unsubscribed");
+}
+
+#[actix_rt::test]
+async fn statuses_outside_the_lifecycle_are_rejected_by_the_database() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    // Act - not checked at compile time, since the value is invalid on purpose
+    let result = sqlx::query("UPDATE subscriptions SET status = 'active'")
+        .execute(&app.db_pool)
+        .await;
+
+    // Assert
+    assert!(result.is_err());
+}
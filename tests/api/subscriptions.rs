@@ -38,7 +38,7 @@ async fn subscribe_persists_new_subscriber() {
     app.post_subscription(body.into()).await;
 
     // Assert
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions",)
+    let saved = sqlx::query!(r#"SELECT email, name, status::text AS "status!" FROM subscriptions"#)
         .fetch_one(&app.db_pool)
         .await
         .expect("Failed to fetch saved subscription.");
@@ -49,6 +49,45 @@ async fn subscribe_persists_new_subscriber() {
 }
 
 
+#[actix_rt::test]
+async fn subscribe_persists_the_time_zone_of_a_new_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20example&email=name%40example.com&time_zone=Europe%2FBerlin";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscription(body.into()).await;
+
+    // Assert
+    let saved = sqlx::query!("SELECT time_zone FROM subscriptions",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+
+    assert_eq!(saved.time_zone.as_deref(), Some("Europe/Berlin"));
+}
+
+
+#[actix_rt::test]
+async fn subscribe_returns_a_400_for_an_unknown_time_zone() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20example&email=name%40example.com&time_zone=Mars%2FOlympus_Mons";
+
+    // Act
+    let response = app.post_subscription(body.into()).await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
+
+
 
 #[actix_rt::test]
 async fn subscribe_returns_a_400_when_data_is_missing() {
@@ -121,8 +160,8 @@ async fn subscribe_sends_a_confirmation_email_for_valid_data() {
             links[0].as_str().to_owned()
     };
 
-    let html_link = get_links(&body["HtmlBody"].as_str().unwrap());
-    let text_link = get_links(&body["TextBody"].as_str().unwrap());
+    let html_link = get_links(body["HtmlBody"].as_str().unwrap());
+    let text_link = get_links(body["TextBody"].as_str().unwrap());
 
     assert_eq!(html_link, text_link);
 }
@@ -145,7 +184,7 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
 
     // Assert
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
-    let confirmation_link = app.get_confirmation_links(&email_request);
+    let confirmation_link = app.get_confirmation_links(email_request);
 
     // The two links should be identical
     assert_eq!(confirmation_link.html, confirmation_link.plain_text);
@@ -165,4 +204,166 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     let response = app.post_subscription(body.into()).await;
 
     assert_eq!(response.status().as_u16(), 500);
-}
\ No newline at end of file
+}
+
+#[actix_rt::test]
+async fn subscribing_twice_while_pending_resends_the_same_confirmation_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let first = app.post_subscription(body.into()).await;
+    let second = app.post_subscription(body.into()).await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 200);
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let first_links = app.get_confirmation_links(&requests[0]);
+    let second_links = app.get_confirmation_links(&requests[1]);
+    assert_eq!(first_links.html, second_links.html);
+}
+
+
+#[actix_rt::test]
+async fn subscribing_a_confirmed_email_returns_200_without_sending_an_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscription(body.into()).await;
+    sqlx::query!("UPDATE subscriptions SET status = 'confirmed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app.post_subscription(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+
+#[actix_rt::test]
+async fn unsubscribed_emails_go_through_double_opt_in_again() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscription(body.into()).await;
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app.post_subscription(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+
+#[actix_rt::test]
+async fn the_same_address_in_a_different_case_is_the_same_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscription("name=bob&email=Bob%40Example.com".into()).await;
+    let response = app.post_subscription("name=bob&email=%20bob%40example.COM%20".into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT email, normalized_email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].email, "Bob@Example.com");
+    assert_eq!(saved[0].normalized_email, "Bob@example.com");
+}
+
+
+#[actix_rt::test]
+async fn concurrent_signups_with_the_same_address_store_a_single_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (first, second) = tokio::join!(
+        app.post_subscription(body.into()),
+        app.post_subscription(body.into()),
+    );
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 1);
+}
+
+
+#[actix_rt::test]
+async fn subscribe_returns_a_400_for_addresses_rejected_by_the_signup_policy() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        ("name=Ursula&email=ursula%40mailinator.com", "a disposable domain", "disposable email provider"),
+        ("name=Ursula&email=postmaster%40domain.com", "a role address", "role address"),
+    ];
+
+    for (body, description, message) in test_cases {
+        // Act
+        let response = app.post_subscription(body.to_string()).await;
+
+        // Assert
+        assert_eq!(400, response.status().as_u16(), "The API did not return a 400 Bad Request for {}.", description);
+        assert!(response.text().await.unwrap().contains(message), "Unexpected error message for {}.", description);
+    }
+}
@@ -3,6 +3,7 @@ use wiremock::{ResponseTemplate, Mock};
 use wiremock::matchers::{path, method};
 
 use crate::helpers::app::spawn_app;
+use crate::helpers::email::create_unconfirmed_subcriber;
 
 #[actix_rt::test]
 async fn confirmation_without_token_are_rejected_with_a_400() {
@@ -42,7 +43,7 @@ async fn the_link_returned_by_subscribe_returns_a_200_if_called() {
         links[0].as_str().to_owned()
     };
 
-    let raw_confirmation_link = &get_link(&body["HtmlBody"].as_str().unwrap());
+    let raw_confirmation_link = &get_link(body["HtmlBody"].as_str().unwrap());
     let mut confirmation_link = Url::parse(raw_confirmation_link).unwrap();
     assert_eq!(confirmation_link.host_str().unwrap(), "127.0.0.1");
 
@@ -68,7 +69,7 @@ async fn the_link_returned_by_subscrive_returns_a_200_if_called() {
 
     app.post_subscription(body.into()).await;
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
-    let confirmation_links = app.get_confirmation_links(&email_request);
+    let confirmation_links = app.get_confirmation_links(email_request);
 
     let response = reqwest::get(confirmation_links.html)
         .await.unwrap();
@@ -91,9 +92,9 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
 
     app.post_subscription(body.into()).await;
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
-    let confirmation_links = app.get_confirmation_links(&email_request);
+    let confirmation_links = app.get_confirmation_links(email_request);
 
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions",)
+    let saved = sqlx::query!(r#"SELECT email, name, status::text AS "status!" FROM subscriptions"#)
         .fetch_one(&app.db_pool)
         .await.expect("Failed to fetch saved subscription");
 
@@ -101,14 +102,91 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     assert_eq!(saved.status, "pending_confirmation");
     assert_eq!(saved.name, "le guin");
 
-    reqwest::get(confirmation_links.html)
-        .await.unwrap().error_for_status().unwrap();
+    app.post_confirmation(&confirmation_links.html)
+        .await.error_for_status().unwrap();
 
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions",)
+    let saved = sqlx::query!(r#"SELECT email, name, status::text AS "status!" FROM subscriptions"#)
         .fetch_one(&app.db_pool)
         .await.expect("Failed to fetch saved subscription");
 
     assert_eq!(saved.email, "ursula_le_guin@gmail.com");
     assert_eq!(saved.status, "confirmed");
     assert_eq!(saved.name, "le guin");
-}
\ No newline at end of file
+}
+
+#[actix_rt::test]
+async fn an_expired_confirmation_link_offers_to_send_a_new_one() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subcriber(&app).await;
+    sqlx::query!("UPDATE subscription_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 410);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(r#"<form action="/subscriptions/resend" method="post">"#));
+
+    let saved = sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+
+#[actix_rt::test]
+async fn following_the_confirmation_link_does_not_confirm_the_subscriber() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subcriber(&app).await;
+
+    // What a mail scanner prefetching the link does
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Confirm my subscription"));
+
+    let saved = sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+
+#[actix_rt::test]
+async fn confirming_twice_is_harmless() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subcriber(&app).await;
+
+    let first = app.post_confirmation(&confirmation_links.html).await;
+    let second = app.post_confirmation(&confirmation_links.html).await;
+
+    assert_eq!(first.status().as_u16(), 200);
+    assert!(first.text().await.unwrap().contains("your subscription is confirmed"));
+    assert_eq!(second.status().as_u16(), 200);
+    assert!(second.text().await.unwrap().contains("already confirmed"));
+
+    let tokens = sqlx::query!("SELECT consumed_at FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(tokens.consumed_at.is_some());
+}
+
+
+#[actix_rt::test]
+async fn an_unknown_token_renders_the_invalid_link_page() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(&format!(
+        "{}/subscriptions/confirm?subscription_token=not-a-real-token",
+        app.address,
+    )).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(response.text().await.unwrap().contains("This confirmation link is not valid."));
+}
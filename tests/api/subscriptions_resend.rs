@@ -0,0 +1,111 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::app::spawn_app;
+use crate::helpers::email::{create_confirmed_subscriber, create_unconfirmed_subcriber};
+
+// The address used by `create_unconfirmed_subcriber`
+const EMAIL: &str = "ursulua_le_guin@gmail.com";
+
+
+#[actix_rt::test]
+async fn a_pending_subscriber_gets_a_new_confirmation_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let first_links = create_unconfirmed_subcriber(&app).await;
+    // Step outside of the cooldown
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '1 hour'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_resend_confirmation(EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let email_request = app.email_server.received_requests().await.unwrap().pop().unwrap();
+    let new_links = app.get_confirmation_links(&email_request);
+    assert_ne!(first_links.html, new_links.html);
+
+    app.post_confirmation(&new_links.html).await.error_for_status().unwrap();
+}
+
+
+#[actix_rt::test]
+async fn resending_within_the_cooldown_sends_nothing() {
+    // Arrange
+    let app = spawn_app().await;
+    create_unconfirmed_subcriber(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_resend_confirmation(EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+
+#[actix_rt::test]
+async fn the_response_does_not_reveal_whether_an_address_is_on_the_list() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let confirmed = app.post_resend_confirmation(EMAIL).await;
+    let unknown = app.post_resend_confirmation("nobody@example.com").await;
+
+    // Assert
+    assert_eq!(confirmed.status().as_u16(), 200);
+    assert_eq!(unknown.status().as_u16(), 200);
+    assert_eq!(confirmed.text().await.unwrap(), unknown.text().await.unwrap());
+}
+
+
+#[actix_rt::test]
+async fn a_failed_send_gets_the_same_response() {
+    // Arrange
+    let app = spawn_app().await;
+    create_unconfirmed_subcriber(&app).await;
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '1 hour'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let pending = app.post_resend_confirmation(EMAIL).await;
+    let unknown = app.post_resend_confirmation("nobody@example.com").await;
+
+    // Assert
+    assert_eq!(pending.status().as_u16(), 200);
+    assert_eq!(pending.text().await.unwrap(), unknown.text().await.unwrap());
+}
@@ -0,0 +1,139 @@
+
+use crate::helpers::app::{spawn_app, TestApp};
+use crate::helpers::email::create_unconfirmed_subcriber;
+
+
+async fn enable_welcome_email(app: &TestApp, include_latest_issue: bool) {
+    let mut form = serde_json::json!({
+        "enabled": "on",
+        "subject": "Welcome, {{ name | reader }}!",
+        "text_content": "Hi {{ name }}, glad to have you.",
+        "html_content": "<p>Hi {{ name }}, glad to have you.</p>",
+    });
+    if include_latest_issue {
+        form["include_latest_issue"] = "on".into();
+    }
+    let response = app.post_welcome_email(&form).await;
+    assert_eq!(response.status().as_u16(), 303);
+}
+
+/// The bodies of the emails sent so far, in order.
+async fn sent_emails(app: &TestApp) -> Vec<serde_json::Value> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect()
+}
+
+
+#[actix_rt::test]
+async fn you_must_be_logged_in_to_edit_the_welcome_email() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_welcome_email(&serde_json::json!({
+        "enabled": "on",
+        "subject": "Welcome",
+        "text_content": "Hi",
+        "html_content": "<p>Hi</p>",
+    })).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), "/login");
+    let enabled = sqlx::query!("SELECT enabled FROM welcome_email")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .enabled;
+    assert!(!enabled);
+}
+
+#[actix_rt::test]
+async fn no_welcome_email_is_sent_while_it_is_disabled() {
+    // Arrange
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subcriber(&app).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+
+    // Assert - only the confirmation email went out
+    assert_eq!(sent_emails(&app).await.len(), 1);
+}
+
+#[actix_rt::test]
+async fn confirming_sends_the_welcome_email_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    enable_welcome_email(&app, false).await;
+    let confirmation_links = create_unconfirmed_subcriber(&app).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+
+    // Assert
+    let emails = sent_emails(&app).await;
+    assert_eq!(emails.len(), 2);
+    let welcome = &emails[1];
+    assert_eq!(welcome["Subject"], "Welcome, le guin!");
+    assert!(welcome["TextBody"].as_str().unwrap().starts_with("Hi le guin, glad to have you."));
+    assert!(welcome["HtmlBody"].as_str().unwrap().contains("/subscriptions/preferences?token="));
+    let welcome_sent = sqlx::query!("SELECT welcome_sent_at FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .welcome_sent_at;
+    assert!(welcome_sent.is_some());
+}
+
+#[actix_rt::test]
+async fn the_welcome_email_can_include_the_latest_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+    enable_welcome_email(&app, true).await;
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })).await;
+    let confirmation_links = create_unconfirmed_subcriber(&app).await;
+    app.mount_ok_email_server().await;
+
+    // Act
+    app.post_confirmation(&confirmation_links.html).await.error_for_status().unwrap();
+
+    // Assert
+    let emails = sent_emails(&app).await;
+    let welcome = emails.last().unwrap();
+    assert!(welcome["TextBody"].as_str().unwrap().contains("Newsletter body as plain text"));
+    assert!(welcome["HtmlBody"].as_str().unwrap().contains("<h2>Newsletter title</h2><p>Newsletter body as HTML</p>"));
+}
+
+#[actix_rt::test]
+async fn a_welcome_email_without_a_subject_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_as_admin().await;
+
+    // Act
+    app.post_welcome_email(&serde_json::json!({
+        "enabled": "on",
+        "subject": "",
+        "text_content": "Hi",
+        "html_content": "<p>Hi</p>",
+    })).await;
+
+    // Assert
+    let html_page = app.get_welcome_email().await.text().await.unwrap();
+    assert!(html_page.contains("The welcome email needs a subject."));
+}